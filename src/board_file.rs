@@ -0,0 +1,498 @@
+// A plain text format for hand-authored boards, played with `--board <file>`. A board file looks
+// like this:
+//
+//     # lines starting with '#' are comments
+//     time = 15
+//     max_revealed = 6
+//
+//     |>>v<<|
+//     |>~*< |
+//     |>>^<~|
+//
+// `time` is how many seconds the player gets and `max_revealed` is how many cells can be revealed
+// at once. Both are optional and default to level 1's settings.
+//
+// Boards in a campaign pack (see campaign.rs) can also set what it takes to earn every star with
+// `star_time = <SECS>`, the time that has to be left, and `star_reveals = <N>`, the most reveals the
+// board can be won in. Both are optional and get worked out from the board when they're missing.
+//
+// Every row of the grid sits between a pair of '|' so trailing empty cells survive editors that
+// strip whitespace. Each cell is one glyph:
+//
+//     < > ^ v   a hint pointing left, right, up or down
+//     *         the solution. There has to be exactly one
+//     ~         a confusion trap
+//     (space)   an empty cell
+//
+// Hints are taken as written and don't have to point at the solution, so a board can lie.
+//
+// Treasure hunt boards (--treasure) are always rolled fresh and can't be written as board files.
+
+use crate::game::{hint_direction, CellDistribution, GameGrid, GridItem, HintDir, TrapType};
+use crate::LevelConfig;
+use std::path::Path;
+use std::time::Duration;
+
+const DEFAULT_BOARD_TIME: Duration = Duration::from_secs(15);
+const DEFAULT_MAX_REVEALED_CELLS: usize = 6;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Board {
+    pub width: i32,
+    pub height: i32,
+    pub board_time: Duration,
+    pub max_revealed_cells: usize,
+    // the star thresholds for campaign boards
+    pub star_time: Option<Duration>,
+    pub star_reveals: Option<usize>,
+    // row by row, starting from the top left
+    pub items: Vec<GridItem>,
+}
+
+impl Board {
+    // A board with the solution in the middle and every other cell a hint pointing at it
+    pub fn new(width: i32, height: i32) -> Self {
+        let mut board = Board {
+            width,
+            height,
+            board_time: DEFAULT_BOARD_TIME,
+            max_revealed_cells: DEFAULT_MAX_REVEALED_CELLS,
+            star_time: None,
+            star_reveals: None,
+            items: vec![GridItem::Hint(HintDir::Left); (width * height) as usize],
+        };
+        board.set(width / 2, height / 2, GridItem::Solution);
+        board.point_hints_at_solution();
+        board
+    }
+
+    pub fn get(&self, x: i32, y: i32) -> Option<GridItem> {
+        if x < 0 || x >= self.width || y < 0 || y >= self.height {
+            return None;
+        }
+
+        Some(self.items[(self.width * y + x) as usize])
+    }
+
+    // Puts `item` in the cell at (x, y). Placing the solution moves it, leaving an empty cell
+    // where it used to be.
+    pub fn set(&mut self, x: i32, y: i32, item: GridItem) {
+        if self.get(x, y).is_none() {
+            return;
+        }
+
+        if item == GridItem::Solution {
+            for old_item in self.items.iter_mut() {
+                if *old_item == GridItem::Solution {
+                    *old_item = GridItem::Empty;
+                }
+            }
+        }
+
+        self.items[(self.width * y + x) as usize] = item;
+    }
+
+    pub fn find_solution(&self) -> Option<(i32, i32)> {
+        let index = self
+            .items
+            .iter()
+            .position(|item| *item == GridItem::Solution)?;
+        Some((index as i32 % self.width, index as i32 / self.width))
+    }
+
+    // Turns every hint around to point at wherever the solution is now
+    pub fn point_hints_at_solution(&mut self) {
+        let (solution_x, solution_y) = match self.find_solution() {
+            Some(solution) => solution,
+            None => return,
+        };
+
+        for (index, item) in self.items.iter_mut().enumerate() {
+            if let GridItem::Hint(_) = item {
+                let x = index as i32 % self.width;
+                let y = index as i32 / self.width;
+                *item = GridItem::Hint(hint_direction(x - solution_x, y - solution_y));
+            }
+        }
+    }
+
+    pub fn from_grid(grid: &GameGrid, board_time: Duration) -> Self {
+        let items = (0..grid.height())
+            .flat_map(|y| (0..grid.width()).map(move |x| (x, y)))
+            .map(|(x, y)| grid.cell(x, y).unwrap().item)
+            .collect();
+
+        Board {
+            width: grid.width(),
+            height: grid.height(),
+            board_time,
+            max_revealed_cells: grid.max_revealed_cells(),
+            star_time: None,
+            star_reveals: None,
+            items,
+        }
+    }
+
+    pub fn to_grid(&self, reveal_time: Duration) -> GameGrid {
+        GameGrid::from_items(
+            self.width,
+            self.height,
+            self.items.clone(),
+            self.max_revealed_cells,
+            reveal_time,
+        )
+    }
+
+    // The config a level playing this board runs with. Nothing was randomly generated so the
+    // distribution and difficulty band are only there for show.
+    pub fn level_config(&self) -> LevelConfig {
+        LevelConfig {
+            grid_size: (self.width, self.height),
+            board_time: self.board_time,
+            max_revealed_cells: self.max_revealed_cells,
+            cell_distribution: CellDistribution::default(),
+            difficulty_band: (0.0, f64::INFINITY),
+        }
+    }
+}
+
+pub fn load(path: &Path) -> Result<Board, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn parse(text: &str) -> Result<Board, String> {
+    let mut board_time = DEFAULT_BOARD_TIME;
+    let mut max_revealed_cells = DEFAULT_MAX_REVEALED_CELLS;
+    let mut star_time = None;
+    let mut star_reveals = None;
+    let mut width = None;
+    let mut items = vec![];
+    let mut solution_found = false;
+
+    for (line_index, line) in text.lines().enumerate() {
+        // columns count characters from 1, like most editors
+        let error_at =
+            |column: usize, e: String| format!("line {}, column {}: {}", line_index + 1, column, e);
+
+        let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if !trimmed.starts_with('|') {
+            let (key, value) = match trimmed.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => {
+                    return Err(error_at(
+                        indent + 1,
+                        format!(
+                            "expected <key> = <value> or a row of the grid, got '{}'",
+                            trimmed
+                        ),
+                    ))
+                }
+            };
+
+            let value_column = line.find('=').map(|equals| {
+                let value_start = &line[equals + 1..];
+                line[..equals].chars().count()
+                    + 2
+                    + value_start
+                        .chars()
+                        .take_while(|c| c.is_whitespace())
+                        .count()
+            });
+            let value_error = |e: String| error_at(value_column.unwrap(), e);
+
+            match key {
+                "time" => board_time = crate::config::parse_seconds(value).map_err(value_error)?,
+                "max_revealed" => max_revealed_cells = parse_count(value).map_err(value_error)?,
+                "star_time" => {
+                    star_time = Some(crate::config::parse_seconds(value).map_err(value_error)?)
+                }
+                "star_reveals" => star_reveals = Some(parse_count(value).map_err(value_error)?),
+                _ => {
+                    return Err(error_at(
+                        indent + 1,
+                        format!("unrecognized setting '{}'", key),
+                    ))
+                }
+            }
+            continue;
+        }
+
+        // a row of the grid. The cells are everything between the first '|' and the last one.
+        let row: Vec<char> = line.trim_end().chars().collect();
+        let row_end = row.len() - 1;
+        if row_end == indent || row[row_end] != '|' {
+            return Err(error_at(
+                row.len() + 1,
+                String::from("expected a '|' to end the row"),
+            ));
+        }
+
+        let cells = &row[indent + 1..row_end];
+        match width {
+            None => width = Some(cells.len()),
+            Some(width) if width != cells.len() => {
+                return Err(error_at(
+                    row_end + 1,
+                    format!(
+                        "row is {} cells wide but the rows above it are {} wide",
+                        cells.len(),
+                        width
+                    ),
+                ))
+            }
+            _ => (),
+        }
+
+        for (cell_index, glyph) in cells.iter().enumerate() {
+            let column = indent + cell_index + 2;
+            let item = parse_glyph(*glyph).ok_or_else(|| {
+                error_at(
+                    column,
+                    format!(
+                        "unknown cell '{}' (expected one of < > ^ v * ~ or a space)",
+                        glyph
+                    ),
+                )
+            })?;
+
+            if item == GridItem::Solution {
+                if solution_found {
+                    return Err(error_at(
+                        column,
+                        String::from("a board can only have one solution"),
+                    ));
+                }
+                solution_found = true;
+            }
+
+            items.push(item);
+        }
+    }
+
+    let width = match width {
+        Some(width) if width > 0 => width as i32,
+        _ => return Err(String::from("the board has no grid")),
+    };
+
+    if !solution_found {
+        return Err(String::from("the board has no solution ('*')"));
+    }
+
+    Ok(Board {
+        width,
+        height: items.len() as i32 / width,
+        board_time,
+        max_revealed_cells,
+        star_time,
+        star_reveals,
+        items,
+    })
+}
+
+// Writes out the board in the same format `parse` reads.
+pub fn to_text(board: &Board) -> String {
+    let mut text = format!(
+        "time = {}\nmax_revealed = {}\n",
+        board.board_time.as_secs_f64(),
+        board.max_revealed_cells
+    );
+    if let Some(star_time) = board.star_time {
+        text += &format!("star_time = {}\n", star_time.as_secs_f64());
+    }
+    if let Some(star_reveals) = board.star_reveals {
+        text += &format!("star_reveals = {}\n", star_reveals);
+    }
+    text.push('\n');
+
+    for row in board.items.chunks(board.width as usize) {
+        text.push('|');
+        text.extend(row.iter().map(|item| glyph(*item)));
+        text += "|\n";
+    }
+
+    text
+}
+
+pub fn save(path: &Path, board: &Board) -> Result<(), String> {
+    std::fs::write(path, to_text(board))
+        .map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+// Writes out any grid as a board file, i.e. to replay a randomly generated board later
+pub fn export(grid: &GameGrid, board_time: Duration) -> String {
+    to_text(&Board::from_grid(grid, board_time))
+}
+
+fn parse_count(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(count) if count > 0 => Ok(count),
+        _ => Err(format!(
+            "invalid count '{}' (expected a positive number)",
+            value
+        )),
+    }
+}
+
+fn parse_glyph(glyph: char) -> Option<GridItem> {
+    match glyph {
+        '<' => Some(GridItem::Hint(HintDir::Left)),
+        '>' => Some(GridItem::Hint(HintDir::Right)),
+        '^' => Some(GridItem::Hint(HintDir::Up)),
+        'v' => Some(GridItem::Hint(HintDir::Down)),
+        '*' => Some(GridItem::Solution),
+        '~' => Some(GridItem::Trap(TrapType::Confusion)),
+        ' ' => Some(GridItem::Empty),
+        _ => None,
+    }
+}
+
+pub fn glyph(item: GridItem) -> char {
+    match item {
+        GridItem::Hint(HintDir::Left) => '<',
+        GridItem::Hint(HintDir::Right) => '>',
+        GridItem::Hint(HintDir::Up) => '^',
+        GridItem::Hint(HintDir::Down) => 'v',
+        GridItem::Solution => '*',
+        GridItem::Trap(TrapType::Confusion) => '~',
+        // treasure hunt items never end up on a board (see the top of the file), but if one did
+        // it'd be as a plain hint or an empty cell
+        GridItem::ValueHint(hint_dir) => glyph(GridItem::Hint(hint_dir)),
+        GridItem::Treasure(_) => ' ',
+        GridItem::Empty => ' ',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::DEFAULT_REVEAL_TIME;
+    use crate::utils::SeededRng;
+
+    const EXAMPLE: &str = "\
+# a tiny board
+time = 12
+max_revealed = 4
+
+|>>v<<|
+|>~*< |
+|>>^<~|
+";
+
+    #[test]
+    fn test_parse() {
+        let board = parse(EXAMPLE).unwrap();
+        assert_eq!((5, 3), (board.width, board.height));
+        assert_eq!(Duration::from_secs(12), board.board_time);
+        assert_eq!(4, board.max_revealed_cells);
+
+        let grid = board.to_grid(DEFAULT_REVEAL_TIME);
+        assert_eq!(Some((2, 1)), grid.find_solution());
+        assert_eq!(
+            GridItem::Trap(TrapType::Confusion),
+            grid.cell(1, 1).unwrap().item
+        );
+        assert_eq!(GridItem::Empty, grid.cell(4, 1).unwrap().item);
+        assert_eq!(GridItem::Hint(HintDir::Up), grid.cell(2, 2).unwrap().item);
+
+        // everything but the grid is optional
+        let board = parse("|*|").unwrap();
+        assert_eq!(DEFAULT_BOARD_TIME, board.board_time);
+        assert_eq!(DEFAULT_MAX_REVEALED_CELLS, board.max_revealed_cells);
+        assert_eq!((None, None), (board.star_time, board.star_reveals));
+    }
+
+    #[test]
+    fn test_star_thresholds() {
+        let board = parse("star_time = 7.5\nstar_reveals = 3\n|*<|").unwrap();
+        assert_eq!(Some(Duration::from_millis(7500)), board.star_time);
+        assert_eq!(Some(3), board.star_reveals);
+        assert_eq!(board, parse(&to_text(&board)).unwrap());
+
+        assert_eq!(
+            "line 1, column 16: invalid count '0' (expected a positive number)",
+            parse("star_reveals = 0\n|*|").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |text: &str| parse(text).unwrap_err();
+
+        assert_eq!(
+            "line 2, column 3: unknown cell 'x' (expected one of < > ^ v * ~ or a space)",
+            error("|*<|\n|>x|")
+        );
+        assert_eq!(
+            "line 2, column 3: expected a '|' to end the row",
+            error("|* |\n|> ")
+        );
+        assert_eq!(
+            "line 3, column 5: row is 3 cells wide but the rows above it are 2 wide",
+            error("# hi\n|*<|\n|<<<|")
+        );
+        assert_eq!(
+            "line 1, column 4: a board can only have one solution",
+            error("|*<*|")
+        );
+        assert_eq!(
+            "line 1, column 8: invalid time 'soon' (expected a positive number of seconds)",
+            error("time = soon\n|*|")
+        );
+        assert_eq!(
+            "line 1, column 1: unrecognized setting 'size'",
+            error("size = 3\n|*|")
+        );
+        assert_eq!("the board has no solution ('*')", error("|<<|"));
+        assert_eq!("the board has no grid", error("time = 3"));
+        assert_eq!("the board has no grid", error("||"));
+    }
+
+    #[test]
+    fn test_editing() {
+        let mut board = Board::new(5, 3);
+        assert_eq!(Some((2, 1)), board.find_solution());
+        assert_eq!(Some(GridItem::Hint(HintDir::Right)), board.get(0, 1));
+        assert_eq!(None, board.get(5, 0));
+
+        // moving the solution leaves an empty cell behind and the hints can be turned to match
+        board.set(4, 2, GridItem::Solution);
+        assert_eq!(Some((4, 2)), board.find_solution());
+        assert_eq!(Some(GridItem::Empty), board.get(2, 1));
+        assert_eq!(Some(GridItem::Hint(HintDir::Left)), board.get(3, 1));
+
+        board.set(0, 0, GridItem::Trap(TrapType::Confusion));
+        board.point_hints_at_solution();
+        assert_eq!(Some(GridItem::Hint(HintDir::Down)), board.get(3, 1));
+        assert_eq!(Some(GridItem::Trap(TrapType::Confusion)), board.get(0, 0));
+        assert_eq!(board, parse(&to_text(&board)).unwrap());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let board = parse(EXAMPLE).unwrap();
+        assert_eq!(board, parse(&to_text(&board)).unwrap());
+
+        for seed in 0..10 {
+            let grid = GameGrid::new(
+                12,
+                7,
+                5,
+                DEFAULT_REVEAL_TIME,
+                CellDistribution::default(),
+                &mut SeededRng::new(seed),
+            );
+
+            let text = export(&grid, Duration::from_millis(9500));
+            let board = parse(&text).unwrap();
+            assert_eq!(Board::from_grid(&grid, Duration::from_millis(9500)), board);
+            assert_eq!(text, to_text(&board));
+        }
+    }
+}
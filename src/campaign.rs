@@ -0,0 +1,705 @@
+// Campaign mode, started with `lnf campaign`. A campaign is made of packs: ordered lists of
+// hand-authored boards that get harder as they go. Winning a board earns up to three stars:
+//
+//     *     for winning at all
+//     **    for winning with at least `star_time` left on the clock
+//     ***   for also winning in `star_reveals` reveals or fewer
+//
+// and earning a star on a board unlocks the next one in its pack.
+//
+// A pack is a text file with a header, then each board (in the board file format, see
+// board_file.rs) after a line of `---`, i.e.
+//
+//     # comments are fine anywhere
+//     name = First Steps
+//
+//     ---
+//     time = 15
+//     star_time = 8
+//     |>>*<<|
+//     ---
+//     |v<<<<|
+//     |*<<<<|
+//
+// The packs that come with lnf are in the packs/ directory and get built into the binary. More can
+// be added by dropping them into the packs/ directory next to the config file, i.e.
+// ~/.config/lnf/packs/my-pack.txt. The best number of stars earned on every board is kept in the
+// progress file in the same place.
+
+use crate::board_file::{self, Board};
+use crate::game::DEFAULT_REVEAL_TIME;
+use crate::render::Surface;
+use crate::theme::Style;
+use crate::{cli, config, difficulty, layout, term};
+use crate::{GameResult, InputUpdate, LevelOutcome, Pilot};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// (id, text) for every pack that's built in, in the order they're shown
+const BUNDLED_PACKS: [(&str, &str); 2] = [
+    ("first-steps", include_str!("../packs/first-steps.txt")),
+    ("twists", include_str!("../packs/twists.txt")),
+];
+
+const MAX_STARS: u8 = 3;
+
+// Boards without their own `star_reveals` give this many reveals on top of what the solver needs
+const SPARE_REVEALS: usize = 2;
+
+// where everything on the level select screen goes
+const PACKS_TOP: i32 = 3;
+const PACKS_LEFT: i32 = 2;
+const PACK_NAME_WIDTH: i32 = 20;
+const BOARD_CELL_WIDTH: i32 = 6;
+
+const HELP_TEXT: &str = "up/down: pack   left/right: board   enter/click: play   q: quit";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pack {
+    // what the pack's progress is saved under. The file name for packs that aren't built in.
+    pub id: String,
+    pub name: String,
+    pub boards: Vec<PackBoard>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackBoard {
+    pub board: Board,
+    // what it takes to get the second and third stars
+    pub star_time: Duration,
+    pub star_reveals: usize,
+}
+
+impl PackBoard {
+    // fills in any star thresholds the board didn't set itself
+    fn new(board: Board) -> Self {
+        let star_time = board.star_time.unwrap_or(board.board_time / 3);
+        let star_reveals = board.star_reveals.unwrap_or_else(|| {
+            difficulty::analyze(&board.to_grid(DEFAULT_REVEAL_TIME)).reveals + SPARE_REVEALS
+        });
+
+        PackBoard {
+            board,
+            star_time,
+            star_reveals,
+        }
+    }
+
+    pub fn stars(&self, outcome: &LevelOutcome) -> u8 {
+        if outcome.result != GameResult::Win {
+            return 0;
+        }
+
+        let mut stars = 1;
+        if outcome.time_left >= self.star_time {
+            stars += 1;
+            if outcome.reveals <= self.star_reveals {
+                stars += 1;
+            }
+        }
+        stars
+    }
+}
+
+// the most stars earned on every board that's been won, by pack id and board index
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Progress {
+    stars: BTreeMap<(String, usize), u8>,
+}
+
+impl Progress {
+    pub fn stars(&self, pack_id: &str, board_index: usize) -> u8 {
+        self.stars
+            .get(&(pack_id.to_string(), board_index))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // Keeps `stars` if it beats the best so far. Returns whether it did.
+    pub fn record(&mut self, pack_id: &str, board_index: usize, stars: u8) -> bool {
+        if stars <= self.stars(pack_id, board_index) {
+            return false;
+        }
+
+        self.stars.insert((pack_id.to_string(), board_index), stars);
+        true
+    }
+
+    pub fn pack_stars(&self, pack: &Pack) -> u32 {
+        (0..pack.boards.len())
+            .map(|board_index| self.stars(&pack.id, board_index) as u32)
+            .sum()
+    }
+
+    // The first board of a pack is always open. Every other one needs a star on the board before it.
+    pub fn is_unlocked(&self, pack: &Pack, board_index: usize) -> bool {
+        board_index == 0 || self.stars(&pack.id, board_index - 1) > 0
+    }
+}
+
+pub struct Campaign {
+    packs: Vec<Pack>,
+    progress: Progress,
+}
+
+// Loads every pack and the progress made on them so far.
+pub fn open() -> Result<Campaign, String> {
+    Ok(Campaign {
+        packs: load_packs()?,
+        progress: load_progress()?,
+    })
+}
+
+pub fn load_packs() -> Result<Vec<Pack>, String> {
+    let mut packs: Vec<Pack> = BUNDLED_PACKS
+        .iter()
+        .map(|(id, text)| parse_pack(id, text).expect("bundled packs are tested"))
+        .collect();
+
+    let packs_dir = match config::dir() {
+        Some(dir) => dir.join("packs"),
+        None => return Ok(packs),
+    };
+
+    let entries = match std::fs::read_dir(&packs_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(packs),
+        Err(e) => return Err(format!("failed to read {}: {}", packs_dir.display(), e)),
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let id = path.file_stem().unwrap().to_string_lossy().into_owned();
+        if packs.iter().any(|pack| pack.id == id) {
+            return Err(format!(
+                "{}: there's already a pack called '{}'",
+                path.display(),
+                id
+            ));
+        }
+
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        packs.push(parse_pack(&id, &text).map_err(|e| format!("{}: {}", path.display(), e))?);
+    }
+
+    Ok(packs)
+}
+
+pub fn parse_pack(id: &str, text: &str) -> Result<Pack, String> {
+    let mut name = None;
+    let mut board_texts: Vec<String> = vec![];
+
+    for (line_index, line) in text.lines().enumerate() {
+        if line.trim() == "---" {
+            // pad each board with the lines above it so errors point at the right line of the pack
+            board_texts.push("\n".repeat(line_index + 1));
+            continue;
+        }
+
+        if let Some(board_text) = board_texts.last_mut() {
+            *board_text += line;
+            board_text.push('\n');
+            continue;
+        }
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let line_error = |e: String| format!("line {}: {}", line_index + 1, e);
+        match trimmed.split_once('=') {
+            Some((key, value)) if key.trim() == "name" => name = Some(value.trim().to_string()),
+            Some((key, _)) => {
+                return Err(line_error(format!("unrecognized setting '{}'", key.trim())))
+            }
+            None => {
+                return Err(line_error(format!(
+                    "expected <key> = <value> or '---', got '{}'",
+                    trimmed
+                )))
+            }
+        }
+    }
+
+    let name = name.ok_or_else(|| String::from("the pack has no name"))?;
+    if board_texts.is_empty() {
+        return Err(String::from("the pack has no boards"));
+    }
+
+    let boards = board_texts
+        .iter()
+        .enumerate()
+        .map(|(board_index, board_text)| {
+            board_file::parse(board_text)
+                .map(PackBoard::new)
+                .map_err(|e| format!("board {}: {}", board_index + 1, e))
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(Pack {
+        id: id.to_string(),
+        name,
+        boards,
+    })
+}
+
+fn progress_path() -> Option<PathBuf> {
+    config::dir().map(|dir| dir.join("progress"))
+}
+
+pub fn load_progress() -> Result<Progress, String> {
+    let path = match progress_path() {
+        Some(path) => path,
+        None => return Ok(Progress::default()),
+    };
+
+    match std::fs::read_to_string(&path) {
+        Ok(text) => parse_progress(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Progress::default()),
+        Err(e) => Err(format!("failed to read {}: {}", path.display(), e)),
+    }
+}
+
+pub fn save_progress(progress: &Progress) -> Result<(), String> {
+    let path = progress_path()
+        .ok_or_else(|| String::from("couldn't find a directory for the progress file"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+    }
+
+    std::fs::write(&path, progress_to_text(progress))
+        .map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+// The progress file has a `<pack id>/<board number> = <stars>` line for every board that's been won.
+// Board numbers count from 1.
+pub fn parse_progress(text: &str) -> Result<Progress, String> {
+    let mut progress = Progress::default();
+
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid_line = || {
+            format!(
+                "line {}: expected <pack>/<board> = <stars>, got '{}'",
+                line_index + 1,
+                line
+            )
+        };
+
+        let (key, stars) = line.split_once('=').ok_or_else(invalid_line)?;
+        let (pack_id, board_number) = key.trim().rsplit_once('/').ok_or_else(invalid_line)?;
+        let board_number = match board_number.parse::<usize>() {
+            Ok(number) if number > 0 => number,
+            _ => return Err(invalid_line()),
+        };
+        let stars = match stars.trim().parse::<u8>() {
+            Ok(stars) if stars <= MAX_STARS => stars,
+            _ => return Err(invalid_line()),
+        };
+
+        progress.record(pack_id, board_number - 1, stars);
+    }
+
+    Ok(progress)
+}
+
+pub fn progress_to_text(progress: &Progress) -> String {
+    let mut text = String::from("# the most stars earned on each campaign board\n");
+    for ((pack_id, board_index), stars) in &progress.stars {
+        text += &format!("{}/{} = {}\n", pack_id, board_index + 1, stars);
+    }
+    text
+}
+
+fn star_text(stars: u8) -> String {
+    (0..MAX_STARS)
+        .map(|star| if star < stars { '*' } else { '-' })
+        .collect()
+}
+
+struct LevelSelect {
+    campaign: Campaign,
+    // the selected pack and board
+    selected: (usize, usize),
+    status: String,
+}
+
+pub fn run(
+    campaign: Campaign,
+    options: &cli::Options,
+    accessibility: &config::Accessibility,
+    window: &pancurses::Window,
+    signals: &term::Signals,
+) {
+    let mut level_select = LevelSelect {
+        campaign,
+        selected: (0, 0),
+        status: String::new(),
+    };
+
+    let mut screen_rect = layout::screen_rect(window);
+    while !signals.quit_requested() {
+        signals.handle_suspend(window);
+
+        match crate::get_input_update(window) {
+            Some(InputUpdate::Mouse(mouse_state)) if mouse_state.click => {
+                if let Some(selected) = level_select.board_at(mouse_state.x, mouse_state.y) {
+                    level_select.selected = selected;
+                    level_select.play(options, accessibility, window, signals);
+                }
+            }
+            Some(InputUpdate::Scroll(dx, dy)) => level_select.move_selection(dx, dy),
+            Some(InputUpdate::Key('\n')) | Some(InputUpdate::Key(' ')) => {
+                level_select.play(options, accessibility, window, signals)
+            }
+            Some(InputUpdate::Key('q')) => return,
+            Some(InputUpdate::Resize) => screen_rect = layout::screen_rect(window),
+            _ => (),
+        }
+
+        window.erase();
+        level_select.render(&screen_rect, window);
+        term::present(window);
+
+        std::thread::sleep(Duration::from_millis(33));
+    }
+}
+
+impl LevelSelect {
+    fn selected_pack(&self) -> &Pack {
+        &self.campaign.packs[self.selected.0]
+    }
+
+    fn move_selection(&mut self, dx: i32, dy: i32) {
+        let last_pack = self.campaign.packs.len() as i32 - 1;
+        let pack_index = (self.selected.0 as i32 + dy).clamp(0, last_pack) as usize;
+
+        let last_board = self.campaign.packs[pack_index].boards.len() as i32 - 1;
+        let board_index = (self.selected.1 as i32 + dx).clamp(0, last_board) as usize;
+
+        self.selected = (pack_index, board_index);
+    }
+
+    fn board_at(&self, x: i32, y: i32) -> Option<(usize, usize)> {
+        let pack_row = y - PACKS_TOP;
+        let board_column = x - PACKS_LEFT - PACK_NAME_WIDTH;
+        if pack_row < 0 || pack_row % 2 != 0 || board_column < 0 {
+            return None;
+        }
+
+        let pack_index = (pack_row / 2) as usize;
+        let board_index = (board_column / BOARD_CELL_WIDTH) as usize;
+        let pack = self.campaign.packs.get(pack_index)?;
+        if board_index < pack.boards.len() {
+            Some((pack_index, board_index))
+        } else {
+            None
+        }
+    }
+
+    // Plays the selected board, then saves any stars that beat the best so far.
+    fn play(
+        &mut self,
+        options: &cli::Options,
+        accessibility: &config::Accessibility,
+        window: &pancurses::Window,
+        signals: &term::Signals,
+    ) {
+        let (pack_index, board_index) = self.selected;
+        let pack = &self.campaign.packs[pack_index];
+        if !self.campaign.progress.is_unlocked(pack, board_index) {
+            self.status = String::from("that board is still locked");
+            return;
+        }
+
+        let pack_board = &pack.boards[board_index];
+        let play_options = cli::Options {
+            board: Some(pack_board.board.clone()),
+            cell_metrics: options.cell_metrics,
+            ..cli::Options::default()
+        };
+
+        let outcome = crate::run_game(
+            board_index + 1,
+            Pilot::Player,
+            &mut crate::RunState::default(),
+            &play_options,
+            accessibility,
+            window,
+            signals,
+        );
+        if outcome.result == GameResult::Quit {
+            return;
+        }
+
+        let stars = pack_board.stars(&outcome);
+        self.status = match stars {
+            0 => String::from("no stars this time"),
+            1 => String::from("earned 1 star"),
+            _ => format!("earned {} stars", stars),
+        };
+
+        if self.campaign.progress.record(&pack.id, board_index, stars) {
+            if let Err(e) = save_progress(&self.campaign.progress) {
+                self.status = e;
+            }
+        }
+
+        // straight on to the next board once it's been unlocked
+        if stars > 0 {
+            self.move_selection(1, 0);
+        }
+    }
+
+    fn render<S: Surface>(&self, screen_rect: &crate::Rect, surface: &S) {
+        surface.draw_text(1, PACKS_LEFT, "Campaign");
+
+        let highlight = Style::Highlight.attributes();
+        let progress = &self.campaign.progress;
+        for (pack_index, pack) in self.campaign.packs.iter().enumerate() {
+            let top = PACKS_TOP + pack_index as i32 * 2;
+            let marker = if pack_index == self.selected.0 {
+                '>'
+            } else {
+                ' '
+            };
+            surface.draw_text(top, PACKS_LEFT - 2, format!("{} {}", marker, pack.name));
+
+            for board_index in 0..pack.boards.len() {
+                let text = if progress.is_unlocked(pack, board_index) {
+                    format!("[{}]", star_text(progress.stars(&pack.id, board_index)))
+                } else {
+                    String::from("[###]")
+                };
+
+                let selected = (pack_index, board_index) == self.selected;
+                if selected {
+                    surface.attributes_on(highlight);
+                }
+                surface.draw_text(
+                    top,
+                    PACKS_LEFT + PACK_NAME_WIDTH + board_index as i32 * BOARD_CELL_WIDTH,
+                    text,
+                );
+                if selected {
+                    surface.attributes_off(highlight);
+                }
+            }
+
+            surface.draw_text(
+                top,
+                PACKS_LEFT + PACK_NAME_WIDTH + pack.boards.len() as i32 * BOARD_CELL_WIDTH + 1,
+                format!(
+                    "{}/{}",
+                    progress.pack_stars(pack),
+                    pack.boards.len() * MAX_STARS as usize
+                ),
+            );
+        }
+
+        let board_index = self.selected.1;
+        let pack = self.selected_pack();
+        let pack_board = &pack.boards[board_index];
+        let details_top = PACKS_TOP + self.campaign.packs.len() as i32 * 2 + 1;
+        let mut details = vec![format!(
+            "{}, board {} of {}",
+            pack.name,
+            board_index + 1,
+            pack.boards.len()
+        )];
+        if progress.is_unlocked(pack, board_index) {
+            details.push(format!(
+                "{}x{} board, {}s on the clock",
+                pack_board.board.width,
+                pack_board.board.height,
+                pack_board.board.board_time.as_secs_f64()
+            ));
+            details.push(format!(
+                "**  win with {}s left",
+                pack_board.star_time.as_secs_f64()
+            ));
+            details.push(format!(
+                "*** ...and in {} reveals or fewer",
+                pack_board.star_reveals
+            ));
+        } else {
+            details.push(format!(
+                "locked: earn a star on board {} to play this one",
+                board_index
+            ));
+        }
+        details.push(String::new());
+        details.push(self.status.clone());
+
+        for (i, text) in details.iter().enumerate() {
+            surface.draw_text(details_top + i as i32, PACKS_LEFT, text);
+        }
+
+        surface.draw_text(
+            screen_rect.bottom() - 1,
+            std::cmp::max(0, screen_rect.center_x() - (HELP_TEXT.len() / 2) as i32),
+            HELP_TEXT,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACK: &str = "\
+# a test pack
+name = Tests
+
+---
+time = 10
+star_time = 4
+star_reveals = 2
+|>*<|
+---
+|v<|
+|*<|
+";
+
+    fn outcome(result: GameResult, secs_left: u64, reveals: usize) -> LevelOutcome {
+        LevelOutcome {
+            result,
+            time_left: Duration::from_secs(secs_left),
+            reveals,
+            elapsed: Duration::from_secs(10),
+            score: 0,
+        }
+    }
+
+    #[test]
+    fn test_bundled_packs_parse() {
+        for (id, text) in BUNDLED_PACKS.iter() {
+            let pack = parse_pack(id, text).unwrap();
+            assert!(!pack.boards.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_parse_pack() {
+        let pack = parse_pack("tests", PACK).unwrap();
+        assert_eq!("Tests", pack.name);
+        assert_eq!(2, pack.boards.len());
+        assert_eq!(Duration::from_secs(4), pack.boards[0].star_time);
+        assert_eq!(2, pack.boards[0].star_reveals);
+        assert_eq!(
+            (2, 2),
+            (pack.boards[1].board.width, pack.boards[1].board.height)
+        );
+
+        // missing thresholds come from the board
+        assert_eq!(Duration::from_secs(5), pack.boards[1].star_time);
+        assert!(pack.boards[1].star_reveals > SPARE_REVEALS);
+    }
+
+    #[test]
+    fn test_parse_pack_errors() {
+        let error = |text: &str| parse_pack("tests", text).unwrap_err();
+
+        assert_eq!("the pack has no name", error("---\n|*|"));
+        assert_eq!("the pack has no boards", error("name = Empty"));
+        assert_eq!(
+            "line 1: unrecognized setting 'author'",
+            error("author = me\n---\n|*|")
+        );
+        assert_eq!(
+            "board 2: line 5, column 3: unknown cell 'x' (expected one of < > ^ v * ~ or a space)",
+            error("name = Bad\n---\n|*|\n---\n|*x|")
+        );
+    }
+
+    #[test]
+    fn test_stars() {
+        let pack = parse_pack("tests", PACK).unwrap();
+        let board = &pack.boards[0];
+
+        assert_eq!(0, board.stars(&outcome(GameResult::Lose, 0, 1)));
+        assert_eq!(0, board.stars(&outcome(GameResult::Quit, 8, 1)));
+        assert_eq!(1, board.stars(&outcome(GameResult::Win, 3, 1)));
+        assert_eq!(2, board.stars(&outcome(GameResult::Win, 4, 3)));
+        assert_eq!(3, board.stars(&outcome(GameResult::Win, 9, 2)));
+    }
+
+    #[test]
+    fn test_unlocking() {
+        let pack = parse_pack("tests", PACK).unwrap();
+        let mut progress = Progress::default();
+        assert!(progress.is_unlocked(&pack, 0));
+        assert!(!progress.is_unlocked(&pack, 1));
+
+        assert!(!progress.record("tests", 0, 0));
+        assert!(!progress.is_unlocked(&pack, 1));
+
+        assert!(progress.record("tests", 0, 2));
+        assert!(progress.is_unlocked(&pack, 1));
+
+        // only better scores are kept
+        assert!(!progress.record("tests", 0, 1));
+        assert_eq!(2, progress.stars("tests", 0));
+        assert_eq!(2, progress.pack_stars(&pack));
+    }
+
+    #[test]
+    fn test_progress_round_trip() {
+        let mut progress = Progress::default();
+        progress.record("first-steps", 0, 3);
+        progress.record("first-steps", 1, 1);
+        progress.record("my/pack", 4, 2);
+
+        let text = progress_to_text(&progress);
+        assert!(text.contains("first-steps/1 = 3\n"));
+        assert_eq!(Ok(progress), parse_progress(&text));
+
+        assert!(parse_progress("first-steps = 3").is_err());
+        assert!(parse_progress("first-steps/0 = 3").is_err());
+        assert!(parse_progress("first-steps/1 = 4").is_err());
+    }
+
+    #[test]
+    fn test_level_select() {
+        let pack = parse_pack("tests", PACK).unwrap();
+        let mut level_select = LevelSelect {
+            campaign: Campaign {
+                packs: vec![pack.clone(), pack],
+                progress: Progress::default(),
+            },
+            selected: (0, 0),
+            status: String::new(),
+        };
+
+        level_select.move_selection(5, 1);
+        assert_eq!((1, 1), level_select.selected);
+        level_select.move_selection(-1, 3);
+        assert_eq!((1, 0), level_select.selected);
+
+        let board_left = PACKS_LEFT + PACK_NAME_WIDTH + BOARD_CELL_WIDTH;
+        assert_eq!(
+            Some((1, 1)),
+            level_select.board_at(board_left, PACKS_TOP + 2)
+        );
+        assert_eq!(None, level_select.board_at(board_left, PACKS_TOP + 1));
+        assert_eq!(None, level_select.board_at(PACKS_LEFT, PACKS_TOP));
+        assert_eq!(
+            None,
+            level_select.board_at(board_left + BOARD_CELL_WIDTH, PACKS_TOP)
+        );
+    }
+}
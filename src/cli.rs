@@ -0,0 +1,790 @@
+// command line parsing for lnf. Kept dependency free since there's only a handful of options.
+
+use crate::board_file::{self, Board};
+use crate::config::{self, Config};
+use crate::game::CellDistribution;
+use crate::hot_seat;
+use crate::machine::Timing;
+use crate::simulate::SimulateOptions;
+use crate::theme::Theme;
+use crate::versus;
+use crate::xform::CellMetrics;
+
+pub const USAGE: &str = "\
+usage: lnf [options]
+       lnf simulate [simulate options]
+       lnf edit <FILE> [options]
+       lnf campaign [options]
+       lnf host [options]
+       lnf join <HOST>:<PORT> [options]
+       lnf watch <HOST>:<PORT> [options]
+
+options:
+    --size <W>x<H>    play every board at a fixed size (i.e. 60x40). boards that don't fit on
+                      screen can be scrolled with the arrow keys, mouse wheel, or by hovering
+                      the mouse just past the edge of the board
+    --board <FILE>    play a hand-authored board file instead of random boards. see
+                      src/board_file.rs for the format
+    --export-boards <DIR>
+                      write every board that gets played to DIR/level-<N>.txt so it can be played
+                      again with --board
+    --cells <MODE>    how big to draw each cell: compact (1x1), normal (3x2), or large (5x3)
+    --theme <THEME>   colors to draw with: classic, colorblind, high-contrast, or monochrome.
+                      overrides the theme set in the config file
+    --line-mode       play with typed commands over plain lines of text instead of drawing the
+                      board, i.e. for use with a screen reader
+    --machine         play with JSON commands and events over stdin and stdout, for bots and tests
+    --timing <MODE>   how time passes with --machine: wall (real time) or step (only during wait
+                      commands)
+    --zen             play without a clock. hidden cells are found at your own pace and the score
+                      is how many reveals it takes. press q to stop
+    --survival        play every board on one bank of time. time left over from each board carries
+                      over to the next, which only adds a little more. the run ends when the bank
+                      runs dry
+    --treasure        play a single board hiding several treasures worth different amounts. the
+                      score is what's collected before time runs out. green hints point at the
+                      most valuable treasure left, the rest at the nearest
+    --hot-seat <TURNS>
+                      two players sharing the mouse. with boards turns they take turns playing
+                      whole boards until both have lost one. with reveals turns they take turns
+                      revealing cells on the same boards and whoever finds the hidden cell wins it
+    --versus <CONTROLS>
+                      two players racing side by side on copies of the same board. CONTROLS says
+                      how each plays, left first: two of mouse, arrows (enter reveals) or wasd
+                      (space reveals), i.e. wasd,arrows. first to win 3 rounds wins
+    --speedrun <LEVELS>
+                      race through levels 1 to LEVELS on the same boards every time, with splits
+                      against your personal best. personal bests are saved next to the config file
+    --spectate-port <PORT>
+                      let anyone on this machine watch the game from another terminal with
+                      lnf watch
+    --record <FILE>   record the session to FILE as an asciicast (asciinema v2) that can be played
+                      back with asciinema play
+    --autoplay        sit back and watch the built-in solver play instead
+    --solver-overlay  show what the solver is thinking: the cells that could still be the hidden
+                      cell and the one it would reveal next
+    --name <NAME>     what other players see you as in a network race (default: your user name)
+    --port <PORT>     the port to host a network race on (default 7878)
+    --race-boards <N> how many boards a hosted network race lasts (default 5)
+    -h, --help        print this message
+
+accessibility options:
+    --reduced-motion  don't blink anything, and show confusion with a steady indicator instead of
+                      flipping hints back and forth
+    --reveal-time <SECS>
+                      how long revealed cells stay revealed (default 4)
+    --show-seen-hints keep showing hints that were revealed before, dimmed, after they're hidden
+    --save-config     save the theme and accessibility options to the config file so they're
+                      used every time
+
+campaign plays through packs of hand-made boards, earning up to three stars on each one. packs
+can be added by putting them in the packs directory next to the config file. see src/campaign.rs
+for the format.
+
+host starts a network race that other players join from their own terminals with join. everyone
+plays the same boards, and whoever finds a board's hidden cell first wins it. the host starts the
+race from the lobby once everyone's in. see src/net.rs for the protocol.
+
+watch shows a game being played with --spectate-port, read-only. the arrow keys scroll around
+boards that don't fit on screen. see src/spectate.rs for what gets streamed.
+
+edit opens a board file in the level editor, or starts a new one (sized by --size) if the file
+doesn't exist yet. the keys to use are shown at the bottom of the editor.
+
+simulate plays lots of boards with the solver and prints stats about each level, for tuning
+difficulty. simulate options:
+    --levels <N>      how many levels to play, starting from level 1 (default 10)
+    --boards <N>      how many boards to play on each level (default 1000)
+    --seed <N>        seed for generating boards (default 0). the same seed plays the same boards
+    --format <FORMAT> print the stats as csv (the default) or json
+    --screen <W>x<H>  the terminal size boards have to fit on (default 80x24)
+    --click-time <SECS>
+                      how long the solver takes to make each click (default 1)
+    --distribution <T>,<E>,<H>
+                      out of every T+E+H cells, T are traps, E are empty and H are hints. replaces
+                      every level's own distribution
+    --no-reroll       play boards as they're first rolled instead of re-rolling them until their
+                      difficulty lands in the level's target band
+";
+
+// what lnf was asked to do
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Command {
+    #[default]
+    Play,
+    Simulate(SimulateOptions),
+    Edit(std::path::PathBuf),
+    Campaign,
+    Host,
+    Join(String),
+    Watch(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    pub command: Command,
+    pub board_size: Option<(i32, i32)>,
+    pub board: Option<Board>,
+    pub export_dir: Option<std::path::PathBuf>,
+    pub cell_metrics: CellMetrics,
+    pub theme: Option<Theme>,
+    pub line_mode: bool,
+    pub machine: bool,
+    pub timing: Timing,
+    pub zen: bool,
+    pub survival: bool,
+    pub treasure: bool,
+    pub hot_seat: Option<hot_seat::Turns>,
+    pub versus: Option<[versus::Controls; 2]>,
+    pub speedrun: Option<usize>,
+    pub name: Option<String>,
+    pub port: Option<u16>,
+    pub race_boards: Option<usize>,
+    pub spectate_port: Option<u16>,
+    pub record: Option<std::path::PathBuf>,
+    pub autoplay: bool,
+    pub solver_overlay: bool,
+    pub reduced_motion: bool,
+    pub reveal_time: Option<std::time::Duration>,
+    pub show_seen_hints: bool,
+    pub save_config: bool,
+    pub help: bool,
+}
+
+pub fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+
+    let mut args = args.peekable();
+    if args.peek().map(String::as_str) == Some("simulate") {
+        args.next();
+        options.command = Command::Simulate(parse_simulate_args(args)?);
+        return Ok(options);
+    }
+
+    if args.peek().map(String::as_str) == Some("edit") {
+        args.next();
+        let path = args
+            .next()
+            .ok_or_else(|| String::from("edit requires a board file"))?;
+        options.command = Command::Edit(path.into());
+    } else if args.peek().map(String::as_str) == Some("campaign") {
+        args.next();
+        options.command = Command::Campaign;
+    } else if args.peek().map(String::as_str) == Some("host") {
+        args.next();
+        options.command = Command::Host;
+    } else if args.peek().map(String::as_str) == Some("join") {
+        args.next();
+        let addr = args
+            .next()
+            .ok_or_else(|| String::from("join requires an address like example.com:7878"))?;
+        options.command = Command::Join(addr);
+    } else if args.peek().map(String::as_str) == Some("watch") {
+        args.next();
+        let addr = args
+            .next()
+            .ok_or_else(|| String::from("watch requires an address like example.com:7879"))?;
+        options.command = Command::Watch(addr);
+    }
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--size" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| String::from("--size requires a value"))?;
+                options.board_size = Some(parse_board_size(&value)?);
+            }
+            "--board" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| String::from("--board requires a value"))?;
+                options.board = Some(board_file::load(std::path::Path::new(&value))?);
+            }
+            "--export-boards" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| String::from("--export-boards requires a value"))?;
+                options.export_dir = Some(value.into());
+            }
+            "--cells" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| String::from("--cells requires a value"))?;
+                options.cell_metrics = parse_cell_metrics(&value)?;
+            }
+            "--theme" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| String::from("--theme requires a value"))?;
+                options.theme = Some(value.parse()?);
+            }
+            "--line-mode" => options.line_mode = true,
+            "--machine" => options.machine = true,
+            "--timing" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| String::from("--timing requires a value"))?;
+                options.timing = value.parse()?;
+            }
+            "--zen" => options.zen = true,
+            "--survival" => options.survival = true,
+            "--treasure" => options.treasure = true,
+            "--hot-seat" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| String::from("--hot-seat requires a value"))?;
+                options.hot_seat = Some(value.parse()?);
+            }
+            "--versus" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| String::from("--versus requires a value"))?;
+                options.versus = Some(versus::parse_controls(&value)?);
+            }
+            "--speedrun" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| String::from("--speedrun requires a value"))?;
+                options.speedrun = Some(parse_count(&value)?);
+            }
+            "--name" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| String::from("--name requires a value"))?;
+                options.name = Some(value);
+            }
+            "--port" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| String::from("--port requires a value"))?;
+                options.port = Some(parse_port(&value)?);
+            }
+            "--race-boards" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| String::from("--race-boards requires a value"))?;
+                options.race_boards = Some(parse_count(&value)?);
+            }
+            "--spectate-port" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| String::from("--spectate-port requires a value"))?;
+                options.spectate_port = Some(parse_port(&value)?);
+            }
+            "--record" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| String::from("--record requires a value"))?;
+                options.record = Some(value.into());
+            }
+            "--autoplay" => options.autoplay = true,
+            "--solver-overlay" => options.solver_overlay = true,
+            "--reduced-motion" => options.reduced_motion = true,
+            "--reveal-time" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| String::from("--reveal-time requires a value"))?;
+                options.reveal_time = Some(config::parse_seconds(&value)?);
+            }
+            "--show-seen-hints" => options.show_seen_hints = true,
+            "--save-config" => options.save_config = true,
+            "-h" | "--help" => options.help = true,
+            _ => return Err(format!("unrecognized argument '{}'", arg)),
+        }
+    }
+
+    // the text frontends announce the time left, which zen mode doesn't have
+    if options.zen && (options.line_mode || options.machine) {
+        return Err(String::from("--zen only works in the full screen game"));
+    }
+    if options.zen && options.survival {
+        return Err(String::from("--zen and --survival can't be used together"));
+    }
+
+    // treasure hunts are rolled fresh and the solver only knows how to look for a single solution
+    let not_treasure = options.zen
+        || options.survival
+        || options.board.is_some()
+        || options.export_dir.is_some()
+        || options.autoplay
+        || options.solver_overlay;
+    if options.treasure && not_treasure {
+        return Err(String::from(
+            "--treasure can't be used with other modes, --board, --export-boards or the solver",
+        ));
+    }
+
+    // versus races the regular game's boards side by side with every player at the controls
+    let not_versus = options.zen
+        || options.survival
+        || options.treasure
+        || options.hot_seat.is_some()
+        || options.board.is_some()
+        || options.line_mode
+        || options.machine
+        || options.autoplay
+        || options.solver_overlay;
+    if options.versus.is_some() && not_versus {
+        return Err(String::from(
+            "--versus can't be used with other modes, --board or the solver",
+        ));
+    }
+
+    // every player's boards are played back to back in the full screen game with the same rules
+    let not_hot_seat = options.zen
+        || options.survival
+        || options.treasure
+        || options.board.is_some()
+        || options.line_mode
+        || options.machine
+        || options.autoplay;
+    if options.hot_seat.is_some() && not_hot_seat {
+        return Err(String::from(
+            "--hot-seat can't be used with other modes, --board or --autoplay",
+        ));
+    }
+
+    // speedruns only count on the same boards with the same rules every time
+    let other_modes = options.zen
+        || options.survival
+        || options.treasure
+        || options.hot_seat.is_some()
+        || options.versus.is_some()
+        || options.board.is_some()
+        || options.board_size.is_some()
+        || options.line_mode
+        || options.machine
+        || options.autoplay;
+    if options.speedrun.is_some() && other_modes {
+        return Err(String::from(
+            "--speedrun can't be used with other modes, --board, --size or --autoplay",
+        ));
+    }
+
+    // network races only make sense with everyone playing the same boards by hand
+    let racing = matches!(options.command, Command::Host | Command::Join(_));
+    if (options.port.is_some() || options.race_boards.is_some()) && options.command != Command::Host
+    {
+        return Err(String::from(
+            "--port and --race-boards only work when hosting a race",
+        ));
+    }
+    if options.name.is_some() && !racing {
+        return Err(String::from("--name only works in a network race"));
+    }
+    if racing && (other_modes || options.speedrun.is_some() || options.solver_overlay) {
+        return Err(String::from(
+            "a network race can't be used with other modes, --board, --size or the solver",
+        ));
+    }
+
+    // only the regular full screen game gets streamed, and only one board of it
+    let not_spectated = options.line_mode
+        || options.machine
+        || options.versus.is_some()
+        || options.command != Command::Play;
+    if options.spectate_port.is_some() && not_spectated {
+        return Err(String::from(
+            "--spectate-port only works in the full screen game, without --versus",
+        ));
+    }
+    // recordings are of what's drawn on screen
+    if options.record.is_some() && (options.line_mode || options.machine) {
+        return Err(String::from("--record only works in the full screen game"));
+    }
+
+    let watching = matches!(options.command, Command::Watch(_));
+    if watching && (other_modes || options.speedrun.is_some() || options.solver_overlay) {
+        return Err(String::from(
+            "watch can't be used with other modes, --board, --size or the solver",
+        ));
+    }
+
+    Ok(options)
+}
+
+impl Options {
+    // command line options win over the ones in the config file
+    pub fn apply_to(&self, config: &mut Config) {
+        if let Some(theme) = self.theme {
+            config.theme = Some(theme);
+        }
+
+        let accessibility = &mut config.accessibility;
+        accessibility.reduced_motion |= self.reduced_motion;
+        accessibility.show_seen_hints |= self.show_seen_hints;
+        if let Some(reveal_time) = self.reveal_time {
+            accessibility.reveal_time = reveal_time;
+        }
+    }
+}
+
+fn parse_simulate_args<I: Iterator<Item = String>>(mut args: I) -> Result<SimulateOptions, String> {
+    let mut options = SimulateOptions::default();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("{} requires a value", arg))
+        };
+
+        match arg.as_str() {
+            "--levels" => options.levels = parse_count(&value()?)?,
+            "--boards" => options.boards = parse_count(&value()?)?,
+            "--seed" => {
+                let value = value()?;
+                options.seed = value
+                    .parse()
+                    .map_err(|_| format!("invalid seed '{}' (expected a number)", value))?;
+            }
+            "--format" => options.format = value()?.parse()?,
+            "--screen" => options.screen = parse_board_size(&value()?)?,
+            "--click-time" => options.click_time = config::parse_seconds(&value()?)?,
+            "--distribution" => {
+                options.cell_distribution = Some(parse_cell_distribution(&value()?)?)
+            }
+            "--no-reroll" => options.no_reroll = true,
+            _ => return Err(format!("unrecognized simulate argument '{}'", arg)),
+        }
+    }
+
+    Ok(options)
+}
+
+fn parse_count(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(count) if count > 0 => Ok(count),
+        _ => Err(format!(
+            "invalid count '{}' (expected a positive number)",
+            value
+        )),
+    }
+}
+
+fn parse_port(value: &str) -> Result<u16, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid port '{}' (expected a number)", value))
+}
+
+fn parse_cell_distribution(value: &str) -> Result<CellDistribution, String> {
+    let invalid_distribution = || {
+        format!(
+            "invalid distribution '{}' (expected <traps>,<empties>,<hints>)",
+            value
+        )
+    };
+
+    let counts: Vec<usize> = value
+        .split(',')
+        .map(|count| count.parse::<usize>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| invalid_distribution())?;
+
+    match counts[..] {
+        [traps, empties, hints] if traps + empties + hints > 0 => Ok(CellDistribution {
+            traps,
+            empties,
+            hints,
+        }),
+        _ => Err(invalid_distribution()),
+    }
+}
+
+fn parse_board_size(value: &str) -> Result<(i32, i32), String> {
+    let invalid_size = || format!("invalid board size '{}' (expected <W>x<H>)", value);
+
+    let mut dimensions = value.split('x').map(|dimension| dimension.parse::<i32>());
+    match (dimensions.next(), dimensions.next(), dimensions.next()) {
+        (Some(Ok(width)), Some(Ok(height)), None) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(invalid_size()),
+    }
+}
+
+fn parse_cell_metrics(value: &str) -> Result<CellMetrics, String> {
+    match value {
+        "compact" => Ok(CellMetrics::COMPACT),
+        "normal" => Ok(CellMetrics::NORMAL),
+        "large" => Ok(CellMetrics::LARGE),
+        _ => Err(format!(
+            "invalid cell mode '{}' (expected compact, normal, or large)",
+            value
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_board_size() {
+        assert_eq!(Ok(Options::default()), parse(&[]));
+        assert_eq!(
+            Some((60, 40)),
+            parse(&["--size", "60x40"]).unwrap().board_size
+        );
+
+        assert!(parse(&["--size"]).is_err());
+        assert!(parse(&["--size", "60"]).is_err());
+        assert!(parse(&["--size", "60x40x2"]).is_err());
+        assert!(parse(&["--size", "0x40"]).is_err());
+        assert!(parse(&["--bogus"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_board() {
+        assert!(parse(&["--board"]).is_err());
+
+        let error = parse(&["--board", "no/such/board.txt"]).unwrap_err();
+        assert!(error.starts_with("failed to read no/such/board.txt"));
+    }
+
+    #[test]
+    fn test_parse_cell_metrics() {
+        assert_eq!(CellMetrics::NORMAL, parse(&[]).unwrap().cell_metrics);
+        assert_eq!(
+            CellMetrics::COMPACT,
+            parse(&["--cells", "compact"]).unwrap().cell_metrics
+        );
+        assert_eq!(
+            CellMetrics::LARGE,
+            parse(&["--cells", "large"]).unwrap().cell_metrics
+        );
+
+        assert!(parse(&["--cells"]).is_err());
+        assert!(parse(&["--cells", "huge"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_theme() {
+        assert_eq!(None, parse(&[]).unwrap().theme);
+        assert_eq!(
+            Some(Theme::Colorblind),
+            parse(&["--theme", "colorblind"]).unwrap().theme
+        );
+
+        assert!(parse(&["--theme"]).is_err());
+        assert!(parse(&["--theme", "rainbow"]).is_err());
+    }
+
+    #[test]
+    fn test_options_override_config() {
+        let mut config = Config::default();
+        config.accessibility.show_seen_hints = true;
+
+        parse(&[
+            "--reduced-motion",
+            "--reveal-time",
+            "6",
+            "--theme",
+            "monochrome",
+        ])
+        .unwrap()
+        .apply_to(&mut config);
+        assert_eq!(Some(Theme::Monochrome), config.theme);
+        assert!(config.accessibility.reduced_motion);
+        assert!(config.accessibility.show_seen_hints);
+        assert_eq!(
+            std::time::Duration::from_secs(6),
+            config.accessibility.reveal_time
+        );
+
+        assert!(parse(&["--reveal-time", "soon"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_machine_timing() {
+        assert_eq!(Timing::WallClock, parse(&["--machine"]).unwrap().timing);
+        assert_eq!(
+            Timing::Stepped,
+            parse(&["--machine", "--timing", "step"]).unwrap().timing
+        );
+
+        assert!(parse(&["--timing"]).is_err());
+        assert!(parse(&["--timing", "fast"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_simulate() {
+        assert_eq!(Command::Play, parse(&[]).unwrap().command);
+        assert_eq!(
+            Command::Simulate(SimulateOptions::default()),
+            parse(&["simulate"]).unwrap().command
+        );
+
+        let options = match parse(&[
+            "simulate",
+            "--levels",
+            "5",
+            "--seed",
+            "99",
+            "--format",
+            "json",
+            "--distribution",
+            "2,2,6",
+        ])
+        .unwrap()
+        .command
+        {
+            Command::Simulate(options) => options,
+            _ => panic!("expected simulate"),
+        };
+        assert_eq!(5, options.levels);
+        assert_eq!(99, options.seed);
+        assert_eq!(crate::simulate::Format::Json, options.format);
+        assert_eq!(
+            Some(CellDistribution {
+                traps: 2,
+                empties: 2,
+                hints: 6
+            }),
+            options.cell_distribution
+        );
+
+        assert!(parse(&["simulate", "--levels", "0"]).is_err());
+        assert!(parse(&["simulate", "--boards"]).is_err());
+        assert!(parse(&["simulate", "--distribution", "1,2"]).is_err());
+        assert!(parse(&["simulate", "--distribution", "0,0,0"]).is_err());
+        assert!(parse(&["simulate", "--size", "10x10"]).is_err());
+        // simulate is only a command when it comes first
+        assert!(parse(&["--autoplay", "simulate"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_edit() {
+        let options = parse(&["edit", "boards/new.txt", "--size", "8x6"]).unwrap();
+        assert_eq!(Command::Edit("boards/new.txt".into()), options.command);
+        assert_eq!(Some((8, 6)), options.board_size);
+
+        assert!(parse(&["edit"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_campaign() {
+        let options = parse(&["campaign", "--cells", "large"]).unwrap();
+        assert_eq!(Command::Campaign, options.command);
+        assert_eq!(CellMetrics::LARGE, options.cell_metrics);
+
+        assert!(parse(&["--cells", "large", "campaign"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_race() {
+        let options = parse(&["host", "--port", "9000", "--race-boards", "3"]).unwrap();
+        assert_eq!(Command::Host, options.command);
+        assert_eq!(Some(9000), options.port);
+        assert_eq!(Some(3), options.race_boards);
+
+        let options = parse(&["join", "example.com:9000", "--name", "ada"]).unwrap();
+        assert_eq!(
+            Command::Join(String::from("example.com:9000")),
+            options.command
+        );
+        assert_eq!(Some(String::from("ada")), options.name);
+
+        assert!(parse(&["join"]).is_err());
+        assert!(parse(&["--port", "9000"]).is_err());
+        assert!(parse(&["join", "example.com:9000", "--race-boards", "3"]).is_err());
+        assert!(parse(&["--name", "ada"]).is_err());
+        assert!(parse(&["host", "--port", "http"]).is_err());
+        assert!(parse(&["host", "--zen"]).is_err());
+        assert!(parse(&["join", "example.com:9000", "--autoplay"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_record() {
+        let options = parse(&["--record", "demo/run.cast", "--autoplay"]).unwrap();
+        assert_eq!(Some("demo/run.cast".into()), options.record);
+        assert!(parse(&["--record"]).is_err());
+        assert!(parse(&["--record", "demo/run.cast", "--machine"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_spectating() {
+        let options = parse(&["--spectate-port", "7879", "--autoplay"]).unwrap();
+        assert_eq!(Some(7879), options.spectate_port);
+        assert!(parse(&["--spectate-port", "7879", "--versus", "wasd,arrows"]).is_err());
+        assert!(parse(&["--spectate-port", "7879", "--line-mode"]).is_err());
+        assert!(parse(&["campaign", "--spectate-port", "7879"]).is_err());
+
+        let options = parse(&["watch", "localhost:7879", "--cells", "large"]).unwrap();
+        assert_eq!(
+            Command::Watch(String::from("localhost:7879")),
+            options.command
+        );
+        assert!(parse(&["watch"]).is_err());
+        assert!(parse(&["watch", "localhost:7879", "--zen"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_zen() {
+        assert!(parse(&["--zen"]).unwrap().zen);
+        assert!(parse(&["--zen", "--line-mode"]).is_err());
+        assert!(parse(&["--machine", "--zen"]).is_err());
+        assert!(parse(&["--zen", "--survival"]).is_err());
+        assert!(parse(&["--survival", "--line-mode"]).unwrap().survival);
+    }
+
+    #[test]
+    fn test_parse_treasure() {
+        assert!(parse(&["--treasure", "--line-mode"]).unwrap().treasure);
+        assert!(!parse(&[]).unwrap().treasure);
+        assert!(parse(&["--treasure", "--survival"]).is_err());
+        assert!(parse(&["--autoplay", "--treasure"]).is_err());
+        assert!(parse(&["--treasure", "--speedrun", "3"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_hot_seat() {
+        assert_eq!(
+            Some(hot_seat::Turns::Reveals),
+            parse(&["--hot-seat", "reveals"]).unwrap().hot_seat
+        );
+        assert!(parse(&["--hot-seat"]).is_err());
+        assert!(parse(&["--hot-seat", "cells"]).is_err());
+        assert!(parse(&["--hot-seat", "boards", "--line-mode"]).is_err());
+        assert!(parse(&["--speedrun", "3", "--hot-seat", "boards"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_versus() {
+        assert_eq!(
+            Some([versus::Controls::Mouse, versus::Controls::Wasd]),
+            parse(&["--versus", "mouse,wasd"]).unwrap().versus
+        );
+        assert!(parse(&["--versus"]).is_err());
+        assert!(parse(&["--versus", "wasd,wasd"]).is_err());
+        assert!(parse(&["--versus", "wasd,arrows", "--hot-seat", "boards"]).is_err());
+        assert!(parse(&["--versus", "wasd,arrows", "--autoplay"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_speedrun() {
+        assert_eq!(None, parse(&[]).unwrap().speedrun);
+        assert_eq!(
+            Some(10),
+            parse(&["--speedrun", "10", "--cells", "compact"])
+                .unwrap()
+                .speedrun
+        );
+
+        assert!(parse(&["--speedrun"]).is_err());
+        assert!(parse(&["--speedrun", "0"]).is_err());
+        assert!(parse(&["--speedrun", "5", "--survival"]).is_err());
+        assert!(parse(&["--speedrun", "5", "--size", "9x9"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_solver_options() {
+        let options = parse(&["--autoplay", "--solver-overlay"]).unwrap();
+        assert!(options.autoplay);
+        assert!(options.solver_overlay);
+        assert!(!parse(&[]).unwrap().autoplay);
+    }
+}
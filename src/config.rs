@@ -0,0 +1,219 @@
+// The user's config file. It's a plain text file of `key = value` lines where blank lines and lines
+// starting with '#' are ignored, i.e.
+//
+//     # colors that are easier to tell apart
+//     theme = colorblind
+//     reveal_time = 6
+//
+// It lives at $XDG_CONFIG_HOME/lnf/config (falling back to ~/.config/lnf/config), or at
+// %APPDATA%\lnf\config on windows. Campaign progress and packs live in the same directory.
+
+use crate::game::DEFAULT_REVEAL_TIME;
+use crate::theme::Theme;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    pub theme: Option<Theme>,
+    pub accessibility: Accessibility,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Accessibility {
+    // don't blink anything, and show confusion with a steady indicator instead of flipping hints
+    pub reduced_motion: bool,
+    // how long revealed cells stay revealed
+    pub reveal_time: Duration,
+    // keep showing hints that have been seen before, dimmed, after they're hidden again
+    pub show_seen_hints: bool,
+}
+
+impl Default for Accessibility {
+    fn default() -> Self {
+        Accessibility {
+            reduced_motion: false,
+            reveal_time: DEFAULT_REVEAL_TIME,
+            show_seen_hints: false,
+        }
+    }
+}
+
+// the directory lnf keeps everything it saves in
+pub fn dir() -> Option<PathBuf> {
+    let config_dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+
+    config_dir.map(|dir| dir.join("lnf"))
+}
+
+pub fn path() -> Option<PathBuf> {
+    dir().map(|dir| dir.join("config"))
+}
+
+// Loads the user's config. Not having a config file at all is fine and just gives the defaults.
+pub fn load() -> Result<Config, String> {
+    let path = match path() {
+        Some(path) => path,
+        None => return Ok(Config::default()),
+    };
+
+    match std::fs::read_to_string(&path) {
+        Ok(text) => parse(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(format!("failed to read {}: {}", path.display(), e)),
+    }
+}
+
+pub fn parse(text: &str) -> Result<Config, String> {
+    let mut config = Config::default();
+
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line_error = |e: String| format!("line {}: {}", line_index + 1, e);
+
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => {
+                return Err(line_error(format!(
+                    "expected <key> = <value>, got '{}'",
+                    line
+                )))
+            }
+        };
+
+        match key {
+            "theme" => config.theme = Some(value.parse().map_err(line_error)?),
+            "reduced_motion" => {
+                config.accessibility.reduced_motion = parse_bool(value).map_err(line_error)?
+            }
+            "reveal_time" => {
+                config.accessibility.reveal_time = parse_seconds(value).map_err(line_error)?
+            }
+            "show_seen_hints" => {
+                config.accessibility.show_seen_hints = parse_bool(value).map_err(line_error)?
+            }
+            _ => return Err(line_error(format!("unrecognized setting '{}'", key))),
+        }
+    }
+
+    Ok(config)
+}
+
+// Writes out the config in the same format `parse` reads. Any comments in the existing file are lost.
+pub fn save(config: &Config) -> Result<(), String> {
+    let path =
+        path().ok_or_else(|| String::from("couldn't find a directory for the config file"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+    }
+
+    std::fs::write(&path, to_text(config))
+        .map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+pub fn to_text(config: &Config) -> String {
+    let mut text = String::new();
+    if let Some(theme) = config.theme {
+        text += &format!("theme = {}\n", theme);
+    }
+
+    let accessibility = &config.accessibility;
+    text += "\n# accessibility\n";
+    text += &format!("reduced_motion = {}\n", accessibility.reduced_motion);
+    text += &format!(
+        "reveal_time = {}\n",
+        accessibility.reveal_time.as_secs_f64()
+    );
+    text += &format!("show_seen_hints = {}\n", accessibility.show_seen_hints);
+    text
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!(
+            "invalid value '{}' (expected true or false)",
+            value
+        )),
+    }
+}
+
+// parses a positive number of seconds, i.e. "6" or "4.5"
+pub fn parse_seconds(value: &str) -> Result<Duration, String> {
+    match value.parse::<f64>() {
+        Ok(secs) if secs > 0.0 && secs.is_finite() => Ok(Duration::from_secs_f64(secs)),
+        _ => Err(format!(
+            "invalid time '{}' (expected a positive number of seconds)",
+            value
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        assert_eq!(Ok(Config::default()), parse(""));
+        assert_eq!(
+            Ok(Config {
+                theme: Some(Theme::HighContrast),
+                ..Config::default()
+            }),
+            parse("# comment\n\n  theme =  high-contrast  \n")
+        );
+
+        assert_eq!(
+            Err(String::from("line 2: unrecognized setting 'volume'")),
+            parse("theme = classic\nvolume = 11\n")
+        );
+        assert!(parse("theme\n").is_err());
+        assert!(parse("theme = rainbow\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_accessibility() {
+        let config = parse("reduced_motion = true\nreveal_time = 6.5\nshow_seen_hints = true\n");
+        assert_eq!(
+            Ok(Accessibility {
+                reduced_motion: true,
+                reveal_time: Duration::from_millis(6500),
+                show_seen_hints: true,
+            }),
+            config.map(|config| config.accessibility)
+        );
+
+        assert!(parse("reduced_motion = yes\n").is_err());
+        assert!(parse("reveal_time = 0\n").is_err());
+        assert!(parse("reveal_time = -3\n").is_err());
+    }
+
+    #[test]
+    fn test_config_round_trip() {
+        let config = Config {
+            theme: Some(Theme::Monochrome),
+            accessibility: Accessibility {
+                reduced_motion: true,
+                reveal_time: Duration::from_secs(8),
+                show_seen_hints: false,
+            },
+        };
+        let text = to_text(&config);
+        assert_eq!(Ok(config), parse(&text));
+        assert_eq!(Ok(Config::default()), parse(&to_text(&Config::default())));
+    }
+}
@@ -0,0 +1,165 @@
+// Rates how hard a board is so the generator can throw out boards that are way easier or harder
+// than the rest of their level, like a solution walled in by traps and empties or one that's found
+// on the first click.
+//
+// The rating comes from letting the solver play the board with no clock. It knows nothing the
+// player wouldn't, so the number of reveals it needs is a fair stand-in for how long a player will
+// be hunting.
+
+use crate::game::{GameGrid, GridItem};
+use crate::solver::Solver;
+
+// A confusion trap takes away a few seconds of reading hints, which is worth about this many
+// reveals.
+const TRAP_COST: f64 = 2.0;
+
+// how far from the solution (in either direction) a cell counts as being near it
+const NEARBY_DISTANCE: i32 = 2;
+
+// Boards with fewer hints than this near the solution leave the player guessing at the end.
+const MIN_HINT_COVERAGE: f64 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Difficulty {
+    // how many reveals the solver took, counting the one that found the solution
+    pub reveals: usize,
+    // how many traps the solver set off along the way
+    pub traps_hit: usize,
+    // the fraction of cells near the solution that are hints
+    pub hint_coverage: f64,
+}
+
+impl Difficulty {
+    pub fn score(&self) -> f64 {
+        self.reveals as f64 + self.traps_hit as f64 * TRAP_COST
+    }
+
+    // Boards that are bad no matter what the score is
+    pub fn is_degenerate(&self) -> bool {
+        self.reveals <= 1 || self.hint_coverage < MIN_HINT_COVERAGE
+    }
+
+    // How far the score is from landing in `band`. Zero means it's in the band.
+    pub fn distance_from(&self, band: (f64, f64)) -> f64 {
+        if self.is_degenerate() {
+            return f64::INFINITY;
+        }
+
+        let score = self.score();
+        if score < band.0 {
+            band.0 - score
+        } else if score > band.1 {
+            score - band.1
+        } else {
+            0.0
+        }
+    }
+}
+
+pub fn analyze(grid: &GameGrid) -> Difficulty {
+    let mut solver = Solver::new(grid);
+    let mut reveals = 0;
+    let mut traps_hit = 0;
+    loop {
+        let (x, y) = solver
+            .best_probe(|_, _| true)
+            .expect("the solution is always a candidate");
+        let item = grid.cell(x, y).unwrap().item;
+
+        reveals += 1;
+        match item {
+            GridItem::Solution => break,
+            GridItem::Trap(_) => traps_hit += 1,
+            _ => (),
+        }
+        solver.learn(x, y, item);
+    }
+
+    Difficulty {
+        reveals,
+        traps_hit,
+        hint_coverage: hint_coverage(grid),
+    }
+}
+
+fn hint_coverage(grid: &GameGrid) -> f64 {
+    let (solution_x, solution_y) = grid.find_solution().unwrap();
+
+    let mut nearby = 0;
+    let mut hints = 0;
+    for y in solution_y - NEARBY_DISTANCE..=solution_y + NEARBY_DISTANCE {
+        for x in solution_x - NEARBY_DISTANCE..=solution_x + NEARBY_DISTANCE {
+            match grid.cell(x, y) {
+                Some(grid_cell) if grid_cell.item != GridItem::Solution => {
+                    nearby += 1;
+                    if let GridItem::Hint(_) = grid_cell.item {
+                        hints += 1;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    if nearby == 0 {
+        1.0
+    } else {
+        hints as f64 / nearby as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{CellDistribution, DEFAULT_REVEAL_TIME};
+    use crate::utils::SeededRng;
+
+    fn new_grid(cell_distribution: CellDistribution, seed: u64) -> GameGrid {
+        GameGrid::new(
+            15,
+            10,
+            6,
+            DEFAULT_REVEAL_TIME,
+            cell_distribution,
+            &mut SeededRng::new(seed),
+        )
+    }
+
+    #[test]
+    fn test_analyze() {
+        for seed in 0..20 {
+            let difficulty = analyze(&new_grid(CellDistribution::default(), seed));
+            assert!(difficulty.reveals >= 1);
+            assert!(difficulty.traps_hit < difficulty.reveals);
+            assert!(difficulty.score() >= difficulty.reveals as f64);
+            assert!((0.0..=1.0).contains(&difficulty.hint_coverage));
+        }
+    }
+
+    #[test]
+    fn test_boards_without_hints_are_degenerate() {
+        let no_hints = CellDistribution {
+            traps: 1,
+            empties: 1,
+            hints: 0,
+        };
+
+        let difficulty = analyze(&new_grid(no_hints, 3));
+        assert_eq!(0.0, difficulty.hint_coverage);
+        assert!(difficulty.is_degenerate());
+        assert_eq!(f64::INFINITY, difficulty.distance_from((0.0, 1000.0)));
+    }
+
+    #[test]
+    fn test_distance_from_band() {
+        let difficulty = Difficulty {
+            reveals: 5,
+            traps_hit: 1,
+            hint_coverage: 0.5,
+        };
+        assert_eq!(7.0, difficulty.score());
+        assert_eq!(0.0, difficulty.distance_from((6.0, 8.0)));
+        assert_eq!(2.0, difficulty.distance_from((9.0, 12.0)));
+        assert_eq!(3.0, difficulty.distance_from((2.0, 4.0)));
+    }
+}
@@ -0,0 +1,366 @@
+// An in-terminal editor for board files, started with `lnf edit <file>`. The board is drawn just
+// like it is in the game, only with every cell showing.
+//
+// The cursor follows the mouse and the arrow keys. Typing a cell's glyph from the board file format
+// (< > ^ v * ~ or space) paints it under the cursor, and clicking paints whatever was typed last.
+
+use crate::board_file::{self, Board};
+use crate::game::{GridItem, HintDir, TrapType};
+use crate::render::Surface;
+use crate::{cli, config, layout, term, xform};
+use crate::{InputUpdate, MouseState, Pilot};
+use std::path::Path;
+use std::time::Duration;
+
+// how big new boards are unless --size says otherwise
+const NEW_BOARD_SIZE: (i32, i32) = (15, 10);
+
+const HELP_TEXT: &str = "arrows/mouse: move   < > ^ v * ~ space: paint   f: fix hints   \
+                         +/-: time   [/]: max revealed   s: save   p: playtest   q: quit";
+
+const UNSAVED_WARNING: &str = "unsaved changes! press q again to quit anyway";
+
+// Opens the board at `path` for editing, or starts a new one if there's nothing there yet.
+pub fn open(path: &Path, board_size: Option<(i32, i32)>) -> Result<Board, String> {
+    if path.exists() {
+        board_file::load(path)
+    } else {
+        let (width, height) = board_size.unwrap_or(NEW_BOARD_SIZE);
+        Ok(Board::new(width, height))
+    }
+}
+
+struct Editor<'a> {
+    path: &'a Path,
+    board: Board,
+    cursor: (i32, i32),
+    // what clicking paints
+    brush: GridItem,
+    saved: bool,
+    status: String,
+}
+
+pub fn run(
+    path: &Path,
+    board: Board,
+    options: &cli::Options,
+    accessibility: &config::Accessibility,
+    window: &pancurses::Window,
+    signals: &term::Signals,
+) {
+    let metrics = &options.cell_metrics;
+    let mut screen_rect = layout::screen_rect(window);
+    let mut camera = xform::Camera::new(board.width, board.height);
+    let mut board_layout = layout::camera_layout(&mut camera, metrics, &screen_rect);
+
+    let mut editor = Editor {
+        path,
+        cursor: board.find_solution().unwrap_or((0, 0)),
+        board,
+        brush: GridItem::Hint(HintDir::Left),
+        saved: path.exists(),
+        status: String::new(),
+    };
+
+    while !signals.quit_requested() {
+        signals.handle_suspend(window);
+
+        match crate::get_input_update(window) {
+            Some(InputUpdate::Mouse(mouse_state)) => {
+                if let Some(board_layout) = &board_layout {
+                    let grid_pos = xform::window_to_game_grid(
+                        mouse_state.x,
+                        mouse_state.y,
+                        board_layout.grid.left,
+                        board_layout.grid.top,
+                        &camera,
+                        metrics,
+                    );
+
+                    if camera.contains(grid_pos.0, grid_pos.1) {
+                        editor.cursor = grid_pos;
+                        if mouse_state.click {
+                            editor.paint(editor.brush);
+                        }
+                    }
+                }
+            }
+            Some(InputUpdate::Scroll(dx, dy)) => {
+                editor.move_cursor(dx, dy);
+                camera.scroll_to_show(editor.cursor.0, editor.cursor.1);
+            }
+            Some(InputUpdate::Key('p')) => {
+                playtest(&editor.board, options, accessibility, window, signals);
+                editor.status.clear();
+            }
+            Some(InputUpdate::Key(c)) => {
+                let keep_editing = editor.handle_key(c);
+                if !keep_editing {
+                    return;
+                }
+            }
+            Some(InputUpdate::Resize) => {
+                screen_rect = layout::screen_rect(window);
+                board_layout = layout::camera_layout(&mut camera, metrics, &screen_rect);
+            }
+            None => (),
+        }
+
+        window.erase();
+        match &board_layout {
+            Some(board_layout) => {
+                let board_view = crate::BoardView {
+                    grid_rect: &board_layout.grid,
+                    camera: &camera,
+                    metrics,
+                    accessibility,
+                };
+                editor.render(&board_view, board_layout, &screen_rect, window);
+            }
+            None => {
+                let (min_width, min_height) = layout::min_viewport_size(&camera);
+                let min_size = layout::min_screen_size(min_width, min_height, metrics);
+                crate::render_too_small_overlay(min_size, &screen_rect, window);
+            }
+        }
+        term::present(window);
+
+        std::thread::sleep(Duration::from_millis(33));
+    }
+}
+
+impl<'a> Editor<'a> {
+    fn move_cursor(&mut self, dx: i32, dy: i32) {
+        self.cursor = (
+            (self.cursor.0 + dx).clamp(0, self.board.width - 1),
+            (self.cursor.1 + dy).clamp(0, self.board.height - 1),
+        );
+    }
+
+    fn paint(&mut self, item: GridItem) {
+        let (x, y) = self.cursor;
+
+        // the solution can be moved but never painted over, or there'd be nothing to find
+        if self.board.get(x, y) == Some(GridItem::Solution) && item != GridItem::Solution {
+            self.status = String::from("move the solution by placing it somewhere else");
+            return;
+        }
+
+        self.board.set(x, y, item);
+        self.saved = false;
+    }
+
+    // Returns false once the editor should close.
+    fn handle_key(&mut self, c: char) -> bool {
+        if let Some(item) = brush_from_key(c) {
+            self.brush = item;
+            self.paint(item);
+            return true;
+        }
+
+        let board = &mut self.board;
+        match c {
+            'f' => {
+                board.point_hints_at_solution();
+                self.status = String::from("every hint points at the solution");
+            }
+            '+' | '=' => board.board_time += Duration::from_secs(1),
+            '-' if board.board_time > Duration::from_secs(1) => {
+                board.board_time -= Duration::from_secs(1)
+            }
+            ']' => board.max_revealed_cells += 1,
+            '[' if board.max_revealed_cells > 1 => board.max_revealed_cells -= 1,
+            's' => {
+                self.status = match board_file::save(self.path, board) {
+                    Ok(()) => {
+                        self.saved = true;
+                        format!("saved {}", self.path.display())
+                    }
+                    Err(e) => e,
+                };
+                return true;
+            }
+            'q' if self.saved || self.status == UNSAVED_WARNING => return false,
+            'q' => {
+                self.status = String::from(UNSAVED_WARNING);
+                return true;
+            }
+            _ => return true,
+        }
+
+        self.saved = false;
+        true
+    }
+
+    fn render<S: Surface>(
+        &self,
+        board_view: &crate::BoardView,
+        board_layout: &layout::BoardLayout,
+        screen_rect: &crate::Rect,
+        surface: &S,
+    ) {
+        // every cell shows in the editor
+        let mut grid = self.board.to_grid(board_view.accessibility.reveal_time);
+        grid.reveal_all();
+
+        surface.draw_text(
+            board_layout.level.top,
+            board_layout.level.left,
+            format!(
+                "Editing: {}{}",
+                self.path.display(),
+                if self.saved { "" } else { " *" }
+            ),
+        );
+        surface.draw_text(
+            board_layout.time.top,
+            board_layout.time.left,
+            format!(
+                "Time: {}s  Max revealed: {}  Brush: '{}'",
+                self.board.board_time.as_secs_f64(),
+                self.board.max_revealed_cells,
+                board_file::glyph(self.brush)
+            ),
+        );
+
+        // the game highlights the cell under the mouse, so the cursor pretends to be the mouse
+        let cursor_rect = xform::game_grid_to_window(
+            self.cursor.0,
+            self.cursor.1,
+            board_view.grid_rect.left,
+            board_view.grid_rect.top,
+            board_view.camera,
+            board_view.metrics,
+        );
+        let cursor_state = MouseState {
+            click: false,
+            x: cursor_rect.left,
+            y: cursor_rect.top,
+        };
+        crate::render_game_board(&grid, &None, None, board_view, surface, &cursor_state);
+
+        if let Some((minimap_rect, cells_per_char)) =
+            layout::minimap_layout(board_view.camera, screen_rect)
+        {
+            crate::render_minimap(
+                &grid,
+                board_view.camera,
+                &minimap_rect,
+                cells_per_char,
+                surface,
+            );
+        }
+
+        for (i, text) in [HELP_TEXT, &self.status].iter().enumerate() {
+            surface.draw_text(
+                board_layout.game_over.top + i as i32,
+                std::cmp::max(0, screen_rect.center_x() - (text.len() / 2) as i32),
+                text,
+            );
+        }
+    }
+}
+
+// the item each key paints, using the same glyphs as board files
+fn brush_from_key(c: char) -> Option<GridItem> {
+    match c {
+        '<' => Some(GridItem::Hint(HintDir::Left)),
+        '>' => Some(GridItem::Hint(HintDir::Right)),
+        '^' => Some(GridItem::Hint(HintDir::Up)),
+        'v' => Some(GridItem::Hint(HintDir::Down)),
+        '*' => Some(GridItem::Solution),
+        '~' => Some(GridItem::Trap(TrapType::Confusion)),
+        ' ' => Some(GridItem::Empty),
+        _ => None,
+    }
+}
+
+// Plays the board as it is right now. Comes back to the editor once the board is won or lost.
+fn playtest(
+    board: &Board,
+    options: &cli::Options,
+    accessibility: &config::Accessibility,
+    window: &pancurses::Window,
+    signals: &term::Signals,
+) {
+    let playtest_options = cli::Options {
+        board: Some(board.clone()),
+        cell_metrics: options.cell_metrics,
+        ..cli::Options::default()
+    };
+
+    crate::run_game(
+        1,
+        Pilot::Player,
+        &mut crate::RunState::default(),
+        &playtest_options,
+        accessibility,
+        window,
+        signals,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_editor(path: &Path) -> Editor<'_> {
+        Editor {
+            path,
+            board: Board::new(5, 3),
+            cursor: (0, 0),
+            brush: GridItem::Empty,
+            saved: true,
+            status: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_painting() {
+        let path = Path::new("board.txt");
+        let mut editor = new_editor(path);
+
+        assert!(editor.handle_key('~'));
+        assert_eq!(
+            Some(GridItem::Trap(TrapType::Confusion)),
+            editor.board.get(0, 0)
+        );
+        assert_eq!(GridItem::Trap(TrapType::Confusion), editor.brush);
+        assert!(!editor.saved);
+
+        // the solution can't be painted over
+        editor.cursor = (2, 1);
+        editor.handle_key(' ');
+        assert_eq!(Some(GridItem::Solution), editor.board.get(2, 1));
+
+        editor.move_cursor(10, -10);
+        assert_eq!((4, 0), editor.cursor);
+        editor.handle_key('*');
+        assert_eq!(Some((4, 0)), editor.board.find_solution());
+
+        editor.handle_key('+');
+        editor.handle_key('[');
+        assert_eq!(Duration::from_secs(16), editor.board.board_time);
+        assert_eq!(5, editor.board.max_revealed_cells);
+    }
+
+    #[test]
+    fn test_quitting_with_unsaved_changes() {
+        let path = Path::new("board.txt");
+        let mut editor = new_editor(path);
+        assert!(!editor.handle_key('q'));
+
+        editor.handle_key('v');
+        assert!(editor.handle_key('q'));
+        assert!(!editor.handle_key('q'));
+    }
+
+    #[test]
+    fn test_brushes_match_board_files() {
+        for c in "<>^v*~ ".chars() {
+            let item = brush_from_key(c).unwrap();
+            assert_eq!(c, board_file::glyph(item));
+        }
+        assert_eq!(None, brush_from_key('x'));
+    }
+}
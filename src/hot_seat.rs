@@ -0,0 +1,328 @@
+// Hot-seat mode, started with `--hot-seat <TURNS>`, is two players passing one mouse back and forth.
+//
+// With `boards` turns the players take turns playing whole boards, each on their own run through
+// the levels. A player is out once they lose a board and the game goes on until both are out. With
+// `reveals` turns both players play the same boards, taking turns revealing one cell at a time, and
+// whoever reveals the hidden cell wins the board. The players swap who goes first on every board
+// and the game ends on the first board that runs out of time.
+//
+// Either way a player's score is how many boards they've won, and the game finishes on a screen
+// comparing the scores.
+
+use crate::render::Surface;
+use crate::theme::Style;
+use crate::utils::Rect;
+use crate::{layout, term, GameResult, InputUpdate};
+use std::time::Duration;
+
+const PLAYER_COUNT: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Turns {
+    Boards,
+    Reveals,
+}
+
+impl std::str::FromStr for Turns {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "boards" => Ok(Turns::Boards),
+            "reveals" => Ok(Turns::Reveals),
+            _ => Err(format!(
+                "invalid turns '{}' (expected boards or reveals)",
+                value
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Player {
+    pub name: String,
+    // the level of the next board they play
+    pub level: usize,
+    // how many boards they've won
+    pub score: usize,
+    // false once they've lost a board with `boards` turns
+    pub playing: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Roster {
+    pub turns: Turns,
+    pub players: Vec<Player>,
+    // whose board it is, or who reveals first on the board with `reveals` turns
+    pub current: usize,
+}
+
+impl Roster {
+    pub fn new(turns: Turns) -> Self {
+        let players = (1..=PLAYER_COUNT)
+            .map(|number| Player {
+                name: format!("Player {}", number),
+                level: 1,
+                score: 0,
+                playing: true,
+            })
+            .collect();
+
+        Roster {
+            turns,
+            players,
+            current: 0,
+        }
+    }
+
+    // the level of the next board
+    pub fn level(&self) -> usize {
+        self.players[self.current].level
+    }
+
+    // whose turn it is once `reveals` cells have been revealed on the current board
+    pub fn turn(&self, reveals: usize) -> usize {
+        match self.turns {
+            Turns::Boards => self.current,
+            Turns::Reveals => (self.current + reveals) % self.players.len(),
+        }
+    }
+
+    // Scores a board that ended with `result` after `reveals` reveals and passes the turn on.
+    // Returns false once the game is over.
+    pub fn finish_board(&mut self, result: GameResult, reveals: usize) -> bool {
+        match (self.turns, result) {
+            (_, GameResult::Quit) => return false,
+            (Turns::Boards, GameResult::Win) => {
+                let player = &mut self.players[self.current];
+                player.level += 1;
+                player.score += 1;
+            }
+            (Turns::Boards, GameResult::Lose) => self.players[self.current].playing = false,
+            (Turns::Reveals, GameResult::Win) => {
+                // whoever made the last reveal found it
+                let winner = self.turn(reveals.saturating_sub(1));
+                self.players[winner].score += 1;
+                for player in &mut self.players {
+                    player.level += 1;
+                }
+            }
+            (Turns::Reveals, GameResult::Lose) => return false,
+        }
+
+        let player_count = self.players.len();
+        let next = (1..=player_count)
+            .map(|step| (self.current + step) % player_count)
+            .find(|index| self.players[*index].playing);
+        match next {
+            Some(next) => {
+                self.current = next;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Whether there's another board after this one ends with `result`
+    pub fn continues_after(&self, result: GameResult, reveals: usize) -> bool {
+        self.clone().finish_board(result, reveals)
+    }
+
+    // everyone with the top score, so more than one player on a tie
+    pub fn leaders(&self) -> Vec<&Player> {
+        let top_score = self.players.iter().map(|player| player.score).max();
+        self.players
+            .iter()
+            .filter(|player| Some(player.score) == top_score)
+            .collect()
+    }
+
+    // one line per player, then who won
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .players
+            .iter()
+            .map(|player| {
+                let boards = if player.score == 1 { "board" } else { "boards" };
+                format!("{}: {} {} won", player.name, player.score, boards)
+            })
+            .collect();
+
+        match self.leaders().as_slice() {
+            [winner] => lines.push(format!("{} wins!", winner.name)),
+            _ => lines.push(String::from("It's a tie!")),
+        }
+        lines
+    }
+}
+
+// Draws whose turn it is and the scores under the board timer. Once a shared board has been won
+// it says who found it instead.
+pub fn render_turn<S: Surface>(
+    roster: &Roster,
+    reveals: usize,
+    result: Option<GameResult>,
+    time_rect: &Rect,
+    surface: &S,
+) {
+    let text = match (roster.turns, result) {
+        (Turns::Reveals, Some(GameResult::Win)) => {
+            let winner = roster.turn(reveals.saturating_sub(1));
+            format!("{} found it!", roster.players[winner].name)
+        }
+        _ => format!("{}'s turn", roster.players[roster.turn(reveals)].name),
+    };
+
+    let attributes = Style::Highlight.attributes();
+    surface.attributes_on(attributes);
+    surface.draw_text(time_rect.top + 1, time_rect.left, &text);
+    surface.attributes_off(attributes);
+
+    let scores: Vec<String> = roster
+        .players
+        .iter()
+        .map(|player| player.score.to_string())
+        .collect();
+    surface.draw_text(
+        time_rect.top + 1,
+        time_rect.left + text.len() as i32 + 1,
+        format!("({})", scores.join("-")),
+    );
+}
+
+// Shows a summary of how every player did until someone presses a key or clicks. The last line of
+// the summary says who won. Split-screen versus (see versus.rs) finishes on this screen too.
+pub fn run_results_screen(summary: &[String], window: &pancurses::Window, signals: &term::Signals) {
+    let mut screen_rect = layout::screen_rect(window);
+    while !signals.quit_requested() {
+        signals.handle_suspend(window);
+
+        match crate::get_input_update(window) {
+            Some(InputUpdate::Key(_)) => return,
+            Some(InputUpdate::Mouse(mouse_state)) if mouse_state.click => return,
+            Some(InputUpdate::Resize) => screen_rect = layout::screen_rect(window),
+            _ => (),
+        }
+
+        window.erase();
+        render_results(summary, &screen_rect, window);
+        term::present(window);
+
+        std::thread::sleep(Duration::from_millis(33));
+    }
+}
+
+// Draws the summary in the middle of the screen, where its last line says who won
+fn render_results<S: Surface>(summary: &[String], screen_rect: &Rect, surface: &S) {
+    let mut lines = summary.to_vec();
+    lines.push(String::new());
+    lines.push(String::from("press any key to exit"));
+
+    let top = screen_rect.center_y() - lines.len() as i32 / 2;
+    for (i, line) in lines.iter().enumerate() {
+        // the winner stands out
+        let attributes = if i == summary.len() - 1 {
+            Style::Success.attributes()
+        } else {
+            pancurses::A_NORMAL
+        };
+        surface.attributes_on(attributes);
+        surface.draw_text(
+            top + i as i32,
+            std::cmp::max(0, screen_rect.center_x() - (line.len() / 2) as i32),
+            line,
+        );
+        surface.attributes_off(attributes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_taking_turns_on_boards() {
+        let mut roster = Roster::new(Turns::Boards);
+        assert_eq!(0, roster.turn(5));
+
+        assert!(roster.finish_board(GameResult::Win, 3));
+        assert_eq!(1, roster.current);
+        assert_eq!(1, roster.level());
+
+        assert!(roster.finish_board(GameResult::Lose, 3));
+        assert_eq!(0, roster.current);
+        assert_eq!(2, roster.level());
+
+        // player 2 is out, so player 1 keeps going until they lose too
+        assert!(roster.finish_board(GameResult::Win, 3));
+        assert_eq!(0, roster.current);
+        assert_eq!(3, roster.level());
+        assert!(roster.continues_after(GameResult::Win, 3));
+        assert!(!roster.continues_after(GameResult::Lose, 3));
+
+        assert!(!roster.finish_board(GameResult::Lose, 3));
+        assert_eq!(
+            vec![
+                "Player 1: 2 boards won",
+                "Player 2: 0 boards won",
+                "Player 1 wins!"
+            ],
+            roster.summary()
+        );
+    }
+
+    #[test]
+    fn test_taking_turns_on_reveals() {
+        let mut roster = Roster::new(Turns::Reveals);
+        assert_eq!(0, roster.turn(0));
+        assert_eq!(1, roster.turn(1));
+        assert_eq!(0, roster.turn(2));
+
+        // player 2 made the fourth reveal, and player 2 goes first on the next board
+        assert!(roster.finish_board(GameResult::Win, 4));
+        assert_eq!(vec![0, 1], scores(&roster));
+        assert_eq!(1, roster.turn(0));
+        assert_eq!(2, roster.level());
+
+        assert!(roster.finish_board(GameResult::Win, 4));
+        assert_eq!(vec![1, 1], scores(&roster));
+        assert_eq!(Some(&String::from("It's a tie!")), roster.summary().last());
+
+        assert!(!roster.finish_board(GameResult::Lose, 10));
+        assert!(!Roster::new(Turns::Boards).finish_board(GameResult::Quit, 0));
+    }
+
+    fn scores(roster: &Roster) -> Vec<usize> {
+        roster.players.iter().map(|player| player.score).collect()
+    }
+
+    #[test]
+    fn test_results_screen() {
+        let screen_rect = Rect {
+            left: 0,
+            top: 0,
+            width: 30,
+            height: 7,
+        };
+        let buffer = crate::render::Buffer::new(screen_rect.width, screen_rect.height);
+        let summary = vec![
+            String::from("Player 1: 1 board won"),
+            String::from("Player 1 wins!"),
+        ];
+        render_results(&summary, &screen_rect, &buffer);
+        assert_eq!(
+            "\n     Player 1: 1 board won\n        Player 1 wins!\n\n     press any key to exit",
+            buffer.text()
+        );
+        // the winner stands out
+        assert_eq!(Some(Style::Success), buffer.cell(8, 2).unwrap().style);
+        assert_eq!(None, buffer.cell(5, 1).unwrap().style);
+    }
+
+    #[test]
+    fn test_parse_turns() {
+        assert_eq!(Ok(Turns::Boards), "boards".parse());
+        assert_eq!(Ok(Turns::Reveals), "reveals".parse());
+        assert!("cells".parse::<Turns>().is_err());
+    }
+}
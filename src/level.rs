@@ -0,0 +1,429 @@
+// The rules for playing a single board, kept separate from how the board gets drawn so every
+// frontend plays the exact same game.
+
+use crate::game::{GameGrid, GridItem, TrapType};
+use crate::utils::{clock, Timer};
+use crate::GameResult;
+use crate::{LevelConfig, LevelOutcome};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// how long a confusion trap keeps the player confused
+pub const CONFUSION_TIME: Duration = Duration::from_secs(3);
+
+// Something that happened while playing a level, in the order it happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LevelEvent {
+    Revealed {
+        x: i32,
+        y: i32,
+        item: GridItem,
+    },
+    Rehidden {
+        x: i32,
+        y: i32,
+    },
+    Trap {
+        trap_type: TrapType,
+        duration: Duration,
+    },
+    ConfusionEnded,
+    // a treasure worth `value` was collected. Collecting the last one wins the level.
+    Collected {
+        value: u32,
+    },
+    Won,
+    Lost,
+}
+
+pub struct Level {
+    number: usize,
+    config: LevelConfig,
+    grid: GameGrid,
+    timer: Timer,
+    // untimed levels (zen mode) can't be lost. They count up instead.
+    timed: bool,
+    started: Duration,
+    confusion_timer: Option<Timer>,
+    result: Option<GameResult>,
+    frozen_time_left: Duration,
+    frozen_elapsed: Duration,
+    reveals: usize,
+    // what the treasure collected so far is worth, out of what all of it is worth
+    score: u32,
+    treasure_total: u32,
+}
+
+impl Level {
+    pub fn new(number: usize, grid: GameGrid, config: LevelConfig) -> Self {
+        let treasure_total = grid.treasures().iter().map(|(_, _, value)| value).sum();
+        Level {
+            number,
+            config,
+            grid,
+            timer: Timer::new(config.board_time),
+            timed: true,
+            started: clock::now(),
+            confusion_timer: None,
+            result: None,
+            frozen_time_left: config.board_time,
+            frozen_elapsed: Duration::new(0, 0),
+            reveals: 0,
+            score: 0,
+            treasure_total,
+        }
+    }
+
+    // A level without a clock. It only ends once the solution is found.
+    pub fn new_untimed(number: usize, grid: GameGrid, config: LevelConfig) -> Self {
+        Level {
+            timed: false,
+            ..Level::new(number, grid, config)
+        }
+    }
+
+    // A copy of the level with its own clock, started now. Used on freshly generated levels so two
+    // players can race on the same board.
+    pub fn restart(&self) -> Self {
+        Level {
+            timed: self.timed,
+            ..Level::new(self.number, self.grid.clone(), self.config)
+        }
+    }
+
+    pub fn is_timed(&self) -> bool {
+        self.timed
+    }
+
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    // the parameters the level was generated with
+    pub fn config(&self) -> &LevelConfig {
+        &self.config
+    }
+
+    pub fn grid(&self) -> &GameGrid {
+        &self.grid
+    }
+
+    // the level's result once it's been won or lost
+    pub fn result(&self) -> Option<GameResult> {
+        self.result
+    }
+
+    // the time left on the board. It stops counting down once the level is over, and never starts
+    // on untimed levels.
+    pub fn time_left(&self) -> Duration {
+        if !self.timed {
+            self.config.board_time
+        } else if self.result.is_some() {
+            self.frozen_time_left
+        } else {
+            self.timer.time_left()
+        }
+    }
+
+    // how many cells have been revealed so far
+    pub fn reveals(&self) -> usize {
+        self.reveals
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    // Zero unless this is a treasure hunt
+    pub fn treasure_total(&self) -> u32 {
+        self.treasure_total
+    }
+
+    // how long the level has been played for, or took to finish
+    pub fn elapsed(&self) -> Duration {
+        if self.result.is_some() {
+            self.frozen_elapsed
+        } else {
+            clock::now() - self.started
+        }
+    }
+
+    // How the level went. `result` is only used if the level isn't over yet, i.e. when quitting.
+    pub fn outcome(&self, result: GameResult) -> LevelOutcome {
+        LevelOutcome {
+            result: self.result.unwrap_or(result),
+            time_left: self.time_left(),
+            reveals: self.reveals,
+            elapsed: self.elapsed(),
+            score: self.score,
+        }
+    }
+
+    pub fn confusion_time_left(&self) -> Option<Duration> {
+        self.confusion_timer.as_ref().map(|timer| timer.time_left())
+    }
+
+    // Some while the player is confused. Confusion flips the hints back and forth every half
+    // second and the value is whether they're currently flipped.
+    pub fn confusion_state(&self) -> Option<bool> {
+        self.confusion_time_left()
+            .map(|time_left| time_left.as_millis() % 1000 < 500)
+    }
+
+    // Advances the level's timers: hides cells that have been revealed for too long, wears off
+    // confusion, and checks whether we've run out of time.
+    pub fn update(&mut self) -> Vec<LevelEvent> {
+        let mut events = vec![];
+
+        if self.confusion_timer.as_ref().map(Timer::finished) == Some(true) {
+            self.confusion_timer = None;
+            events.push(LevelEvent::ConfusionEnded);
+        }
+
+        if self.result.is_some() {
+            return events;
+        }
+
+        if let Some((x, y)) = self.grid.reset_expired_cells() {
+            events.push(LevelEvent::Rehidden { x, y });
+        }
+
+        if self.timed && self.timer.finished() {
+            self.finish(GameResult::Lose);
+            events.push(LevelEvent::Lost);
+        }
+
+        events
+    }
+
+    pub fn reveal(&mut self, x: i32, y: i32) -> Vec<LevelEvent> {
+        let mut events = vec![];
+        if self.result.is_some() {
+            return events;
+        }
+
+        let item = match self.grid.try_reveal(x, y) {
+            Some(item) => item,
+            None => return events,
+        };
+        self.reveals += 1;
+        events.push(LevelEvent::Revealed { x, y, item });
+
+        match item {
+            GridItem::Solution => {
+                self.finish(GameResult::Win);
+                events.push(LevelEvent::Won);
+            }
+            GridItem::Trap(trap_type) => match trap_type {
+                TrapType::Confusion => {
+                    self.confusion_timer = Some(Timer::new(CONFUSION_TIME));
+                    events.push(LevelEvent::Trap {
+                        trap_type,
+                        duration: CONFUSION_TIME,
+                    });
+                }
+            },
+            GridItem::Treasure(value) => {
+                self.score += value;
+                self.grid.collect_treasure(x, y);
+                events.push(LevelEvent::Collected { value });
+
+                if self.grid.treasures().is_empty() {
+                    self.finish(GameResult::Win);
+                    events.push(LevelEvent::Won);
+                }
+            }
+            _ => (),
+        }
+
+        events
+    }
+
+    // Ends the level as lost even though there's time left, i.e. when another player found their
+    // hidden cell first
+    pub fn concede(&mut self) {
+        if self.result.is_none() {
+            self.finish(GameResult::Lose);
+        }
+    }
+
+    fn finish(&mut self, result: GameResult) {
+        self.frozen_time_left = self.time_left();
+        self.frozen_elapsed = self.elapsed();
+        self.result = Some(result);
+    }
+}
+
+// A level on a random board with a minute on the clock, for tests all over to play with
+#[cfg(test)]
+pub(crate) fn test_level(number: usize, grid_size: (i32, i32)) -> Level {
+    use crate::game::{CellDistribution, DEFAULT_REVEAL_TIME};
+    use snm_rand_utils::range_rng::ThreadRangeRng;
+
+    let config = LevelConfig {
+        grid_size,
+        board_time: Duration::from_secs(60),
+        max_revealed_cells: 3,
+        cell_distribution: CellDistribution::default(),
+        difficulty_band: (0.0, f64::INFINITY),
+    };
+    let grid = GameGrid::new(
+        config.grid_size.0,
+        config.grid_size.1,
+        config.max_revealed_cells,
+        DEFAULT_REVEAL_TIME,
+        config.cell_distribution,
+        &mut ThreadRangeRng::new(),
+    );
+    Level::new(number, grid, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::DEFAULT_REVEAL_TIME;
+    use crate::utils::clock;
+    use snm_rand_utils::range_rng::ThreadRangeRng;
+
+    fn new_level() -> Level {
+        test_level(1, (6, 4))
+    }
+
+    fn find_item(level: &Level, matches: impl Fn(GridItem) -> bool) -> Option<(i32, i32)> {
+        let grid = level.grid();
+        (0..grid.height())
+            .flat_map(|y| (0..grid.width()).map(move |x| (x, y)))
+            .find(|(x, y)| matches(grid.cell(*x, *y).unwrap().item))
+    }
+
+    #[test]
+    fn test_revealing_solution_wins() {
+        let mut level = new_level();
+        let (x, y) = find_item(&level, |item| item == GridItem::Solution).unwrap();
+
+        assert_eq!(
+            vec![
+                LevelEvent::Revealed {
+                    x,
+                    y,
+                    item: GridItem::Solution
+                },
+                LevelEvent::Won
+            ],
+            level.reveal(x, y)
+        );
+        assert_eq!(Some(GameResult::Win), level.result());
+        let outcome = level.outcome(GameResult::Quit);
+        assert_eq!(GameResult::Win, outcome.result);
+        assert_eq!(1, outcome.reveals);
+
+        // nothing else happens once the level is over
+        assert!(level.reveal(x, y).is_empty());
+        assert!(level.update().is_empty());
+    }
+
+    #[test]
+    fn test_restart_copies_the_board() {
+        let level = new_level();
+        let (x, y) = find_item(&level, |item| item == GridItem::Solution).unwrap();
+
+        let mut restarted = level.restart();
+        assert_eq!(level.grid().width(), restarted.grid().width());
+        assert_eq!(level.grid().height(), restarted.grid().height());
+        restarted.reveal(x, y);
+        assert_eq!(Some(GameResult::Win), restarted.result());
+
+        // the copies are played separately
+        assert!(!level.grid().cell(x, y).unwrap().revealed);
+        assert_eq!(None, level.result());
+    }
+
+    #[test]
+    fn test_timers_with_stepped_time() {
+        clock::use_stepped_time();
+        let mut level = new_level();
+        // traps set off effects of their own, so this needs a cell that only gets rehidden
+        let (x, y) = find_item(&level, |item| {
+            matches!(item, GridItem::Empty | GridItem::Hint(_))
+        })
+        .unwrap();
+
+        level.reveal(x, y);
+        clock::step(DEFAULT_REVEAL_TIME - Duration::from_millis(1));
+        assert!(level.update().is_empty());
+
+        clock::step(Duration::from_millis(1));
+        assert_eq!(vec![LevelEvent::Rehidden { x, y }], level.update());
+
+        clock::step(Duration::from_secs(60));
+        assert_eq!(vec![LevelEvent::Lost], level.update());
+        assert_eq!(Some(GameResult::Lose), level.result());
+        assert_eq!(Duration::from_secs(0), level.time_left());
+    }
+
+    #[test]
+    fn test_untimed_levels_count_up() {
+        clock::use_stepped_time();
+        let level = new_level();
+        let mut level = Level::new_untimed(1, level.grid, level.config);
+        assert!(!level.is_timed());
+
+        clock::step(Duration::from_secs(600));
+        assert!(level.update().is_empty());
+        assert_eq!(None, level.result());
+        assert_eq!(Duration::from_secs(60), level.time_left());
+        assert_eq!(Duration::from_secs(600), level.elapsed());
+
+        let (x, y) = find_item(&level, |item| item == GridItem::Solution).unwrap();
+        level.reveal(x, y);
+        clock::step(Duration::from_secs(5));
+        assert_eq!(Some(GameResult::Win), level.result());
+        assert_eq!(Duration::from_secs(600), level.elapsed());
+    }
+
+    #[test]
+    fn test_treasure_hunt() {
+        let config = new_level().config;
+        let grid = GameGrid::new_treasure_hunt(
+            8,
+            6,
+            config.max_revealed_cells,
+            DEFAULT_REVEAL_TIME,
+            config.cell_distribution,
+            &[1, 5],
+            &mut ThreadRangeRng::new(),
+        );
+        let mut level = Level::new(1, grid, config);
+        assert_eq!(6, level.treasure_total());
+        assert_eq!(None, level.grid().find_solution());
+
+        let (x, y) = find_item(&level, |item| item == GridItem::Treasure(5)).unwrap();
+        assert_eq!(
+            vec![
+                LevelEvent::Revealed {
+                    x,
+                    y,
+                    item: GridItem::Treasure(5)
+                },
+                LevelEvent::Collected { value: 5 }
+            ],
+            level.reveal(x, y)
+        );
+        assert_eq!(5, level.score());
+        assert_eq!(None, level.result());
+        assert_eq!(GridItem::Empty, level.grid().cell(x, y).unwrap().item);
+
+        let (x, y) = find_item(&level, |item| item == GridItem::Treasure(1)).unwrap();
+        assert_eq!(Some(&LevelEvent::Won), level.reveal(x, y).last());
+        assert_eq!(6, level.outcome(GameResult::Quit).score);
+    }
+
+    #[test]
+    fn test_revealing_off_the_board_does_nothing() {
+        let mut level = new_level();
+        assert!(level.reveal(-1, 0).is_empty());
+        assert!(level.reveal(0, 4).is_empty());
+        assert_eq!(None, level.result());
+    }
+}
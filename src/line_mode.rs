@@ -0,0 +1,397 @@
+// A frontend that plays over plain lines of text on stdin and stdout so the game works with a screen
+// reader. Cells are named like a spreadsheet: columns are letters starting from A on the left and
+// rows are numbers starting from 1 at the top, i.e. "C7".
+//
+// This never touches pancurses so it runs in any terminal, or without one.
+
+use crate::cli;
+use crate::config;
+use crate::game::{GridItem, HintDir, TrapType};
+use crate::level::{Level, LevelEvent};
+use crate::GameResult;
+use snm_rand_utils::range_rng::ThreadRangeRng;
+use std::sync::mpsc;
+use std::time::Duration;
+
+const HELP: &str = "\
+commands:
+    reveal <cell>   reveal a cell, i.e. 'reveal C7'. just typing the cell works too
+    look            list the cells that are revealed right now
+    time            say how much time is left
+    help            print this message
+    quit            stop playing";
+
+// There's no screen to fit the board on so boards grow as big as the level allows.
+const MAX_GRID_SIZE: (i32, i32) = (i32::MAX, i32::MAX);
+
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Reveal(i32, i32),
+    Look,
+    Time,
+    Help,
+    Quit,
+}
+
+// Reads stdin on its own thread so timers can keep ticking while we wait on the next line. The
+// channel disconnects once stdin is closed.
+pub fn read_lines_in_background() -> mpsc::Receiver<String> {
+    let (line_sender, lines) = mpsc::channel();
+    std::thread::spawn(move || {
+        let stdin = std::io::stdin();
+        let mut line = String::new();
+        while let Ok(read) = stdin.read_line(&mut line) {
+            if read == 0 || line_sender.send(line.clone()).is_err() {
+                break;
+            }
+            line.clear();
+        }
+    });
+
+    lines
+}
+
+pub fn run(options: &cli::Options, accessibility: &config::Accessibility) {
+    let lines = read_lines_in_background();
+
+    let mut rng = ThreadRangeRng::new();
+    println!("Lost-n-Found. Type 'help' for commands.");
+
+    let mut level_number = 1;
+    let mut time_bank = None;
+    loop {
+        let mut level = crate::new_level(
+            level_number,
+            options,
+            accessibility,
+            MAX_GRID_SIZE,
+            time_bank,
+            &mut rng,
+        );
+        match play_level(&mut level, accessibility, &lines) {
+            // a board from a file or a treasure hunt is the only level there is
+            GameResult::Win if options.board.is_some() || options.treasure => return,
+            GameResult::Win => {
+                level_number += 1;
+                time_bank = Some(level.time_left());
+            }
+            GameResult::Lose if options.treasure => {
+                println!(
+                    "Time's up! You collected {} of {}.",
+                    level.score(),
+                    level.treasure_total()
+                );
+                return;
+            }
+            GameResult::Lose => {
+                println!("Game over. You made it to level {}.", level_number);
+                return;
+            }
+            GameResult::Quit => return,
+        }
+    }
+}
+
+fn play_level(
+    level: &mut Level,
+    accessibility: &config::Accessibility,
+    lines: &mpsc::Receiver<String>,
+) -> GameResult {
+    let grid = level.grid();
+    let goal = if level.treasure_total() > 0 {
+        format!(
+            "collect as much of the treasure as you can. It's worth {} in all",
+            level.treasure_total()
+        )
+    } else {
+        String::from("find the hidden cell")
+    };
+    println!(
+        "Level {}. The board is {} columns, A to {}, by {} rows, 1 to {}. You have {} seconds to \
+         {}.",
+        level.number(),
+        grid.width(),
+        column_name(grid.width() - 1),
+        grid.height(),
+        grid.height(),
+        whole_secs(level.time_left()),
+        goal,
+    );
+
+    let mut last_announced_secs = whole_secs(level.time_left());
+    loop {
+        let events = match lines.recv_timeout(Duration::from_millis(50)) {
+            Ok(line) => match parse_command(&line) {
+                Ok(Command::Reveal(x, y)) => {
+                    if level.grid().cell(x, y).is_none() {
+                        println!("{} is off the board", cell_name(x, y));
+                    }
+                    level.reveal(x, y)
+                }
+                Ok(Command::Look) => {
+                    describe_board(level, accessibility);
+                    vec![]
+                }
+                Ok(Command::Time) => {
+                    describe_time(level);
+                    vec![]
+                }
+                Ok(Command::Help) => {
+                    println!("{}", HELP);
+                    vec![]
+                }
+                Ok(Command::Quit) => return GameResult::Quit,
+                Err(e) => {
+                    println!("{}", e);
+                    vec![]
+                }
+            },
+            Err(mpsc::RecvTimeoutError::Timeout) => vec![],
+            // stdin closed, so there's nobody left to play
+            Err(mpsc::RecvTimeoutError::Disconnected) => return GameResult::Quit,
+        };
+
+        for event in events.into_iter().chain(level.update()) {
+            announce_event(level, event);
+        }
+
+        if let Some(result) = level.result() {
+            return result;
+        }
+
+        // announce the time every 10 seconds and then every second once it's nearly up
+        let secs_left = whole_secs(level.time_left());
+        if secs_left != last_announced_secs && (secs_left % 10 == 0 || secs_left <= 5) {
+            println!("{} seconds left", secs_left);
+            last_announced_secs = secs_left;
+        }
+    }
+}
+
+fn announce_event(level: &Level, event: LevelEvent) {
+    match event {
+        LevelEvent::Revealed { x, y, item } => println!(
+            "{}: {}",
+            cell_name(x, y),
+            describe_item(item, level.confusion_state())
+        ),
+        LevelEvent::Rehidden { x, y } => println!("{} is hidden again", cell_name(x, y)),
+        LevelEvent::Trap {
+            trap_type: TrapType::Confusion,
+            duration,
+        } => println!(
+            "trap: confusion for {}s. hints may point the wrong way",
+            duration.as_secs()
+        ),
+        LevelEvent::ConfusionEnded => println!("confusion wore off"),
+        LevelEvent::Collected { value } => println!(
+            "collected {}. score: {} of {}",
+            value,
+            level.score(),
+            level.treasure_total()
+        ),
+        LevelEvent::Won => println!(
+            "Level {} complete with {} seconds left!",
+            level.number(),
+            whole_secs(level.time_left())
+        ),
+        LevelEvent::Lost => match level.grid().find_solution() {
+            Some((x, y)) => println!("Time's up! It was at {}.", cell_name(x, y)),
+            None => println!("Time's up!"),
+        },
+    }
+}
+
+fn describe_board(level: &Level, accessibility: &config::Accessibility) {
+    let grid = level.grid();
+    let mut revealed = vec![];
+    let mut seen = vec![];
+    for y in 0..grid.height() {
+        for x in 0..grid.width() {
+            let grid_cell = grid.cell(x, y).unwrap();
+            let description = format!(
+                "{} {}",
+                cell_name(x, y),
+                describe_item(grid_cell.item, level.confusion_state())
+            );
+
+            if grid_cell.revealed {
+                revealed.push(description);
+            } else if let (true, GridItem::Hint(_)) = (grid_cell.seen, grid_cell.item) {
+                seen.push(description);
+            }
+        }
+    }
+
+    if revealed.is_empty() {
+        println!("nothing is revealed");
+    } else {
+        println!("revealed: {}", revealed.join(", "));
+    }
+
+    if accessibility.show_seen_hints && !seen.is_empty() {
+        println!("seen before: {}", seen.join(", "));
+    }
+}
+
+fn describe_time(level: &Level) {
+    println!("{} seconds left", whole_secs(level.time_left()));
+    if let Some(confusion_time_left) = level.confusion_time_left() {
+        println!(
+            "confused for {} more seconds",
+            whole_secs(confusion_time_left)
+        );
+    }
+}
+
+// While confused, hints flip back and forth and whatever way they're facing right now is what the
+// player gets told.
+fn describe_item(item: GridItem, confusion_state: Option<bool>) -> String {
+    let direction = |hint_dir: HintDir| {
+        let hint_dir = if confusion_state == Some(true) {
+            hint_dir.flip()
+        } else {
+            hint_dir
+        };
+
+        match hint_dir {
+            HintDir::Left => "left",
+            HintDir::Right => "right",
+            HintDir::Up => "up",
+            HintDir::Down => "down",
+        }
+    };
+
+    match item {
+        GridItem::Solution => String::from("found it"),
+        GridItem::Hint(hint_dir) => format!("hint pointing {}", direction(hint_dir)),
+        GridItem::ValueHint(hint_dir) => format!(
+            "hint pointing {} to the most valuable treasure",
+            direction(hint_dir)
+        ),
+        GridItem::Trap(TrapType::Confusion) => String::from("confusion trap"),
+        GridItem::Treasure(value) => format!("treasure worth {}", value),
+        GridItem::Empty => String::from("empty"),
+    }
+}
+
+// rounds up so the time doesn't read 0 until it's actually out
+fn whole_secs(time: Duration) -> u64 {
+    (time + Duration::from_millis(999)).as_secs()
+}
+
+// columns are lettered like a spreadsheet: A through Z, then AA, AB, ...
+fn column_name(x: i32) -> String {
+    let mut name = vec![];
+    let mut remaining = x + 1;
+    while remaining > 0 {
+        let letter = (remaining - 1) % 26;
+        name.push((b'A' + letter as u8) as char);
+        remaining = (remaining - 1) / 26;
+    }
+
+    name.iter().rev().collect()
+}
+
+fn cell_name(x: i32, y: i32) -> String {
+    format!("{}{}", column_name(x), y + 1)
+}
+
+fn parse_cell(name: &str) -> Option<(i32, i32)> {
+    let row_start = name.find(|c: char| c.is_ascii_digit())?;
+    let (column, row) = name.split_at(row_start);
+    if column.is_empty() || !column.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let mut x: i32 = 0;
+    for c in column.to_ascii_uppercase().bytes() {
+        x = x.checked_mul(26)?.checked_add(i32::from(c - b'A') + 1)?;
+    }
+
+    let y = row.parse::<i32>().ok().filter(|row| *row > 0)?;
+    Some((x - 1, y - 1))
+}
+
+fn parse_command(line: &str) -> Result<Command, String> {
+    let mut words = line.split_whitespace();
+    let command = words.next().unwrap_or("");
+    let argument = words.next();
+    if words.next().is_some() {
+        return Err(format!("too many words in '{}'", line.trim()));
+    }
+
+    let parse_cell_argument = |cell: &str| {
+        parse_cell(cell)
+            .map(|(x, y)| Command::Reveal(x, y))
+            .ok_or_else(|| format!("'{}' isn't a cell. cells look like C7", cell))
+    };
+
+    match (command.to_ascii_lowercase().as_str(), argument) {
+        ("reveal", Some(cell)) | ("r", Some(cell)) => parse_cell_argument(cell),
+        ("reveal", None) | ("r", None) => Err(String::from("reveal which cell? i.e. 'reveal C7'")),
+        ("look", None) => Ok(Command::Look),
+        ("time", None) => Ok(Command::Time),
+        ("help", None) => Ok(Command::Help),
+        ("quit", None) | ("exit", None) => Ok(Command::Quit),
+        ("", None) => Err(String::from("type 'help' for commands")),
+        (_, None) if parse_cell(command).is_some() => parse_cell_argument(command),
+        _ => Err(format!(
+            "unknown command '{}'. type 'help' for commands",
+            line.trim()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_names() {
+        assert_eq!("A1", cell_name(0, 0));
+        assert_eq!("C7", cell_name(2, 6));
+        assert_eq!("Z10", cell_name(25, 9));
+        assert_eq!("AA1", cell_name(26, 0));
+        assert_eq!("BA2", cell_name(52, 1));
+
+        for x in 0..100 {
+            assert_eq!(Some((x, 4)), parse_cell(&cell_name(x, 4)));
+        }
+
+        assert_eq!(Some((2, 6)), parse_cell("c7"));
+        assert_eq!(None, parse_cell("C0"));
+        assert_eq!(None, parse_cell("7"));
+        assert_eq!(None, parse_cell("C"));
+        assert_eq!(None, parse_cell("C7x"));
+        assert_eq!(None, parse_cell("3C"));
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(Ok(Command::Reveal(2, 6)), parse_command("reveal C7\n"));
+        assert_eq!(Ok(Command::Reveal(2, 6)), parse_command("  c7 "));
+        assert_eq!(Ok(Command::Reveal(0, 9)), parse_command("R a10"));
+        assert_eq!(Ok(Command::Look), parse_command("look"));
+        assert_eq!(Ok(Command::Time), parse_command("TIME"));
+        assert_eq!(Ok(Command::Quit), parse_command("quit"));
+
+        assert!(parse_command("").is_err());
+        assert!(parse_command("reveal").is_err());
+        assert!(parse_command("reveal 7C").is_err());
+        assert!(parse_command("reveal C7 D8").is_err());
+        assert!(parse_command("dance").is_err());
+    }
+
+    #[test]
+    fn test_describe_item() {
+        let hint = GridItem::Hint(HintDir::Left);
+        assert_eq!("hint pointing left", describe_item(hint, None));
+        assert_eq!("hint pointing left", describe_item(hint, Some(false)));
+        assert_eq!("hint pointing right", describe_item(hint, Some(true)));
+        assert_eq!(
+            "confusion trap",
+            describe_item(GridItem::Trap(TrapType::Confusion), None)
+        );
+    }
+}
@@ -0,0 +1,468 @@
+// A JSON-lines interface for driving the game from other programs, i.e. bots and regression tests.
+// It's turned on with --machine and like line mode it never touches pancurses.
+//
+// Every line on stdin is one JSON command:
+//
+//     {"cmd": "reveal", "x": 2, "y": 6}   reveal the cell at column 2, row 6 (both from 0)
+//     {"cmd": "state"}                    ask for a "state" event describing the board
+//     {"cmd": "wait", "ms": 500}          let time pass, then get a "waited" event
+//     {"cmd": "quit"}                     stop playing
+//
+// Every line on stdout is one JSON event. Each event has an "event" field saying what it is and a
+// "time_ms" field with how far into the session it happened:
+//
+//     level_started    a new board. "level", "width", "height", "board_time_ms",
+//                      "max_revealed_cells", "reveal_time_ms" and "cell_distribution"
+//                      ({"traps", "empties", "hints"}) describe it. With --survival,
+//                      "board_time_ms" includes the time carried over from the last board.
+//                      With --treasure, "treasure_total" is what all the treasure is worth
+//     revealed         "x", "y" and the "item" that was under the cell
+//     rehidden         "x" and "y" of a cell that was hidden again
+//     trap             a trap went off. "trap" says which kind and "duration_ms" how long it lasts
+//     confusion_ended  the confusion trap wore off
+//     collected        a treasure worth "value" was picked up, bringing the "score" up
+//     won              the level was beaten with "time_left_ms" to spare. The next level's
+//                      "level_started" event follows right after, unless the board came from
+//                      --board or it was a treasure hunt, in which case this is the last event.
+//                      Treasure hunts are won by collecting every treasure
+//     lost             time ran out. "solution" has the "x" and "y" of the cell that was missed,
+//                      or is null on a treasure hunt. This is the last event before the game exits
+//     state            "level", "time_left_ms", "confusion_ms" (null unless confused), "width",
+//                      "height", and "cells": one array per row where hidden cells are null and
+//                      revealed cells are their item
+//     waited           a wait command finished
+//     error            a command couldn't be handled. "message" says why
+//
+// Items look like {"kind": "hint", "value": "left"}, {"kind": "trap", "value": "confusion"},
+// {"kind": "empty"} or {"kind": "solution"}. Treasure hunts add {"kind": "treasure", "value": 3}
+// and {"kind": "value_hint", "value": "up"}, a hint pointing at the most valuable treasure left.
+// Items are always reported as they really are, even while confused.
+//
+// With wall clock timing (the default) the game runs in real time and events can show up at any
+// point. With stepped timing the game clock only moves during wait commands, so a session plays
+// out exactly the same way no matter how fast the other side sends commands.
+
+use crate::cli;
+use crate::config;
+use crate::level::{Level, LevelEvent};
+use crate::line_mode;
+use crate::utils::clock;
+use crate::GameResult;
+use serde::Deserialize;
+use serde_json::json;
+use snm_rand_utils::range_rng::ThreadRangeRng;
+use std::io::Write;
+use std::sync::mpsc;
+use std::time::Duration;
+
+// how often the game gets updated while time is passing
+const TICK: Duration = Duration::from_millis(10);
+
+// Like line mode, there's no screen limiting how big boards can get.
+const MAX_GRID_SIZE: (i32, i32) = (i32::MAX, i32::MAX);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Timing {
+    #[default]
+    WallClock,
+    Stepped,
+}
+
+impl std::str::FromStr for Timing {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "wall" => Ok(Timing::WallClock),
+            "step" => Ok(Timing::Stepped),
+            _ => Err(format!(
+                "invalid timing '{}' (expected wall or step)",
+                value
+            )),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum Command {
+    Reveal { x: i32, y: i32 },
+    State,
+    Wait { ms: u64 },
+    Quit,
+}
+
+struct Session<'a, W: Write> {
+    options: &'a cli::Options,
+    accessibility: &'a config::Accessibility,
+    timing: Timing,
+    rng: ThreadRangeRng,
+    level: Level,
+    start_time: Duration,
+    out: W,
+}
+
+pub fn run(options: &cli::Options, accessibility: &config::Accessibility, timing: Timing) {
+    if timing == Timing::Stepped {
+        clock::use_stepped_time();
+    }
+
+    let lines = line_mode::read_lines_in_background();
+    let stdout = std::io::stdout();
+    let mut session = Session::new(options, accessibility, timing, stdout.lock());
+
+    loop {
+        let line = match timing {
+            // keep the game running in between commands
+            Timing::WallClock => match lines.recv_timeout(TICK) {
+                Ok(line) => line,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if !session.update() {
+                        return;
+                    }
+                    continue;
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+            },
+            Timing::Stepped => match lines.recv() {
+                Ok(line) => line,
+                Err(_) => return,
+            },
+        };
+
+        if !session.handle_line(&line) {
+            return;
+        }
+    }
+}
+
+impl<'a, W: Write> Session<'a, W> {
+    fn new(
+        options: &'a cli::Options,
+        accessibility: &'a config::Accessibility,
+        timing: Timing,
+        out: W,
+    ) -> Self {
+        let mut rng = ThreadRangeRng::new();
+        let level = crate::new_level(1, options, accessibility, MAX_GRID_SIZE, None, &mut rng);
+        let mut session = Session {
+            options,
+            accessibility,
+            timing,
+            rng,
+            level,
+            start_time: clock::now(),
+            out,
+        };
+
+        session.emit_level_started();
+        session
+    }
+
+    // Handles one line of input. Returns false once the session is over.
+    fn handle_line(&mut self, line: &str) -> bool {
+        if line.trim().is_empty() {
+            return true;
+        }
+
+        let command = match serde_json::from_str::<Command>(line) {
+            Ok(command) => command,
+            Err(e) => {
+                self.emit(json!({ "event": "error", "message": e.to_string() }));
+                return true;
+            }
+        };
+
+        match command {
+            Command::Reveal { x, y } => {
+                if self.level.grid().cell(x, y).is_none() {
+                    let message = format!("({}, {}) is off the board", x, y);
+                    self.emit(json!({ "event": "error", "message": message }));
+                    return true;
+                }
+
+                let events = self.level.reveal(x, y);
+                self.handle_events(events)
+            }
+            Command::State => {
+                self.emit_state();
+                true
+            }
+            Command::Wait { ms } => {
+                if !self.wait(Duration::from_millis(ms)) {
+                    return false;
+                }
+
+                self.emit(json!({ "event": "waited" }));
+                true
+            }
+            Command::Quit => false,
+        }
+    }
+
+    // Advances the level. Returns false once the session is over.
+    fn update(&mut self) -> bool {
+        let events = self.level.update();
+        self.handle_events(events)
+    }
+
+    fn wait(&mut self, duration: Duration) -> bool {
+        let end_time = clock::now() + duration;
+        while clock::now() < end_time {
+            let tick = std::cmp::min(TICK, end_time - clock::now());
+            match self.timing {
+                Timing::WallClock => std::thread::sleep(tick),
+                Timing::Stepped => clock::step(tick),
+            }
+
+            if !self.update() {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn handle_events(&mut self, events: Vec<LevelEvent>) -> bool {
+        for event in events {
+            self.emit_level_event(event);
+        }
+
+        match self.level.result() {
+            Some(GameResult::Win) if self.options.board.is_some() || self.options.treasure => false,
+            Some(GameResult::Win) => {
+                self.level = crate::new_level(
+                    self.level.number() + 1,
+                    self.options,
+                    self.accessibility,
+                    MAX_GRID_SIZE,
+                    Some(self.level.time_left()),
+                    &mut self.rng,
+                );
+                self.emit_level_started();
+                true
+            }
+            Some(_) => false,
+            None => true,
+        }
+    }
+
+    fn emit_level_event(&mut self, event: LevelEvent) {
+        let level = &self.level;
+        let event = match event {
+            LevelEvent::Revealed { x, y, item } => {
+                json!({ "event": "revealed", "x": x, "y": y, "item": item })
+            }
+            LevelEvent::Rehidden { x, y } => json!({ "event": "rehidden", "x": x, "y": y }),
+            LevelEvent::Trap {
+                trap_type,
+                duration,
+            } => json!({
+                "event": "trap",
+                "trap": trap_type,
+                "duration_ms": duration.as_millis() as u64,
+            }),
+            LevelEvent::ConfusionEnded => json!({ "event": "confusion_ended" }),
+            LevelEvent::Collected { value } => json!({
+                "event": "collected",
+                "value": value,
+                "score": level.score(),
+            }),
+            LevelEvent::Won => json!({
+                "event": "won",
+                "level": level.number(),
+                "time_left_ms": level.time_left().as_millis() as u64,
+            }),
+            LevelEvent::Lost => {
+                let solution = level
+                    .grid()
+                    .find_solution()
+                    .map(|(x, y)| json!({ "x": x, "y": y }));
+                json!({ "event": "lost", "level": level.number(), "solution": solution })
+            }
+        };
+
+        self.emit(event);
+    }
+
+    fn emit_level_started(&mut self) {
+        let config = self.level.config();
+        let mut event = json!({
+            "event": "level_started",
+            "level": self.level.number(),
+            "width": config.grid_size.0,
+            "height": config.grid_size.1,
+            "board_time_ms": config.board_time.as_millis() as u64,
+            "max_revealed_cells": config.max_revealed_cells,
+            "reveal_time_ms": self.accessibility.reveal_time.as_millis() as u64,
+            "cell_distribution": config.cell_distribution,
+        });
+        if self.options.treasure {
+            event["treasure_total"] = json!(self.level.treasure_total());
+        }
+        self.emit(event);
+    }
+
+    fn emit_state(&mut self) {
+        let grid = self.level.grid();
+        let cells: Vec<Vec<_>> = (0..grid.height())
+            .map(|y| {
+                (0..grid.width())
+                    .map(|x| {
+                        let grid_cell = grid.cell(x, y).unwrap();
+                        if grid_cell.revealed {
+                            Some(grid_cell.item)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let event = json!({
+            "event": "state",
+            "level": self.level.number(),
+            "time_left_ms": self.level.time_left().as_millis() as u64,
+            "confusion_ms": self.level.confusion_time_left().map(|time| time.as_millis() as u64),
+            "width": grid.width(),
+            "height": grid.height(),
+            "cells": cells,
+        });
+        self.emit(event);
+    }
+
+    fn emit(&mut self, mut event: serde_json::Value) {
+        event["time_ms"] = json!((clock::now() - self.start_time).as_millis() as u64);
+
+        // if whoever's reading our output has gone away there's nobody left to tell
+        let _ = writeln!(self.out, "{}", event).and_then(|_| self.out.flush());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn new_session<'a>(
+        options: &'a cli::Options,
+        accessibility: &'a config::Accessibility,
+    ) -> Session<'a, Vec<u8>> {
+        clock::use_stepped_time();
+        Session::new(options, accessibility, Timing::Stepped, vec![])
+    }
+
+    // takes every event written so far
+    fn events(session: &mut Session<Vec<u8>>) -> Vec<Value> {
+        let out = std::mem::take(&mut session.out);
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            Command::Reveal { x: 2, y: 6 },
+            serde_json::from_str(r#"{"cmd": "reveal", "x": 2, "y": 6}"#).unwrap()
+        );
+        assert_eq!(
+            Command::Wait { ms: 500 },
+            serde_json::from_str(r#"{"cmd": "wait", "ms": 500}"#).unwrap()
+        );
+        assert_eq!(
+            Command::State,
+            serde_json::from_str(r#"{"cmd": "state"}"#).unwrap()
+        );
+
+        assert!(serde_json::from_str::<Command>(r#"{"cmd": "reveal", "x": 2}"#).is_err());
+        assert!(serde_json::from_str::<Command>(r#"{"cmd": "dance"}"#).is_err());
+    }
+
+    #[test]
+    fn test_winning_starts_the_next_level() {
+        let options = cli::Options::default();
+        let accessibility = config::Accessibility::default();
+        let mut session = new_session(&options, &accessibility);
+
+        let started = events(&mut session);
+        assert_eq!(1, started.len());
+        assert_eq!("level_started", started[0]["event"]);
+        assert_eq!(1, started[0]["level"]);
+
+        let (x, y) = session.level.grid().find_solution().unwrap();
+        let command = json!({ "cmd": "reveal", "x": x, "y": y }).to_string();
+        assert!(session.handle_line(&command));
+
+        let events = events(&mut session);
+        let names: Vec<_> = events.iter().map(|event| &event["event"]).collect();
+        assert_eq!(vec!["revealed", "won", "level_started"], names);
+        assert_eq!(json!({ "kind": "solution" }), events[0]["item"]);
+        assert_eq!(2, events[2]["level"]);
+    }
+
+    #[test]
+    fn test_survival_carries_time_over() {
+        let options = cli::Options {
+            survival: true,
+            ..cli::Options::default()
+        };
+        let accessibility = config::Accessibility::default();
+        let mut session = new_session(&options, &accessibility);
+        let board_time_ms = events(&mut session)[0]["board_time_ms"].as_u64().unwrap();
+
+        assert!(session.handle_line(r#"{"cmd": "wait", "ms": 4000}"#));
+        let (x, y) = session.level.grid().find_solution().unwrap();
+        let command = json!({ "cmd": "reveal", "x": x, "y": y }).to_string();
+        assert!(session.handle_line(&command));
+
+        let started = events(&mut session).pop().unwrap();
+        assert_eq!("level_started", started["event"]);
+        assert_eq!(
+            board_time_ms - 4000 + board_time_ms / 3,
+            started["board_time_ms"]
+        );
+    }
+
+    #[test]
+    fn test_stepped_time_only_moves_on_wait() {
+        let options = cli::Options::default();
+        let accessibility = config::Accessibility::default();
+        let mut session = new_session(&options, &accessibility);
+        let board_time_ms = events(&mut session)[0]["board_time_ms"].as_u64().unwrap();
+
+        assert!(session.handle_line(r#"{"cmd": "state"}"#));
+        let state = &events(&mut session)[0];
+        assert_eq!(board_time_ms, state["time_left_ms"]);
+        assert_eq!(Value::Null, state["cells"][0][0]);
+
+        assert!(session.handle_line(r#"{"cmd": "wait", "ms": 1000}"#));
+        assert!(session.handle_line(r#"{"cmd": "state"}"#));
+        let waited = events(&mut session);
+        assert_eq!("waited", waited[0]["event"]);
+        assert_eq!(1000, waited[0]["time_ms"]);
+        assert_eq!(board_time_ms - 1000, waited[1]["time_left_ms"]);
+
+        // waiting out the whole board loses and ends the session
+        let wait = json!({ "cmd": "wait", "ms": board_time_ms }).to_string();
+        assert!(!session.handle_line(&wait));
+        assert_eq!("lost", events(&mut session).last().unwrap()["event"]);
+    }
+
+    #[test]
+    fn test_bad_commands_report_errors() {
+        let options = cli::Options::default();
+        let accessibility = config::Accessibility::default();
+        let mut session = new_session(&options, &accessibility);
+        events(&mut session);
+
+        assert!(session.handle_line("not json"));
+        assert!(session.handle_line(r#"{"cmd": "reveal", "x": -1, "y": 0}"#));
+        let events = events(&mut session);
+        assert_eq!(2, events.len());
+        assert!(events.iter().all(|event| event["event"] == "error"));
+    }
+}
@@ -3,8 +3,29 @@ extern crate snm_rand_utils;
 
 use snm_rand_utils::range_rng::*;
 
+mod board_file;
+mod campaign;
+mod cli;
+mod config;
+mod difficulty;
+mod editor;
+mod hot_seat;
+mod level;
+mod line_mode;
+mod machine;
+mod net;
+mod race;
+mod record;
+mod render;
+mod simulate;
+mod solver;
+mod spectate;
+mod speedrun;
+mod theme;
+mod versus;
+
 mod utils {
-    #[derive(Debug, PartialEq, Eq)]
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
     pub struct Rect {
         pub left: i32,
         pub top: i32,
@@ -32,21 +53,81 @@ mod utils {
         }
     }
 
+    // The game clock only advances while the game is actually running. Any time spent suspended
+    // (i.e. after a ctrl-z) is subtracted out so that timers pick up right where they left off.
+    //
+    // The clock can also be switched over to stepped time, where it only moves when it's told to.
+    // That's for driving the game from a program rather than in real time.
+    pub mod clock {
+        use std::cell::Cell;
+        use std::time::{Duration, Instant};
+
+        thread_local! {
+            static EPOCH: Instant = Instant::now();
+            static SUSPENDED_TIME: Cell<Duration> = const { Cell::new(Duration::new(0, 0)) };
+            static SUSPEND_START: Cell<Option<Instant>> = const { Cell::new(None) };
+            static STEPPED_TIME: Cell<Option<Duration>> = const { Cell::new(None) };
+        }
+
+        pub fn now() -> Duration {
+            if let Some(stepped_time) = STEPPED_TIME.with(|time| time.get()) {
+                return stepped_time;
+            }
+
+            let wall_time = EPOCH.with(|epoch| epoch.elapsed());
+            let in_progress_suspension = SUSPEND_START
+                .with(|start| start.get())
+                .map(|start| start.elapsed())
+                .unwrap_or_default();
+            let suspended_time = SUSPENDED_TIME.with(|time| time.get()) + in_progress_suspension;
+            wall_time - suspended_time
+        }
+
+        pub fn suspend() {
+            SUSPEND_START.with(|start| {
+                if start.get().is_none() {
+                    start.set(Some(Instant::now()));
+                }
+            });
+        }
+
+        pub fn resume() {
+            if let Some(suspend_start) = SUSPEND_START.with(|start| start.take()) {
+                SUSPENDED_TIME.with(|time| time.set(time.get() + suspend_start.elapsed()));
+            }
+        }
+
+        // stops the clock where it is. From here on it only moves forward through `step`.
+        pub fn use_stepped_time() {
+            let now = now();
+            STEPPED_TIME.with(|time| time.set(Some(now)));
+        }
+
+        pub fn step(duration: Duration) {
+            STEPPED_TIME.with(|time| {
+                let stepped_time = time.get().expect("the clock isn't using stepped time");
+                time.set(Some(stepped_time + duration));
+            });
+        }
+    }
+
+    #[derive(Clone)]
     pub struct Timer {
-        start_time: std::time::Instant,
+        start_time: std::time::Duration,
         duration: std::time::Duration,
     }
 
     impl Timer {
         pub fn new(duration: std::time::Duration) -> Self {
             Timer {
-                start_time: std::time::Instant::now(),
+                start_time: clock::now(),
                 duration,
             }
         }
 
         pub fn time_left(&self) -> std::time::Duration {
-            self.duration - std::cmp::min(self.start_time.elapsed(), self.duration)
+            let elapsed = clock::now() - self.start_time;
+            self.duration - std::cmp::min(elapsed, self.duration)
         }
 
         pub fn finished(&self) -> bool {
@@ -55,17 +136,50 @@ mod utils {
             self.time_left() == zero
         }
     }
+
+    // A small random number generator that always gives the same numbers for the same seed, for
+    // when a board needs to be generated again exactly (i.e. simulations). This is splitmix64.
+    pub struct SeededRng {
+        state: u64,
+    }
+
+    impl SeededRng {
+        pub fn new(seed: u64) -> Self {
+            SeededRng { state: seed }
+        }
+
+        pub fn next_u64(&mut self) -> u64 {
+            self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = self.state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        }
+    }
+
+    impl snm_rand_utils::range_rng::RangeRng<usize> for SeededRng {
+        fn gen_range(&mut self, lower: usize, upper: usize) -> usize {
+            assert!(lower < upper);
+            lower + (self.next_u64() % (upper - lower) as u64) as usize
+        }
+    }
 }
 
+use utils::clock;
 use utils::Rect;
 use utils::Timer;
 
+use render::{Glyph, Surface};
+use theme::Style;
+
 const TITLE: &str = "Lost-n-Found";
 
 mod game {
     use super::*;
+    use serde::{Deserialize, Serialize};
 
-    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
     pub enum HintDir {
         Left,
         Up,
@@ -84,35 +198,115 @@ mod game {
         }
     }
 
-    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
     pub enum TrapType {
         Confusion,
     }
 
-    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    // serialized as i.e. {"kind": "hint", "value": "left"} or {"kind": "solution"}
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(tag = "kind", content = "value", rename_all = "snake_case")]
     pub enum GridItem {
         Solution,
         Hint(HintDir),
+        // In treasure hunts plain hints point at the nearest treasure and these point at the most
+        // valuable one
+        ValueHint(HintDir),
         Trap(TrapType),
+        // one of the valuable items hidden on a treasure hunt board, and what it's worth
+        Treasure(u32),
         Empty,
     }
 
-    #[derive(Debug, Clone, Copy)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
     pub struct GridCell {
         pub item: GridItem,
         pub revealed: bool,
+        // whether the cell has ever been revealed, even if it's since been hidden again
+        pub seen: bool,
+    }
+
+    // The odds of each kind of item being generated for a non-solution cell. i.e. out of every
+    // `total()` cells, `traps` will be traps, `empties` will be duds, and `hints` will be hints.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+    pub struct CellDistribution {
+        pub traps: usize,
+        pub empties: usize,
+        pub hints: usize,
+    }
+
+    impl CellDistribution {
+        pub fn total(&self) -> usize {
+            self.traps + self.empties + self.hints
+        }
+    }
+
+    impl Default for CellDistribution {
+        fn default() -> Self {
+            // 70% chance of generating a hint, 20% chance of generating a dud, 10% of generating a trap
+            CellDistribution {
+                traps: 1,
+                empties: 2,
+                hints: 7,
+            }
+        }
+    }
+
+    // one in this many hints on a treasure hunt board is a value hint
+    const VALUE_HINT_ODDS: usize = 4;
+
+    // how long a revealed cell stays revealed before it's hidden again
+    pub const DEFAULT_REVEAL_TIME: std::time::Duration = std::time::Duration::from_secs(4);
+
+    // Which way a hint points given how far it is from the solution. The bigger of the two
+    // displacements wins, with ties going to the vertical direction.
+    pub fn hint_direction(x_displacement: i32, y_displacement: i32) -> HintDir {
+        assert!(x_displacement != 0 || y_displacement != 0);
+        if x_displacement.abs() > y_displacement.abs() {
+            if x_displacement > 0 {
+                HintDir::Left
+            } else {
+                HintDir::Right
+            }
+        } else {
+            if y_displacement > 0 {
+                HintDir::Up
+            } else {
+                HintDir::Down
+            }
+        }
+    }
+
+    // Rolls what goes in a cell that isn't hiding anything, using `hint` if it comes up a hint.
+    fn roll_item(
+        cell_distribution: CellDistribution,
+        hint: GridItem,
+        rng: &mut dyn RangeRng<usize>,
+    ) -> GridItem {
+        let random_cell = rng.gen_range(0, cell_distribution.total());
+        if random_cell < cell_distribution.traps {
+            GridItem::Trap(TrapType::Confusion)
+        } else if random_cell < cell_distribution.traps + cell_distribution.empties {
+            GridItem::Empty
+        } else {
+            hint
+        }
     }
 
+    #[derive(Clone)]
     struct CellTimer {
         x: i32,
         y: i32,
         timer: Timer,
     }
 
+    #[derive(Clone)]
     pub struct GameGrid {
         cells: Box<[GridCell]>,
         timers: Vec<CellTimer>,
         max_revealed_cells: usize,
+        reveal_time: std::time::Duration,
         width: i32,
         height: i32,
     }
@@ -122,6 +316,8 @@ mod game {
             width: i32,
             height: i32,
             max_revealed_cells: usize,
+            reveal_time: std::time::Duration,
+            cell_distribution: CellDistribution,
             rng: &mut dyn RangeRng<usize>,
         ) -> Self {
             let solution_cell = (
@@ -130,87 +326,116 @@ mod game {
             );
 
             let num_cells = (width * height) as usize;
-            let mut cells = Vec::with_capacity(num_cells);
+            let mut items = Vec::with_capacity(num_cells);
             for row in 0..height {
                 for col in 0..width {
-                    fn displacement_to_hint_direction(
-                        x_displacement: i32,
-                        y_displacement: i32,
-                    ) -> HintDir {
-                        assert!(x_displacement != 0 || y_displacement != 0);
-                        if x_displacement.abs() > y_displacement.abs() {
-                            if x_displacement > 0 {
-                                HintDir::Left
-                            } else {
-                                HintDir::Right
-                            }
-                        } else {
-                            if y_displacement > 0 {
-                                HintDir::Up
-                            } else {
-                                HintDir::Down
-                            }
-                        }
-                    }
-
                     let x_displacement = col - solution_cell.0;
                     let y_displacement = row - solution_cell.1;
-                    let item = {
-                        if x_displacement == 0 && y_displacement == 0 {
-                            GridItem::Solution
-                        } else {
-                            enum RandomCell {
-                                Empty,
-                                Trap,
-                                Hint,
-                            }
-
-                            // 70% chance of generating a hint, 20% chance of generating a dud, 10% of generating a trap
-                            const RANDOM_CELL_DISTRIBUTION: [RandomCell; 10] = [
-                                RandomCell::Trap,
-                                RandomCell::Empty,
-                                RandomCell::Empty,
-                                RandomCell::Hint,
-                                RandomCell::Hint,
-                                RandomCell::Hint,
-                                RandomCell::Hint,
-                                RandomCell::Hint,
-                                RandomCell::Hint,
-                                RandomCell::Hint,
-                            ];
-
-                            let random_cell = snm_rand_utils::range_rng::select_rand(
-                                &RANDOM_CELL_DISTRIBUTION,
-                                rng,
-                            );
-
-                            match random_cell {
-                                RandomCell::Empty => GridItem::Empty,
-                                RandomCell::Trap => GridItem::Trap(TrapType::Confusion),
-                                RandomCell::Hint => GridItem::Hint(displacement_to_hint_direction(
-                                    x_displacement,
-                                    y_displacement,
-                                )),
-                            }
-                        }
+                    let item = if x_displacement == 0 && y_displacement == 0 {
+                        GridItem::Solution
+                    } else {
+                        let hint = GridItem::Hint(hint_direction(x_displacement, y_displacement));
+                        roll_item(cell_distribution, hint, rng)
                     };
 
-                    cells.push(GridCell {
-                        item,
-                        revealed: false,
-                    });
+                    items.push(item);
+                }
+            }
+
+            GameGrid::from_items(width, height, items, max_revealed_cells, reveal_time)
+        }
+
+        // A board for a treasure hunt, with a treasure for each of `treasure_values` instead of a
+        // single solution. Boards too small for all of them leave at least one cell for a hint and
+        // drop the treasures at the end of the list.
+        pub fn new_treasure_hunt(
+            width: i32,
+            height: i32,
+            max_revealed_cells: usize,
+            reveal_time: std::time::Duration,
+            cell_distribution: CellDistribution,
+            treasure_values: &[u32],
+            rng: &mut dyn RangeRng<usize>,
+        ) -> Self {
+            let num_cells = (width * height) as usize;
+            let max_treasures = std::cmp::max(num_cells - 1, 1);
+            let treasure_values =
+                &treasure_values[..std::cmp::min(treasure_values.len(), max_treasures)];
+
+            let mut items = vec![None; num_cells];
+            for value in treasure_values {
+                loop {
+                    let index = rng.gen_range(0, num_cells);
+                    if items[index].is_none() {
+                        items[index] = Some(GridItem::Treasure(*value));
+                        break;
+                    }
                 }
             }
 
+            // the hints get pointed the right way once every treasure is in place
+            let items = items
+                .into_iter()
+                .map(|item| {
+                    item.unwrap_or_else(|| {
+                        let hint = if rng.gen_range(0, VALUE_HINT_ODDS) == 0 {
+                            GridItem::ValueHint(HintDir::Left)
+                        } else {
+                            GridItem::Hint(HintDir::Left)
+                        };
+                        roll_item(cell_distribution, hint, rng)
+                    })
+                })
+                .collect();
+
+            let mut grid =
+                GameGrid::from_items(width, height, items, max_revealed_cells, reveal_time);
+            grid.point_hints_at_treasure();
+            grid
+        }
+
+        // A grid with the given items laid out row by row, i.e. from a board file
+        pub fn from_items(
+            width: i32,
+            height: i32,
+            items: Vec<GridItem>,
+            max_revealed_cells: usize,
+            reveal_time: std::time::Duration,
+        ) -> Self {
+            assert_eq!((width * height) as usize, items.len());
+            let cells: Vec<GridCell> = items
+                .into_iter()
+                .map(|item| GridCell {
+                    item,
+                    revealed: false,
+                    seen: false,
+                })
+                .collect();
+
             GameGrid {
                 cells: cells.into_boxed_slice(),
                 timers: Vec::with_capacity(max_revealed_cells + 1),
                 max_revealed_cells,
+                reveal_time,
                 width,
                 height,
             }
         }
 
+        // A grid showing exactly the given cells, i.e. one being watched from another terminal.
+        // Nothing ever gets revealed or hidden on it.
+        pub fn from_cells(width: i32, height: i32, cells: Vec<GridCell>) -> Self {
+            let items = cells.iter().map(|cell| cell.item).collect();
+            let mut grid = GameGrid::from_items(width, height, items, 0, std::time::Duration::ZERO);
+            grid.cells = cells.into_boxed_slice();
+            grid
+        }
+
+        // every cell, row by row
+        pub fn cells(&self) -> &[GridCell] {
+            &self.cells
+        }
+
         pub fn width(&self) -> i32 {
             self.width
         }
@@ -219,6 +444,17 @@ mod game {
             self.height
         }
 
+        // shows every cell for good, i.e. for the editor
+        pub fn reveal_all(&mut self) {
+            for cell in self.cells.iter_mut() {
+                cell.revealed = true;
+            }
+        }
+
+        pub fn max_revealed_cells(&self) -> usize {
+            self.max_revealed_cells
+        }
+
         pub fn cell(&self, x: i32, y: i32) -> Option<GridCell> {
             if x < 0 || x >= self.width || y < 0 || y >= self.height {
                 return None;
@@ -228,6 +464,68 @@ mod game {
             Some(self.cells[index])
         }
 
+        // the position and value of every treasure still on the board
+        pub fn treasures(&self) -> Vec<(i32, i32, u32)> {
+            (0..self.height)
+                .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+                .filter_map(|(x, y)| match self.cell(x, y).unwrap().item {
+                    GridItem::Treasure(value) => Some((x, y, value)),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        // Takes the treasure at (x, y) off the board and turns the hints towards what's left.
+        pub fn collect_treasure(&mut self, x: i32, y: i32) {
+            if let Some(grid_cell) = self.mut_cell(x, y) {
+                if let GridItem::Treasure(_) = grid_cell.item {
+                    grid_cell.item = GridItem::Empty;
+                    self.point_hints_at_treasure();
+                }
+            }
+        }
+
+        // Points plain hints at the nearest treasure and value hints at the most valuable one,
+        // going with the nearest when there's a tie. Boards without treasure are left alone.
+        fn point_hints_at_treasure(&mut self) {
+            let treasures = self.treasures();
+            if treasures.is_empty() {
+                return;
+            }
+
+            let width = self.width;
+            for (index, grid_cell) in self.cells.iter_mut().enumerate() {
+                let (x, y) = (index as i32 % width, index as i32 / width);
+                let distance = |(treasure_x, treasure_y, _): &(i32, i32, u32)| {
+                    (treasure_x - x).pow(2) + (treasure_y - y).pow(2)
+                };
+                let direction = |(treasure_x, treasure_y, _): &(i32, i32, u32)| {
+                    hint_direction(x - treasure_x, y - treasure_y)
+                };
+
+                match grid_cell.item {
+                    GridItem::Hint(_) => {
+                        let nearest = treasures.iter().min_by_key(|t| distance(t)).unwrap();
+                        grid_cell.item = GridItem::Hint(direction(nearest));
+                    }
+                    GridItem::ValueHint(_) => {
+                        let most_valuable = treasures
+                            .iter()
+                            .min_by_key(|t| (std::cmp::Reverse(t.2), distance(t)))
+                            .unwrap();
+                        grid_cell.item = GridItem::ValueHint(direction(most_valuable));
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        pub fn find_solution(&self) -> Option<(i32, i32)> {
+            (0..self.height)
+                .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+                .find(|(x, y)| self.cell(*x, *y).unwrap().item == GridItem::Solution)
+        }
+
         fn mut_cell(&mut self, x: i32, y: i32) -> Option<&mut GridCell> {
             if x < 0 || x >= self.width || y < 0 || y >= self.height {
                 return None;
@@ -240,6 +538,7 @@ mod game {
         pub fn try_reveal(&mut self, x: i32, y: i32) -> Option<GridItem> {
             let revealed_item = self.mut_cell(x, y).map(|mut_cell| {
                 mut_cell.revealed = true;
+                mut_cell.seen = true;
                 mut_cell.item
             });
 
@@ -247,16 +546,18 @@ mod game {
                 self.timers.push(CellTimer {
                     x,
                     y,
-                    timer: Timer::new(std::time::Duration::from_secs(4)),
+                    timer: Timer::new(self.reveal_time),
                 });
             }
 
             revealed_item
         }
 
-        pub fn reset_expired_cells(&mut self) {
+        // Hides the oldest revealed cell if it's been revealed for too long or if too many cells are
+        // revealed. Returns the cell that got hidden.
+        pub fn reset_expired_cells(&mut self) -> Option<(i32, i32)> {
             if self.timers.is_empty() {
-                return;
+                return None;
             }
 
             if self.timers.len() > self.max_revealed_cells || self.timers[0].timer.finished() {
@@ -265,7 +566,10 @@ mod game {
                     .mut_cell(oldest_cell_timer.x, oldest_cell_timer.y)
                     .unwrap();
                 cell_to_revert.revealed = false;
+                return Some((oldest_cell_timer.x, oldest_cell_timer.y));
             }
+
+            None
         }
     }
 }
@@ -290,237 +594,1045 @@ mod xform {
     //
     // where a single game-grid cell actually comprises of a 3x2 block of window cells/chars.
     // Addiitionally it's important to remember that the grid may (will) be offset within the window.
+    //
+    // That 3x2 block is just the default. The cell metrics describe how big each cell's block is.
+    // Every cell is followed by a vertical bar and the last row of each cell is underlined to
+    // double as the horizontal border, so the pattern above holds for any cell size.
+    //
+    // Boards can also be bigger than the window. In that case only the slice of the grid that the
+    // camera is looking at gets rendered, and the camera's top-left cell sits at the grid's origin.
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Camera {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        grid_width: i32,
+        grid_height: i32,
+    }
+
+    impl Camera {
+        // a camera looking at the entire grid
+        pub fn new(grid_width: i32, grid_height: i32) -> Self {
+            Camera {
+                x: 0,
+                y: 0,
+                width: grid_width,
+                height: grid_height,
+                grid_width,
+                grid_height,
+            }
+        }
+
+        pub fn x(&self) -> i32 {
+            self.x
+        }
+
+        pub fn y(&self) -> i32 {
+            self.y
+        }
+
+        pub fn width(&self) -> i32 {
+            self.width
+        }
+
+        pub fn height(&self) -> i32 {
+            self.height
+        }
+
+        pub fn grid_width(&self) -> i32 {
+            self.grid_width
+        }
+
+        pub fn grid_height(&self) -> i32 {
+            self.grid_height
+        }
+
+        // true if the whole grid is visible (i.e. there's nowhere to scroll)
+        pub fn sees_whole_grid(&self) -> bool {
+            self.width == self.grid_width && self.height == self.grid_height
+        }
+
+        pub fn contains(&self, x: i32, y: i32) -> bool {
+            x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+        }
+
+        // Resizes the viewport (capped to the size of the grid) and keeps it inside the grid
+        pub fn resize(&mut self, width: i32, height: i32) {
+            self.width = std::cmp::max(std::cmp::min(width, self.grid_width), 0);
+            self.height = std::cmp::max(std::cmp::min(height, self.grid_height), 0);
+            self.scroll(0, 0);
+        }
+
+        pub fn scroll(&mut self, dx: i32, dy: i32) {
+            let max_x = self.grid_width - self.width;
+            let max_y = self.grid_height - self.height;
+            self.x = std::cmp::max(std::cmp::min(self.x + dx, max_x), 0);
+            self.y = std::cmp::max(std::cmp::min(self.y + dy, max_y), 0);
+        }
+
+        // Scrolls just far enough that the cell at (x, y) is in view
+        pub fn scroll_to_show(&mut self, x: i32, y: i32) {
+            let distance_outside = |pos: i32, low: i32, size: i32| {
+                if pos < low {
+                    pos - low
+                } else if pos >= low + size {
+                    pos - (low + size - 1)
+                } else {
+                    0
+                }
+            };
+
+            self.scroll(
+                distance_outside(x, self.x, self.width),
+                distance_outside(y, self.y, self.height),
+            );
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct CellMetrics {
+        pub width: i32,
+        pub height: i32,
+    }
+
+    impl Default for CellMetrics {
+        fn default() -> Self {
+            CellMetrics::NORMAL
+        }
+    }
+
+    impl CellMetrics {
+        // a single character per cell for squeezing big boards onto small terminals
+        pub const COMPACT: CellMetrics = CellMetrics {
+            width: 1,
+            height: 1,
+        };
+        pub const NORMAL: CellMetrics = CellMetrics {
+            width: 3,
+            height: 2,
+        };
+        // big chunky cells that are easier to read
+        pub const LARGE: CellMetrics = CellMetrics {
+            width: 5,
+            height: 3,
+        };
+
+        // how many window columns we move right for every grid cell we move right
+        pub const fn stride_x(&self) -> i32 {
+            // every cell is followed by a vertical bar
+            self.width + 1
+        }
+
+        // how many window rows we move down for every grid cell we move down
+        pub const fn stride_y(&self) -> i32 {
+            // the last row of the cell already includes the next horizontal border
+            self.height
+        }
+    }
 
     // Add space between cells in the grid when we render them to the window.
     // That'll just make it easier to see each cell
-    pub fn game_grid_to_window(x: i32, y: i32, grid_left: i32, grid_top: i32) -> Rect {
+    pub fn game_grid_to_window(
+        x: i32,
+        y: i32,
+        grid_left: i32,
+        grid_top: i32,
+        camera: &Camera,
+        metrics: &CellMetrics,
+    ) -> Rect {
         // To calculate the horizontal range of cells in the window...
         // 1. account for the grids left offset (where is the grid rendered in the window)
         // 2. account for the leading vertical border cell
-        // 3. skip 3 cell spaces and the next vertical bar for every grid cell you move right
-        let window_left = grid_left + 1 + (3 + 1) * x;
+        // 3. account for how far right the camera has scrolled
+        // 4. skip the cell's width and the next vertical bar for every grid cell you move right
+        let window_left = grid_left + 1 + metrics.stride_x() * (x - camera.x);
 
         // To calculate the vertical range of cells in the window...
         // 1. account for the grids top offset (where is the grid rendered in the window)
         // 2. account for the leading horizontal border cell
-        // 3. skip 2 cell spaces (the second cell space also includes the next horizontal border)
-        let window_top = grid_top + 1 + 2 * y;
-
-        // every grid cell in the window is a 2x2 cell of characters
-        let width = 3;
-        let height = 2;
+        // 3. account for how far down the camera has scrolled
+        // 4. skip the cell's height for every grid cell you move down (the last row of the cell
+        //    also includes the next horizontal border)
+        let window_top = grid_top + 1 + metrics.stride_y() * (y - camera.y);
 
+        // every grid cell in the window is a block of characters the size of the cell metrics
         Rect {
             left: window_left,
             top: window_top,
-            width,
-            height,
+            width: metrics.width,
+            height: metrics.height,
         }
     }
 
-    pub fn window_to_game_grid(x: i32, y: i32, grid_left: i32, grid_top: i32) -> (i32, i32) {
+    pub fn window_to_game_grid(
+        x: i32,
+        y: i32,
+        grid_left: i32,
+        grid_top: i32,
+        camera: &Camera,
+        metrics: &CellMetrics,
+    ) -> (i32, i32) {
         // first shift our window position so that our grid is aligned at the origin
         // additionally subtract an additional 1 to account for the grid border
         let window_at_origin = (x - grid_left - 1, y - grid_top - 1);
 
-        // finally divide the x portion by 4 (3 cells + a border)
-        // and divide the y portion by 2 (2 cells one of which includes the next border)
-        (window_at_origin.0 / 4, window_at_origin.1 / 2)
+        // next divide the x portion by the horizontal stride (the cell + a border)
+        // and divide the y portion by the vertical stride (the last row includes the next border)
+        // finally shift by the camera position to get back to the full grid's coords
+        (
+            window_at_origin.0 / metrics.stride_x() + camera.x,
+            window_at_origin.1 / metrics.stride_y() + camera.y,
+        )
     }
 }
 
-fn setup_pancurses_mouse() {
-    let mut oldmask: pancurses::mmask_t = 0;
-    let mousemask = pancurses::BUTTON1_CLICKED | pancurses::REPORT_MOUSE_POSITION;
-    pancurses::mousemask(mousemask, Some(&mut oldmask));
-}
+// helpers for deciding where everything goes on screen given the current terminal size
+mod layout {
+    use super::*;
 
-enum Color {
-    BlackOnGreen,
-    BlackOnYellow,
-    BlackOnRed,
-    BlackOnBlue,
-    BlackOnWhite,
-    BlackOnGray,
-    BlackOnDarkGray,
-    BlackOnOrange,
-}
+    pub struct BoardLayout {
+        pub grid: Rect,
+        pub level: Rect,
+        pub time: Rect,
+        pub game_over: Rect,
+    }
 
-impl Color {
-    fn to_num(&self) -> u8 {
-        match self {
-            Color::BlackOnGreen => 1,
-            Color::BlackOnYellow => 2,
-            Color::BlackOnRed => 3,
-            Color::BlackOnBlue => 4,
-            Color::BlackOnWhite => 5,
-            Color::BlackOnGray => 6,
-            Color::BlackOnDarkGray => 7,
-            Color::BlackOnOrange => 8,
+    pub fn screen_rect(window: &pancurses::Window) -> Rect {
+        Rect {
+            left: 0,
+            top: 0,
+            width: window.get_max_x(),
+            height: window.get_max_y(),
         }
     }
 
-    fn setup() {
-        pancurses::start_color();
-
-        pancurses::init_color(pancurses::COLOR_GREEN, 500, 1000, 500);
-        pancurses::init_pair(
-            Color::BlackOnGreen.to_num() as i16,
-            pancurses::COLOR_BLACK,
-            pancurses::COLOR_GREEN,
+    // The board layout is the grid centered on the screen with the level and timer header stacked
+    // above it and the game over text below it. Returns None if that doesn't fit on the screen.
+    pub fn board_layout(
+        grid_width: i32,
+        grid_height: i32,
+        metrics: &xform::CellMetrics,
+        screen: &Rect,
+    ) -> Option<BoardLayout> {
+        // Not using the rect straight from the xform because this grid isn't ACTUALLY sized
+        // normally like a rect. There are spaces
+        let grid_bounds = xform::game_grid_to_window(
+            grid_width,
+            grid_height,
+            0,
+            0,
+            &xform::Camera::new(grid_width, grid_height),
+            metrics,
         );
+        let grid = Rect {
+            left: screen.left + (screen.width - grid_bounds.right()) / 2,
+            top: screen.top + (screen.height - grid_bounds.bottom()) / 2,
+            width: grid_bounds.right(),
+            height: grid_bounds.bottom(),
+        };
 
-        pancurses::init_color(pancurses::COLOR_YELLOW, 750, 750, 500);
-        pancurses::init_pair(
-            Color::BlackOnYellow.to_num() as i16,
-            pancurses::COLOR_BLACK,
-            pancurses::COLOR_YELLOW,
-        );
+        let game_over = Rect {
+            left: grid.left,
+            top: grid.bottom() + 2,
+            width: grid.width,
+            height: 2,
+        };
 
-        pancurses::init_color(pancurses::COLOR_BLUE, 500, 500, 1000);
-        pancurses::init_pair(
-            Color::BlackOnBlue.to_num() as i16,
-            pancurses::COLOR_BLACK,
-            pancurses::COLOR_BLUE,
-        );
+        let time = Rect {
+            left: grid.left,
+            top: grid.top - 4,
+            width: 30,
+            height: 2,
+        };
 
-        pancurses::init_color(pancurses::COLOR_RED, 1000, 500, 500);
-        pancurses::init_pair(
-            Color::BlackOnRed.to_num() as i16,
-            pancurses::COLOR_BLACK,
-            pancurses::COLOR_RED,
-        );
+        let level = Rect {
+            left: time.left,
+            top: time.top - 1,
+            width: 30,
+            height: 2,
+        };
 
-        pancurses::init_pair(
-            Color::BlackOnWhite.to_num() as i16,
-            pancurses::COLOR_BLACK,
-            pancurses::COLOR_WHITE,
-        );
+        let fits_on_screen = grid.left >= screen.left
+            && grid.right() <= screen.right()
+            && level.top >= screen.top
+            && game_over.bottom() <= screen.bottom();
+
+        if fits_on_screen {
+            Some(BoardLayout {
+                grid,
+                level,
+                time,
+                game_over,
+            })
+        } else {
+            None
+        }
+    }
 
-        const CUSTOM_GRAY: i16 = 10;
-        pancurses::init_color(CUSTOM_GRAY, 500, 500, 500);
-        pancurses::init_pair(
-            Color::BlackOnGray.to_num() as i16,
-            pancurses::COLOR_BLACK,
-            CUSTOM_GRAY,
-        );
+    // Points the camera at as much of the grid as fits on the screen and lays the board out around
+    // that. Returns None if the screen can't even fit a reasonably sized slice of the grid.
+    pub fn camera_layout(
+        camera: &mut xform::Camera,
+        metrics: &xform::CellMetrics,
+        screen: &Rect,
+    ) -> Option<BoardLayout> {
+        let (max_width, max_height) = max_grid_size(metrics, screen);
+        camera.resize(max_width, max_height);
+
+        let (min_width, min_height) = min_viewport_size(camera);
+        if camera.width() < min_width || camera.height() < min_height {
+            return None;
+        }
 
-        const CUSTOM_DARK_GRAY: i16 = 11;
-        pancurses::init_color(CUSTOM_DARK_GRAY, 250, 250, 250);
-        pancurses::init_pair(
-            Color::BlackOnDarkGray.to_num() as i16,
-            pancurses::COLOR_BLACK,
-            CUSTOM_DARK_GRAY,
-        );
+        board_layout(camera.width(), camera.height(), metrics, screen)
+    }
 
-        const CUSTOM_ORANGE: i16 = 12;
-        pancurses::init_color(CUSTOM_ORANGE, 750, 450, 0);
-        pancurses::init_pair(
-            Color::BlackOnOrange.to_num() as i16,
-            pancurses::COLOR_BLACK,
-            CUSTOM_ORANGE,
-        );
+    // the smallest slice of a grid that's still worth playing through a camera
+    pub fn min_viewport_size(camera: &xform::Camera) -> (i32, i32) {
+        const MIN_VIEWPORT_SIZE: (i32, i32) = (4, 4);
+        (
+            std::cmp::min(camera.grid_width(), MIN_VIEWPORT_SIZE.0),
+            std::cmp::min(camera.grid_height(), MIN_VIEWPORT_SIZE.1),
+        )
     }
 
-    pub fn to_color_pair(&self) -> pancurses::chtype {
-        pancurses::COLOR_PAIR(self.to_num() as pancurses::chtype)
+    // The minimap sits in the top-right corner of the screen and is only shown when the camera
+    // can't see the whole grid. Returns the minimap's rect along with how many grid cells wide and
+    // tall each character of the minimap covers.
+    pub fn minimap_layout(camera: &xform::Camera, screen: &Rect) -> Option<(Rect, (i32, i32))> {
+        // keep the minimap inside the header rows above the grid
+        const MINIMAP_HEIGHT: i32 = 4;
+
+        if camera.sees_whole_grid() {
+            return None;
+        }
+
+        // Grid cells are roughly square on screen (twice as many columns as rows, see xform) while
+        // characters are about twice as tall as they are wide. Cover twice as many rows per
+        // character to keep the shape.
+        let rows_per_char = (camera.grid_height() + MINIMAP_HEIGHT - 1) / MINIMAP_HEIGHT;
+        let cols_per_char = std::cmp::max(rows_per_char / 2, 1);
+        let width = (camera.grid_width() + cols_per_char - 1) / cols_per_char;
+        let height = (camera.grid_height() + rows_per_char - 1) / rows_per_char;
+
+        let minimap = Rect {
+            left: screen.right() - width,
+            top: screen.top,
+            width,
+            height,
+        };
+        Some((minimap, (cols_per_char, rows_per_char)))
     }
-}
 
-#[derive(PartialEq, Eq)]
-enum GameResult {
-    Win,
-    Lose,
+    // Splits the screen into `count` side by side areas, one for each board being played
+    pub fn split_screen(screen: &Rect, count: usize) -> Vec<Rect> {
+        let count = count as i32;
+        (0..count)
+            .map(|index| {
+                let left = screen.left + screen.width * index / count;
+                let right = screen.left + screen.width * (index + 1) / count;
+                Rect {
+                    left,
+                    top: screen.top,
+                    width: right - left,
+                    height: screen.height,
+                }
+            })
+            .collect()
+    }
+
+    // the largest grid whose board layout still fits on the given screen
+    pub fn max_grid_size(metrics: &xform::CellMetrics, screen: &Rect) -> (i32, i32) {
+        let (min_width, min_height) = min_screen_size(0, 0, metrics);
+
+        // every extra grid cell costs one stride's worth of columns and rows (see xform)
+        (
+            (screen.width - min_width) / metrics.stride_x(),
+            (screen.height - min_height) / metrics.stride_y(),
+        )
+    }
+
+    // the smallest screen that the board layout for a grid of the given size fits on
+    pub fn min_screen_size(
+        grid_width: i32,
+        grid_height: i32,
+        metrics: &xform::CellMetrics,
+    ) -> (i32, i32) {
+        const HEADER_HEIGHT: i32 = 5;
+
+        // the grid is always centered so we need room for the header both above and below it
+        let grid_bounds = xform::game_grid_to_window(
+            grid_width,
+            grid_height,
+            0,
+            0,
+            &xform::Camera::new(grid_width, grid_height),
+            metrics,
+        );
+        (
+            grid_bounds.right(),
+            grid_bounds.bottom() + 2 * HEADER_HEIGHT,
+        )
+    }
 }
 
-struct GameOverState {
-    result: GameResult,
-    msg_timer: Timer,
-    frozen_game_time: std::time::Duration,
+fn setup_pancurses_mouse() {
+    let mut oldmask: pancurses::mmask_t = 0;
+    let mousemask = pancurses::BUTTON1_CLICKED
+        | pancurses::BUTTON4_PRESSED
+        | pancurses::BUTTON5_PRESSED
+        | pancurses::REPORT_MOUSE_POSITION;
+    pancurses::mousemask(mousemask, Some(&mut oldmask));
 }
 
-fn main() {
-    let window = pancurses::initscr();
-    pancurses::noecho(); // prevent key inputs rendering to the screen
-    pancurses::cbreak();
-    pancurses::curs_set(0);
-    pancurses::set_title(TITLE);
-    setup_pancurses_mouse();
-    window.nodelay(true); // don't block waiting for key inputs (we'll poll)
-    window.keypad(true); // let special keys be captured by the program (i.e. esc/backspace/del/arrow keys)
-
-    const WIN: Rect = Rect {
-        left: 0,
-        top: 0,
-        width: 100,
-        height: 60,
-    };
-    pancurses::resize_term(WIN.height, WIN.width);
+// helpers for owning the terminal while the game is running and handing it back in a sane state
+// when we exit (cleanly, via a signal, or via a panic)
+mod term {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    // tracks whether curses currently owns the terminal so that we never restore it twice
+    // (i.e. once in the panic hook and again when the Terminal is dropped while unwinding)
+    static CURSES_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+    fn setup_curses_modes(window: &pancurses::Window) {
+        pancurses::noecho(); // prevent key inputs rendering to the screen
+        pancurses::cbreak();
+        pancurses::curs_set(0);
+        setup_pancurses_mouse();
+        window.nodelay(true); // don't block waiting for key inputs (we'll poll)
+        window.keypad(true); // let special keys be captured by the program (i.e. esc/backspace/del/arrow keys)
+    }
 
-    Color::setup();
-    let mut level = 1;
-    loop {
-        let result = run_game(level, &window);
-        if result == GameResult::Lose {
-            break;
+    fn restore_terminal() {
+        if CURSES_ACTIVE.swap(false, Ordering::SeqCst) {
+            pancurses::mousemask(0, None);
+            pancurses::curs_set(1);
+            pancurses::nocbreak();
+            pancurses::echo();
+            pancurses::endwin();
         }
+    }
 
-        level += 1;
+    pub struct Terminal {
+        window: pancurses::Window,
     }
-}
 
-#[derive(Debug)]
-struct MouseState {
-    click: bool,
-    x: i32,
-    y: i32,
-}
+    impl Terminal {
+        pub fn init() -> Self {
+            let window = pancurses::initscr();
+            CURSES_ACTIVE.store(true, Ordering::SeqCst);
+            setup_curses_modes(&window);
+            pancurses::set_title(TITLE);
+
+            // make sure a panic message is readable and the shell is usable afterwards
+            let default_panic_hook = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |panic_info| {
+                restore_terminal();
+                default_panic_hook(panic_info);
+            }));
+
+            Terminal { window }
+        }
 
-fn get_mouse_update(window: &pancurses::Window) -> Option<MouseState> {
-    if let Some(pancurses::Input::KeyMouse) = window.getch() {
-        if let Ok(mouse_event) = pancurses::getmouse() {
-            return Some(MouseState {
-                click: (mouse_event.bstate & pancurses::BUTTON1_CLICKED) != 0,
-                x: mouse_event.x,
-                y: mouse_event.y,
-            });
+        pub fn window(&self) -> &pancurses::Window {
+            &self.window
         }
     }
 
-    None
+    // Puts the frame that's been drawn on screen, and in the recording if there is one
+    pub fn present(window: &pancurses::Window) {
+        window.refresh();
+        record::frame(window);
+    }
+
+    impl Drop for Terminal {
+        fn drop(&mut self) {
+            restore_terminal();
+        }
+    }
+
+    pub struct Signals {
+        quit: Arc<AtomicBool>,
+        suspend: Arc<AtomicBool>,
+        resumed: Arc<AtomicBool>,
+    }
+
+    impl Signals {
+        pub fn register() -> std::io::Result<Self> {
+            use signal_hook::consts::{SIGINT, SIGTERM};
+
+            let signals = Signals {
+                quit: Arc::new(AtomicBool::new(false)),
+                suspend: Arc::new(AtomicBool::new(false)),
+                resumed: Arc::new(AtomicBool::new(false)),
+            };
+
+            signal_hook::flag::register(SIGINT, Arc::clone(&signals.quit))?;
+            signal_hook::flag::register(SIGTERM, Arc::clone(&signals.quit))?;
+
+            #[cfg(unix)]
+            {
+                use signal_hook::consts::{SIGCONT, SIGTSTP};
+                signal_hook::flag::register(SIGTSTP, Arc::clone(&signals.suspend))?;
+                signal_hook::flag::register(SIGCONT, Arc::clone(&signals.resumed))?;
+            }
+
+            Ok(signals)
+        }
+
+        pub fn quit_requested(&self) -> bool {
+            self.quit.load(Ordering::SeqCst)
+        }
+
+        // acts as if we'd been sent a quit signal
+        pub fn request_quit(&self) {
+            self.quit.store(true, Ordering::SeqCst);
+        }
+
+        // Services any pending suspend/continue signals. Should be called once per frame.
+        pub fn handle_suspend(&self, window: &pancurses::Window) {
+            if self.suspend.swap(false, Ordering::SeqCst) {
+                suspend(window);
+            }
+
+            // we may have been stopped by something other than a ctrl-z (i.e. SIGSTOP), in which
+            // case the screen may have been trampled on while we were away. Force a full redraw.
+            if self.resumed.swap(false, Ordering::SeqCst) {
+                window.clearok(true);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    fn suspend(window: &pancurses::Window) {
+        clock::suspend();
+        restore_terminal();
+
+        // We replaced the default SIGTSTP handler so we could clean up first. Now actually stop.
+        // This call returns once we've been continued (i.e. via `fg`).
+        let _ = signal_hook::low_level::emulate_default_handler(signal_hook::consts::SIGTSTP);
+
+        // a refresh after endwin puts curses back in charge of the terminal
+        CURSES_ACTIVE.store(true, Ordering::SeqCst);
+        window.refresh();
+        setup_curses_modes(window);
+        window.clearok(true);
+        clock::resume();
+    }
+
+    #[cfg(not(unix))]
+    fn suspend(_window: &pancurses::Window) {}
 }
 
-fn render_level_header(level: usize, level_rect: &Rect, window: &pancurses::Window) {
-    window.mvaddstr(level_rect.top, level_rect.left, format!("Level: {}", level));
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum GameResult {
+    Win,
+    Lose,
+    Quit,
 }
 
-fn render_game_timer(
-    time_remaining: std::time::Duration,
-    time_rect: &Rect,
-    window: &pancurses::Window,
-) {
-    assert!(time_remaining >= std::time::Duration::new(0, 0));
-    window.mvaddstr(
+// who's picking the cells to reveal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pilot {
+    Player,
+    Solver,
+    // the solver plays behind the title screen until the player presses a key
+    Attract,
+}
+
+// how a level went once run_game is done with it
+#[derive(Debug, Clone, Copy)]
+struct LevelOutcome {
+    result: GameResult,
+    time_left: std::time::Duration,
+    reveals: usize,
+    elapsed: std::time::Duration,
+    // what the treasure collected on a treasure hunt was worth
+    score: u32,
+}
+
+// what a board needs to know about the boards played before it in the same run
+#[derive(Default)]
+struct RunState {
+    // the time left over from the last board, which survival mode plays this one on
+    time_bank: Option<std::time::Duration>,
+    speedrun: Option<speedrun::Run>,
+    // who's playing in hot-seat mode, and whose turn it is
+    hot_seat: Option<hot_seat::Roster>,
+    // the score so far in split-screen versus
+    versus: Option<versus::Match>,
+    // the network race this board is part of
+    race: Option<race::Race>,
+    // anyone watching with `lnf watch`
+    spectators: Option<spectate::Server>,
+}
+
+struct GameOverState {
+    result: GameResult,
+    msg_timer: Timer,
+}
+
+fn main() {
+    let options = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if options.help {
+        print!("{}", cli::USAGE);
+        return;
+    }
+
+    let mut config = match config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    options.apply_to(&mut config);
+    if options.save_config {
+        if let Err(e) = config::save(&config) {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+    }
+
+    if let Some(export_dir) = &options.export_dir {
+        if let Err(e) = std::fs::create_dir_all(export_dir) {
+            eprintln!("error: failed to create {}: {}", export_dir.display(), e);
+            std::process::exit(2);
+        }
+    }
+
+    if let cli::Command::Simulate(simulate_options) = &options.command {
+        simulate::run(simulate_options);
+        return;
+    }
+
+    if options.machine {
+        machine::run(&options, &config.accessibility, options.timing);
+        return;
+    }
+
+    if options.line_mode {
+        line_mode::run(&options, &config.accessibility);
+        return;
+    }
+
+    let editing = match &options.command {
+        cli::Command::Edit(path) => match editor::open(path, options.board_size) {
+            Ok(board) => Some((path, board)),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(2);
+            }
+        },
+        _ => None,
+    };
+
+    let mut personal_bests = speedrun::PersonalBests::new();
+    if options.speedrun.is_some() {
+        personal_bests = match speedrun::load_personal_bests() {
+            Ok(personal_bests) => personal_bests,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(2);
+            }
+        };
+    }
+
+    let campaign = match &options.command {
+        cli::Command::Campaign => match campaign::open() {
+            Ok(campaign) => Some(campaign),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(2);
+            }
+        },
+        _ => None,
+    };
+
+    // network races connect before taking over the terminal so connection errors can be printed
+    let name = options.name.clone().unwrap_or_else(race::default_name);
+    let race = match &options.command {
+        cli::Command::Host => race::host(
+            options.port.unwrap_or(race::DEFAULT_PORT),
+            options.race_boards.unwrap_or(race::DEFAULT_BOARDS),
+            &name,
+        )
+        .map(|(race, port)| Some((race, Some(port)))),
+        cli::Command::Join(addr) => race::join(addr, &name).map(|race| Some((race, None))),
+        _ => Ok(None),
+    };
+    let race = match race {
+        Ok(race) => race,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let spectators = match options.spectate_port {
+        Some(port) => match spectate::Server::listen(&format!("127.0.0.1:{}", port)) {
+            Ok(spectators) => Some(spectators),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+
+    let watcher = match &options.command {
+        cli::Command::Watch(addr) => match spectate::Watcher::connect(addr) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(2);
+            }
+        },
+        _ => None,
+    };
+
+    let recorder = match &options.record {
+        Some(path) => match record::Recorder::create(path) {
+            Ok(recorder) => Some(recorder),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+
+    let signals = term::Signals::register().expect("failed to register signal handlers");
+    let terminal = term::Terminal::init();
+    let window = terminal.window();
+
+    theme::setup(config.theme.unwrap_or_default());
+
+    if let Some(recorder) = recorder {
+        if let Err(e) = record::start(recorder, window) {
+            drop(terminal);
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+    }
+
+    if let Some((path, board)) = editing {
+        editor::run(
+            path,
+            board,
+            &options,
+            &config.accessibility,
+            window,
+            &signals,
+        );
+        return;
+    }
+
+    if let Some(campaign) = campaign {
+        campaign::run(campaign, &options, &config.accessibility, window, &signals);
+        return;
+    }
+
+    if let Some(watcher) = watcher {
+        spectate::run(watcher, &options, &config.accessibility, window, &signals);
+        return;
+    }
+
+    if let Some((race, hosting)) = race {
+        let summary = race::run(
+            race,
+            hosting,
+            &options,
+            &config.accessibility,
+            window,
+            &signals,
+        );
+        drop(terminal);
+        match summary {
+            Ok(summary) => {
+                for line in summary {
+                    println!("{}", line);
+                }
+            }
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let pilot = if options.autoplay {
+        Pilot::Solver
+    } else {
+        if !run_title_screen(&options, &config.accessibility, window, &signals) {
+            return;
+        }
+        Pilot::Player
+    };
+
+    if let Some(controls) = options.versus {
+        let summary = versus::run(controls, &options, &config.accessibility, window, &signals);
+        drop(terminal);
+        for line in summary {
+            println!("{}", line);
+        }
+        return;
+    }
+
+    let mut level = 1;
+    let mut run = RunState {
+        time_bank: None,
+        speedrun: options
+            .speedrun
+            .map(|levels| speedrun::Run::new(levels, &personal_bests)),
+        hot_seat: options.hot_seat.map(hot_seat::Roster::new),
+        versus: None,
+        race: None,
+        spectators,
+    };
+    // zen mode is scored by how many reveals it takes to find each hidden cell
+    let mut zen_score = (0, 0);
+    let mut treasure_score;
+    loop {
+        let outcome = run_game(
+            level,
+            pilot,
+            &mut run,
+            &options,
+            &config.accessibility,
+            window,
+            &signals,
+        );
+
+        if outcome.result == GameResult::Win {
+            zen_score = (zen_score.0 + 1, zen_score.1 + outcome.reveals);
+        }
+        treasure_score = outcome.score;
+
+        // in hot-seat mode the roster keeps track of every player's level
+        if let Some(roster) = &mut run.hot_seat {
+            if !roster.finish_board(outcome.result, outcome.reveals) {
+                break;
+            }
+            level = roster.level();
+            continue;
+        }
+
+        // a board from a file or a treasure hunt is the only level there is
+        if outcome.result != GameResult::Win || options.board.is_some() || options.treasure {
+            break;
+        }
+
+        level += 1;
+        run.time_bank = Some(outcome.time_left);
+        if let Some(speedrun) = &mut run.speedrun {
+            speedrun.finish_level(outcome.elapsed);
+            if speedrun.is_finished() {
+                break;
+            }
+        }
+    }
+
+    if let Some(roster) = &run.hot_seat {
+        if !signals.quit_requested() {
+            hot_seat::run_results_screen(&roster.summary(), window, &signals);
+        }
+    }
+
+    drop(terminal);
+    if let Some(roster) = &run.hot_seat {
+        for line in roster.summary() {
+            println!("{}", line);
+        }
+    } else if let Some(speedrun) = &run.speedrun {
+        print!("{}", speedrun::summary(speedrun));
+        if speedrun.is_personal_best() {
+            personal_bests.insert(speedrun.levels, speedrun.splits.clone());
+            if let Err(e) = speedrun::save_personal_bests(&personal_bests) {
+                eprintln!("error: {}", e);
+            }
+        }
+    } else if options.treasure {
+        println!("collected treasure worth {}", treasure_score);
+    } else if options.survival && level > 1 {
+        println!("survived {} boards", level - 1);
+    } else if options.zen && zen_score.0 > 0 {
+        println!(
+            "found {} hidden cells in {} reveals ({:.1} per board)",
+            zen_score.0,
+            zen_score.1,
+            zen_score.1 as f64 / zen_score.0 as f64
+        );
+    }
+}
+
+#[derive(Debug)]
+struct MouseState {
+    click: bool,
+    x: i32,
+    y: i32,
+}
+
+enum InputUpdate {
+    Mouse(MouseState),
+    Scroll(i32, i32),
+    Key(char),
+    Resize,
+}
+
+fn get_input_update(window: &pancurses::Window) -> Option<InputUpdate> {
+    match window.getch() {
+        Some(pancurses::Input::KeyMouse) => {
+            if let Ok(mouse_event) = pancurses::getmouse() {
+                // the mouse wheel scrolls vertically, or horizontally while holding shift
+                let wheel_scroll = if (mouse_event.bstate & pancurses::BUTTON4_PRESSED) != 0 {
+                    -1
+                } else if (mouse_event.bstate & pancurses::BUTTON5_PRESSED) != 0 {
+                    1
+                } else {
+                    0
+                };
+
+                if wheel_scroll != 0 {
+                    return if (mouse_event.bstate & pancurses::BUTTON_SHIFT) != 0 {
+                        Some(InputUpdate::Scroll(wheel_scroll, 0))
+                    } else {
+                        Some(InputUpdate::Scroll(0, wheel_scroll))
+                    };
+                }
+
+                return Some(InputUpdate::Mouse(MouseState {
+                    click: (mouse_event.bstate & pancurses::BUTTON1_CLICKED) != 0,
+                    x: mouse_event.x,
+                    y: mouse_event.y,
+                }));
+            }
+        }
+        Some(pancurses::Input::KeyLeft) => return Some(InputUpdate::Scroll(-1, 0)),
+        Some(pancurses::Input::KeyRight) => return Some(InputUpdate::Scroll(1, 0)),
+        Some(pancurses::Input::KeyUp) => return Some(InputUpdate::Scroll(0, -1)),
+        Some(pancurses::Input::KeyDown) => return Some(InputUpdate::Scroll(0, 1)),
+        Some(pancurses::Input::KeyResize) => {
+            // let curses pick up the new terminal dimensions
+            pancurses::resize_term(0, 0);
+            return Some(InputUpdate::Resize);
+        }
+        Some(pancurses::Input::Character(c)) => return Some(InputUpdate::Key(c)),
+        _ => (),
+    }
+
+    None
+}
+
+fn render_level_header<S: Surface>(level: usize, level_rect: &Rect, surface: &S) {
+    surface.draw_text(level_rect.top, level_rect.left, format!("Level: {}", level));
+}
+
+// what the timer under the level header shows, which spectators get sent too
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct TimerReadout {
+    timed: bool,
+    // the time left on timed levels, or how long it's been on untimed ones
+    time: std::time::Duration,
+    reveals: usize,
+    score: u32,
+    treasure_total: u32,
+}
+
+impl TimerReadout {
+    fn of(level: &level::Level) -> Self {
+        TimerReadout {
+            timed: level.is_timed(),
+            time: if level.is_timed() {
+                level.time_left()
+            } else {
+                level.elapsed()
+            },
+            reveals: level.reveals(),
+            score: level.score(),
+            treasure_total: level.treasure_total(),
+        }
+    }
+}
+
+// Counts down the time left, or up from zero on untimed levels where the score is how many reveals
+// it takes to find the solution.
+fn render_game_timer<S: Surface>(readout: &TimerReadout, time_rect: &Rect, surface: &S) {
+    let label = if readout.timed { "Time" } else { "Elapsed" };
+    let time = readout.time;
+
+    surface.draw_text(
         time_rect.top,
         time_rect.left,
         format!(
-            "Time: {:02}.{:03}",
-            time_remaining.as_secs(),
-            time_remaining.subsec_millis(),
+            "{}: {:02}.{:03}",
+            label,
+            time.as_secs(),
+            time.subsec_millis()
         ),
     );
+
+    if !readout.timed {
+        surface.draw_text(
+            time_rect.top + 1,
+            time_rect.left,
+            format!("Reveals: {}", readout.reveals),
+        );
+    } else if readout.treasure_total > 0 {
+        surface.draw_text(
+            time_rect.top + 1,
+            time_rect.left,
+            format!("Score: {} of {}", readout.score, readout.treasure_total),
+        );
+    }
+}
+
+// where and how the visible slice of the game grid gets drawn in the window
+struct BoardView<'a> {
+    grid_rect: &'a Rect,
+    camera: &'a xform::Camera,
+    metrics: &'a xform::CellMetrics,
+    accessibility: &'a config::Accessibility,
 }
 
-fn render_game_board(
+fn render_game_board<S: Surface>(
     game_grid: &GameGrid,
     game_over_state: &Option<GameOverState>,
     confusion_state: Option<bool>,
-    grid_rect: &Rect,
-    window: &pancurses::Window,
+    board_view: &BoardView,
+    surface: &S,
     mouse_state: &MouseState,
 ) {
+    let BoardView {
+        grid_rect,
+        camera,
+        metrics,
+        accessibility,
+    } = *board_view;
+
+    // reduced motion swaps blinking for steady attributes
+    let (blink_attribute, hover_attribute) = if accessibility.reduced_motion {
+        (pancurses::A_NORMAL, pancurses::A_REVERSE)
+    } else {
+        (pancurses::A_BLINK, pancurses::A_BLINK)
+    };
+
     // add the leading border cells on top of the grid
-    let border_attribute = Color::BlackOnDarkGray.to_color_pair();
+    let border_attribute = Style::Border.attributes();
 
     let game_lost = game_over_state
         .as_ref()
@@ -529,27 +1641,73 @@ fn render_game_board(
 
     let show_confusion = confusion_state.is_some() && !game_lost;
 
-    fn generate_cell(c: u64) -> [[u64; 3]; 2] {
-        const EMPTY: u64 = ' ' as u64;
-        [[c, EMPTY, c], [c, EMPTY, c]]
+    // Every row of a cell has the cell's glyph on its left and right edges. Big cells also get the
+    // glyph in their center so they're easier to read.
+    fn generate_cell(c: Glyph, metrics: &xform::CellMetrics) -> Vec<Vec<Glyph>> {
+        const EMPTY: Glyph = Glyph::Char(' ');
+        let center = (metrics.width / 2, metrics.height / 2);
+        (0..metrics.height)
+            .map(|row| {
+                (0..metrics.width)
+                    .map(|col| {
+                        let on_edge = col == 0 || col == metrics.width - 1;
+                        let on_center = metrics.width >= 5 && (col, row) == center;
+                        if on_edge || on_center {
+                            c
+                        } else {
+                            EMPTY
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
     }
 
-    let left_cell = generate_cell('<' as u64);
-    let right_cell = generate_cell('>' as u64);
-    let up_cell = generate_cell('^' as u64);
-    let down_cell = generate_cell('v' as u64);
-    let diamond_cell = generate_cell(pancurses::ACS_DIAMOND());
-    let empty_cell = generate_cell(' ' as u64);
-    let confusion_trap_cell = generate_cell('~' as u64);
+    let left_cell = generate_cell('<'.into(), metrics);
+    let right_cell = generate_cell('>'.into(), metrics);
+    let up_cell = generate_cell('^'.into(), metrics);
+    let down_cell = generate_cell('v'.into(), metrics);
+    let diamond_cell = generate_cell(Glyph::Diamond, metrics);
+    let empty_cell = generate_cell(' '.into(), metrics);
+    let confusion_trap_cell = generate_cell('~'.into(), metrics);
+    let confused_hint_cell = generate_cell('?'.into(), metrics);
+    // treasure shows what it's worth, which is never more than a digit
+    let treasure_cells: Vec<_> = (0..=9)
+        .map(|digit| {
+            let c = std::char::from_digit(digit, 10).unwrap();
+            generate_cell(c.into(), metrics)
+        })
+        .collect();
+
+    // If we are confused and want to show it, either flip the hint directions back and forth or,
+    // with reduced motion, hide them entirely.
+    let hint_cell = |hint_dir: HintDir| {
+        let hint_dir = match confusion_state {
+            Some(_) if show_confusion && accessibility.reduced_motion => {
+                return (&confused_hint_cell, Style::Trap.attributes());
+            }
+            Some(true) if show_confusion => hint_dir.flip(),
+            _ => hint_dir,
+        };
+
+        match hint_dir {
+            HintDir::Left => (&left_cell, Style::HintLeft.attributes()),
+            HintDir::Right => (&right_cell, Style::HintRight.attributes()),
+            HintDir::Up => (&up_cell, Style::HintUp.attributes()),
+            HintDir::Down => (&down_cell, Style::HintDown.attributes()),
+        }
+    };
 
-    // render the grid
-    for row in 0..game_grid.height() {
-        let row_offset = (row * 2) + grid_rect.top + 1;
+    // render the slice of the grid that the camera can see
+    for view_row in 0..camera.height() {
+        let row = camera.y() + view_row;
+        let row_offset = (view_row * metrics.stride_y()) + grid_rect.top + 1;
 
         // render each cell
-        for col in 0..game_grid.width() {
-            let col_offset = grid_rect.left + 1 + 4 * col;
-            // safe to unwrap since we are iterating over the grid by its own bounds
+        for view_col in 0..camera.width() {
+            let col = camera.x() + view_col;
+            let col_offset = grid_rect.left + 1 + metrics.stride_x() * view_col;
+            // safe to unwrap since the camera always stays within the grid's bounds
             let grid_cell = game_grid.cell(col, row).unwrap();
 
             // show the cell if it's currently revealed or if we lost
@@ -558,193 +1716,780 @@ fn render_game_board(
             let (grid_item_lines, grid_item_attributes) = if show_cell {
                 match grid_cell.item {
                     GridItem::Solution => (
-                        diamond_cell,
-                        Color::BlackOnWhite.to_color_pair() | pancurses::A_BLINK,
+                        &diamond_cell,
+                        Style::Highlight.attributes() | blink_attribute,
+                    ),
+                    GridItem::Hint(hint_dir) => hint_cell(hint_dir),
+                    // value hints look like any other hint but in a color that stands out
+                    GridItem::ValueHint(hint_dir) => (
+                        hint_cell(hint_dir).0,
+                        Style::Success.attributes() | pancurses::A_BOLD,
                     ),
-                    GridItem::Hint(hint_dir) => {
-                        // If we are confused and want to show it, flip the hint directions
-                        let hint_dir = if show_confusion && confusion_state.unwrap() {
-                            hint_dir.flip()
-                        } else {
-                            hint_dir
-                        };
-
-                        match hint_dir {
-                            HintDir::Left => (left_cell, Color::BlackOnBlue.to_color_pair()),
-                            HintDir::Right => (right_cell, Color::BlackOnYellow.to_color_pair()),
-                            HintDir::Up => (up_cell, Color::BlackOnRed.to_color_pair()),
-                            HintDir::Down => (down_cell, Color::BlackOnGreen.to_color_pair()),
-                        }
-                    }
                     GridItem::Trap(trap_type) => match trap_type {
-                        TrapType::Confusion => {
-                            (confusion_trap_cell, Color::BlackOnOrange.to_color_pair())
-                        }
+                        TrapType::Confusion => (&confusion_trap_cell, Style::Trap.attributes()),
                     },
-                    GridItem::Empty => (empty_cell, Color::BlackOnGray.to_color_pair()),
+                    GridItem::Treasure(value) => (
+                        &treasure_cells[std::cmp::min(value, 9) as usize],
+                        Style::Highlight.attributes(),
+                    ),
+                    GridItem::Empty => (&empty_cell, Style::Empty.attributes()),
                 }
             } else {
-                (empty_cell, Color::BlackOnDarkGray.to_color_pair())
+                match grid_cell.item {
+                    // hints that were seen before stay visible, just dimmed on a hidden cell
+                    GridItem::Hint(hint_dir) | GridItem::ValueHint(hint_dir)
+                        if grid_cell.seen && accessibility.show_seen_hints =>
+                    {
+                        let (hint_lines, _) = hint_cell(hint_dir);
+                        (hint_lines, Style::Hidden.attributes() | pancurses::A_DIM)
+                    }
+                    _ => (&empty_cell, Style::Hidden.attributes()),
+                }
             };
 
-            window.attron(grid_item_attributes);
-            window.mv(row_offset, col_offset);
-            for c in &grid_item_lines[0] {
-                window.addch(*c);
-            }
-            // use underlines to draw interior horizontal borders for cells
-            window.attron(pancurses::A_UNDERLINE);
-            window.mv(row_offset + 1, col_offset);
-            for c in &grid_item_lines[1] {
-                window.addch(*c);
+            surface.attributes_on(grid_item_attributes);
+            for (line_index, line) in grid_item_lines.iter().enumerate() {
+                // use underlines on the last line to draw interior horizontal borders for cells
+                let is_last_line = line_index == grid_item_lines.len() - 1;
+                if is_last_line {
+                    surface.attributes_on(pancurses::A_UNDERLINE);
+                }
+
+                for (glyph_index, glyph) in line.iter().enumerate() {
+                    surface.draw_glyph(
+                        row_offset + line_index as i32,
+                        col_offset + glyph_index as i32,
+                        *glyph,
+                    );
+                }
+
+                if is_last_line {
+                    surface.attributes_off(pancurses::A_UNDERLINE);
+                }
             }
-            window.attroff(pancurses::A_UNDERLINE);
-            window.attroff(grid_item_attributes);
+            surface.attributes_off(grid_item_attributes);
 
             // draw interior vertical borders for cells
-            if col < game_grid.width() - 1 {
-                window.attron(border_attribute);
-                window.mvaddch(row_offset, col_offset + 3, pancurses::ACS_VLINE());
-                window.mvaddch(row_offset + 1, col_offset + 3, pancurses::ACS_VLINE());
-                window.attroff(border_attribute);
+            if view_col < camera.width() - 1 {
+                surface.attributes_on(border_attribute);
+                for line_index in 0..metrics.height {
+                    surface.draw_glyph(
+                        row_offset + line_index,
+                        col_offset + metrics.width,
+                        Glyph::VerticalLine,
+                    );
+                }
+                surface.attributes_off(border_attribute);
+            }
+        }
+    }
+
+    // if we are hovering over a grid cell, highlight the selected cell
+    let mouse_game_grid_pos = xform::window_to_game_grid(
+        mouse_state.x,
+        mouse_state.y,
+        grid_rect.left,
+        grid_rect.top,
+        camera,
+        metrics,
+    );
+    if camera.contains(mouse_game_grid_pos.0, mouse_game_grid_pos.1) {
+        let highlighted_rect = xform::game_grid_to_window(
+            mouse_game_grid_pos.0,
+            mouse_game_grid_pos.1,
+            grid_rect.left,
+            grid_rect.top,
+            camera,
+            metrics,
+        );
+
+        for row in highlighted_rect.top..=highlighted_rect.bottom() {
+            for col in highlighted_rect.left..=highlighted_rect.right() {
+                surface.add_attributes(row, col, hover_attribute);
+            }
+        }
+    }
+}
+
+fn render_minimap<S: Surface>(
+    game_grid: &GameGrid,
+    camera: &xform::Camera,
+    minimap_rect: &Rect,
+    cells_per_char: (i32, i32),
+    surface: &S,
+) {
+    for minimap_row in 0..minimap_rect.height {
+        for minimap_col in 0..minimap_rect.width {
+            // each character of the minimap summarizes a block of grid cells
+            let block_left = minimap_col * cells_per_char.0;
+            let block_top = minimap_row * cells_per_char.1;
+            let block_cells = (block_top..block_top + cells_per_char.1).flat_map(|row| {
+                (block_left..block_left + cells_per_char.0).map(move |col| (col, row))
+            });
+
+            let mut any_revealed = false;
+            let mut any_in_view = false;
+            for (col, row) in block_cells {
+                if let Some(grid_cell) = game_grid.cell(col, row) {
+                    any_revealed |= grid_cell.revealed;
+                    any_in_view |= camera.contains(col, row);
+                }
+            }
+
+            let (minimap_char, mut minimap_attributes) = if any_revealed {
+                ('#', Style::Highlight.attributes())
+            } else {
+                ('.', Style::Border.attributes())
+            };
+
+            if any_in_view {
+                minimap_attributes |= pancurses::A_REVERSE;
             }
+
+            surface.attributes_on(minimap_attributes);
+            surface.draw_glyph(
+                minimap_rect.top + minimap_row,
+                minimap_rect.left + minimap_col,
+                minimap_char.into(),
+            );
+            surface.attributes_off(minimap_attributes);
+        }
+    }
+}
+
+// If the mouse is hovering just past the edge of the visible slice of the board, returns which
+// way to scroll the camera.
+fn get_edge_hover_scroll(mouse_state: &MouseState, grid_rect: &Rect) -> (i32, i32) {
+    // how many characters past the grid's edge count as hovering over the edge
+    const EDGE_HOVER_SIZE: i32 = 2;
+
+    let in_band = |pos: i32, low: i32, high: i32| {
+        pos >= low - EDGE_HOVER_SIZE && pos <= high + EDGE_HOVER_SIZE
+    };
+
+    let in_horizontal_band = in_band(mouse_state.x, grid_rect.left, grid_rect.right());
+    let in_vertical_band = in_band(mouse_state.y, grid_rect.top, grid_rect.bottom());
+    if !in_horizontal_band || !in_vertical_band {
+        return (0, 0);
+    }
+
+    let scroll_dir = |pos: i32, low: i32, high: i32| {
+        if pos <= low {
+            -1
+        } else if pos >= high {
+            1
+        } else {
+            0
+        }
+    };
+
+    (
+        scroll_dir(mouse_state.x, grid_rect.left, grid_rect.right()),
+        scroll_dir(mouse_state.y, grid_rect.top, grid_rect.bottom()),
+    )
+}
+
+// Marks every cell the solver thinks could still be the solution along with the cell it's going to
+// reveal next, and says how many candidates are left above the board.
+fn render_solver_overlay<S: Surface>(
+    solver: &solver::Solver,
+    next_move: Option<(i32, i32)>,
+    game_grid: &GameGrid,
+    board_view: &BoardView,
+    level_rect: &Rect,
+    surface: &S,
+) {
+    let BoardView {
+        grid_rect,
+        camera,
+        metrics,
+        ..
+    } = *board_view;
+
+    let mark_cell = |x: i32, y: i32, glyph: Glyph, attributes: pancurses::chtype| {
+        let cell_rect =
+            xform::game_grid_to_window(x, y, grid_rect.left, grid_rect.top, camera, metrics);
+
+        surface.attributes_on(attributes);
+        surface.draw_glyph(cell_rect.top, cell_rect.center_x(), glyph);
+        surface.attributes_off(attributes);
+    };
+
+    for y in camera.y()..camera.y() + camera.height() {
+        for x in camera.x()..camera.x() + camera.width() {
+            let revealed = game_grid.cell(x, y).unwrap().revealed;
+            if solver.is_candidate(x, y) && !revealed && next_move != Some((x, y)) {
+                mark_cell(x, y, Glyph::Bullet, Style::Highlight.attributes());
+            }
+        }
+    }
+
+    if let Some((x, y)) = next_move {
+        if camera.contains(x, y) {
+            mark_cell(x, y, '*'.into(), Style::Alert.attributes());
+        }
+    }
+
+    let candidates_text = format!("solver: {} candidates", solver.candidate_count());
+    surface.draw_text(
+        level_rect.top,
+        std::cmp::max(
+            level_rect.left,
+            grid_rect.right() - candidates_text.len() as i32 + 1,
+        ),
+        candidates_text,
+    );
+}
+
+fn render_title_text<S: Surface>(surface: &S, title_rect: &Rect) {
+    const TITLE_LINES: [&str; 2] = [TITLE, "press any key to play, q to quit"];
+
+    let title_attributes = Style::Highlight.attributes();
+    surface.attributes_on(title_attributes);
+    for (i, text) in TITLE_LINES.iter().enumerate() {
+        surface.draw_text(
+            title_rect.center_y() + (i as i32),
+            title_rect.center_x() - (text.len() / 2) as i32,
+            text,
+        );
+    }
+    surface.attributes_off(title_attributes);
+}
+
+fn render_too_small_overlay<S: Surface>(min_size: (i32, i32), screen_rect: &Rect, surface: &S) {
+    let lines = [
+        String::from("Terminal too small!"),
+        format!("Need {}x{}", min_size.0, min_size.1),
+        format!("Have {}x{}", screen_rect.width, screen_rect.height),
+    ];
+
+    let overlay_attributes = Style::Alert.attributes();
+    surface.attributes_on(overlay_attributes);
+    for (i, line) in lines.iter().enumerate() {
+        surface.draw_text(
+            screen_rect.center_y() - 1 + (i as i32),
+            std::cmp::max(0, screen_rect.center_x() - (line.len() / 2) as i32),
+            line,
+        );
+    }
+    surface.attributes_off(overlay_attributes);
+}
+
+fn render_confusion_text<S: Surface>(surface: &S, confusion_rect: &Rect) {
+    const CONFUSION_TEXT: &str = "Confused! Hints are hidden";
+
+    let confusion_attributes = Style::Trap.attributes();
+    surface.attributes_on(confusion_attributes);
+    surface.draw_text(
+        confusion_rect.center_y(),
+        confusion_rect.center_x() - (CONFUSION_TEXT.len() / 2) as i32,
+        CONFUSION_TEXT,
+    );
+    surface.attributes_off(confusion_attributes);
+}
+
+fn render_game_over_text<S: Surface>(
+    game_over_state: &GameOverState,
+    continues: bool,
+    surface: &S,
+    game_over_rect: &Rect,
+) {
+    let (game_over_text, game_over_attributes) = match (game_over_state.result, continues) {
+        (GameResult::Win, true) => ("Success! Next board in...", Style::Success.attributes()),
+        (GameResult::Win, false) => ("Success! Exiting in...", Style::Success.attributes()),
+        (_, true) => ("Failed! Next board in...", Style::Alert.attributes()),
+        (_, false) => ("Failed! Exiting in...", Style::Alert.attributes()),
+    };
+
+    // adjust the time by a half second so that the time reads better.
+    let adjusted_time_left =
+        game_over_state.msg_timer.time_left() + std::time::Duration::from_millis(500);
+    let secs_left = adjusted_time_left.as_secs();
+
+    let time_text = format!("{} secs", secs_left);
+
+    surface.attributes_on(game_over_attributes);
+    for (i, text) in [game_over_text, &time_text].iter().enumerate() {
+        surface.draw_text(
+            game_over_rect.center_y() + (i as i32),
+            game_over_rect.center_x() - (text.len() / 2) as i32,
+            text,
+        );
+    }
+    surface.attributes_off(game_over_attributes);
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LevelConfig {
+    grid_size: (i32, i32),
+    board_time: std::time::Duration,
+    max_revealed_cells: usize,
+    cell_distribution: CellDistribution,
+    // the range of difficulty scores (see difficulty.rs) that the level's boards have to land in
+    difficulty_band: (f64, f64),
+}
+
+fn get_level_config(level: usize, max_grid_size: (i32, i32)) -> LevelConfig {
+    // Once the board can't grow any more on this screen, every difficulty step it would have
+    // grown by gets pushed onto the other difficulty knobs instead.
+    let (grid_size, overflow_steps) = get_grid_size_from_level(level, max_grid_size);
+    LevelConfig {
+        grid_size,
+        board_time: get_board_time_from_level(level, overflow_steps),
+        max_revealed_cells: get_max_revealed_cells_from_level(level, overflow_steps),
+        cell_distribution: get_cell_distribution_from_level(overflow_steps),
+        difficulty_band: get_difficulty_band_from_level(level, grid_size),
+    }
+}
+
+fn get_board_time_from_level(level: usize, overflow_steps: usize) -> std::time::Duration {
+    const MAX_TIME_SECS: u64 = 15;
+    const MAX_TIME_REDUCTION_SECS: u64 = 10;
+    const MIN_AFFECTED_LEVEL: usize = 6; // don't start reducing the board time until we get to at least level 6
+
+    let adjusted_level = level - std::cmp::min(level, MIN_AFFECTED_LEVEL);
+    let difficulty_step = adjusted_level as u64 / 3; // every 3 levels the difficulty step increases
+    let time_reduction_in_secs = difficulty_step * 2; // every difficulty step drops the timer by 2 seconds
+    let overflow_time_reduction_in_secs = overflow_steps as u64; // every overflow step drops the timer by 1 more second
+    let capped_time_reduction_in_secs = std::cmp::min(
+        time_reduction_in_secs + overflow_time_reduction_in_secs,
+        MAX_TIME_REDUCTION_SECS,
+    );
+
+    std::time::Duration::from_secs(MAX_TIME_SECS - capped_time_reduction_in_secs)
+}
+
+// In survival mode the first board gets the usual time and every board after that only tops up
+// whatever was left over from the one before.
+fn get_survival_board_time(
+    board_time: std::time::Duration,
+    time_bank: Option<std::time::Duration>,
+) -> std::time::Duration {
+    const TOP_UP_DIVISOR: u32 = 3;
+
+    match time_bank {
+        Some(time_bank) => time_bank + board_time / TOP_UP_DIVISOR,
+        None => board_time,
+    }
+}
+
+// Returns the grid size along with the number of difficulty steps the board couldn't grow by
+// because it would no longer fit in `max_grid_size`.
+fn get_grid_size_from_level(level: usize, max_grid_size: (i32, i32)) -> ((i32, i32), usize) {
+    // start as a 15x10 board and increase by 1 in each dimension every 3 levels
+    const START_BOARD_SIZE: (i32, i32) = (15, 10);
+    const MIN_BOARD_SIZE: (i32, i32) = (4, 4);
+    const MAX_BOARD_GROWTH: i32 = 10;
+
+    let difficulty_step = level / 3; // every 3 levels the difficulty step increases
+    let board_growth = difficulty_step as i32; // every difficulty step increases the board by 1 in each dimension
+    let capped_board_growth = std::cmp::min(board_growth, MAX_BOARD_GROWTH);
+
+    // the board can shrink below its starting size on small screens but never below the minimum
+    let max_width = std::cmp::max(max_grid_size.0, MIN_BOARD_SIZE.0);
+    let max_height = std::cmp::max(max_grid_size.1, MIN_BOARD_SIZE.1);
+    let screen_board_growth = std::cmp::min(
+        max_width - START_BOARD_SIZE.0,
+        max_height - START_BOARD_SIZE.1,
+    );
+    let fitted_board_growth =
+        std::cmp::min(capped_board_growth, std::cmp::max(screen_board_growth, 0));
+    let overflow_steps = capped_board_growth - fitted_board_growth;
+
+    let grid_size = (
+        std::cmp::min(START_BOARD_SIZE.0 + fitted_board_growth, max_width),
+        std::cmp::min(START_BOARD_SIZE.1 + fitted_board_growth, max_height),
+    );
+    (grid_size, overflow_steps as usize)
+}
+
+fn get_max_revealed_cells_from_level(level: usize, overflow_steps: usize) -> usize {
+    const INITIAL_MAX_REVEALED_CELLS: usize = 6;
+    const MAX_REVEALED_CELL_REDUCTION: usize = 5;
+
+    let difficulty_step = level / 5; // every 5 levels, you lose 1 extra revealed cell
+    let revealed_cell_reduction = difficulty_step + overflow_steps / 2; // every 2 overflow steps, you lose 1 more
+    let capped_revealed_cell_reduction =
+        std::cmp::min(revealed_cell_reduction, MAX_REVEALED_CELL_REDUCTION);
+    INITIAL_MAX_REVEALED_CELLS - capped_revealed_cell_reduction
+}
+
+fn get_cell_distribution_from_level(overflow_steps: usize) -> CellDistribution {
+    const MAX_EXTRA_TRAPS: usize = 3;
+
+    // every 2 overflow steps, turn one more hint (out of every 10 cells) into a trap
+    let extra_traps = std::cmp::min(overflow_steps / 2, MAX_EXTRA_TRAPS);
+    let default_distribution = CellDistribution::default();
+    CellDistribution {
+        traps: default_distribution.traps + extra_traps,
+        hints: default_distribution.hints - extra_traps,
+        ..default_distribution
+    }
+}
+
+// Each hint splits the board up about four ways, so a typical board takes around log4(cells)
+// reveals to narrow down plus a couple more to land on the solution. Later levels ask for boards
+// that are a little harder than typical.
+fn get_difficulty_band_from_level(level: usize, grid_size: (i32, i32)) -> (f64, f64) {
+    const MAX_DIFFICULTY_STEPS: usize = 4;
+
+    let cells = (grid_size.0 * grid_size.1) as f64;
+    let typical_score = cells.log(4.0) + 2.0;
+
+    let difficulty_step = std::cmp::min(level / 5, MAX_DIFFICULTY_STEPS); // every 5 levels, target boards half a reveal harder
+    let target_score = typical_score + difficulty_step as f64 * 0.5;
+    (target_score - 2.0, target_score + 3.0)
+}
+
+// Rolls boards until one lands in the level's difficulty band. Small or trap heavy levels might
+// not have many boards in the band, so after enough tries go with whichever board came closest.
+fn generate_grid(
+    config: &LevelConfig,
+    reveal_time: std::time::Duration,
+    rng: &mut dyn RangeRng<usize>,
+) -> GameGrid {
+    const MAX_ROLLS: usize = 20;
+
+    let mut closest: Option<(f64, GameGrid)> = None;
+    for _ in 0..MAX_ROLLS {
+        let game_grid = GameGrid::new(
+            config.grid_size.0,
+            config.grid_size.1,
+            config.max_revealed_cells,
+            reveal_time,
+            config.cell_distribution,
+            rng,
+        );
+
+        let distance = difficulty::analyze(&game_grid).distance_from(config.difficulty_band);
+        if distance == 0.0 {
+            return game_grid;
+        }
+
+        let is_closest = match &closest {
+            Some((closest_distance, _)) => distance < *closest_distance,
+            None => true,
+        };
+        if is_closest {
+            closest = Some((distance, game_grid));
         }
     }
 
-    // if we are hovering over a grid cell, highlight the selected cell
-    let mouse_game_grid_pos =
-        xform::window_to_game_grid(mouse_state.x, mouse_state.y, grid_rect.left, grid_rect.top);
-    if game_grid
-        .cell(mouse_game_grid_pos.0, mouse_game_grid_pos.1)
-        .is_some()
-    {
-        let highlighted_rect = xform::game_grid_to_window(
-            mouse_game_grid_pos.0,
-            mouse_game_grid_pos.1,
-            grid_rect.left,
-            grid_rect.top,
+    closest.unwrap().1
+}
+
+// Generates the board for a level. `max_grid_size` is the biggest board that fits on screen, which
+// decides how much of the level's difficulty can come from the size of the board. Playing a board
+// file always gives back the board from the file.
+//
+// `time_bank` is the time left over from the board before, which survival mode plays this board
+// on. It's None for the first board.
+fn new_level(
+    level: usize,
+    options: &cli::Options,
+    accessibility: &config::Accessibility,
+    max_grid_size: (i32, i32),
+    time_bank: Option<std::time::Duration>,
+    rng: &mut dyn RangeRng<usize>,
+) -> level::Level {
+    let new_level = if options.zen {
+        level::Level::new_untimed
+    } else {
+        level::Level::new
+    };
+
+    if let Some(board) = &options.board {
+        return new_level(
+            level,
+            board.to_grid(accessibility.reveal_time),
+            board.level_config(),
+        );
+    }
+
+    let mut level_config = match options.board_size {
+        // fixed size boards don't need to fit on screen since we can scroll around them
+        Some(board_size) => get_level_config(level, board_size),
+        None => get_level_config(level, max_grid_size),
+    };
+    if let Some(board_size) = options.board_size {
+        level_config.grid_size = board_size;
+        level_config.difficulty_band = get_difficulty_band_from_level(level, board_size);
+    }
+    if options.survival {
+        level_config.board_time = get_survival_board_time(level_config.board_time, time_bank);
+    }
+
+    // treasure hunts play a single board on a fixed time budget, so there's nothing to re-roll for
+    if options.treasure {
+        const TREASURE_HUNT_TIME: std::time::Duration = std::time::Duration::from_secs(45);
+        const TREASURE_VALUES: [u32; 6] = [1, 1, 2, 2, 3, 5];
+
+        level_config.board_time = TREASURE_HUNT_TIME;
+        let (width, height) = level_config.grid_size;
+        let game_grid = GameGrid::new_treasure_hunt(
+            width,
+            height,
+            level_config.max_revealed_cells,
+            accessibility.reveal_time,
+            level_config.cell_distribution,
+            &TREASURE_VALUES,
+            rng,
+        );
+        return new_level(level, game_grid, level_config);
+    }
+
+    let game_grid = generate_grid(&level_config, accessibility.reveal_time, rng);
+
+    // main made sure the directory exists when we started, so there's not much that can go wrong
+    // here and nowhere good to report it mid-game anyway
+    if let Some(export_dir) = &options.export_dir {
+        let path = export_dir.join(format!("level-{}.txt", level));
+        let _ = std::fs::write(
+            path,
+            board_file::export(&game_grid, level_config.board_time),
         );
+    }
 
-        for row in highlighted_rect.top..=highlighted_rect.bottom() {
-            for col in highlighted_rect.left..=highlighted_rect.right() {
-                window.mvchgat(row, col, 1, window.mvinch(row, col) | pancurses::A_BLINK, 0);
-            }
+    new_level(level, game_grid, level_config)
+}
+
+// Plays demo boards with the solver behind the title until the player presses a key or clicks.
+// Returns false if they'd rather quit.
+fn run_title_screen(
+    options: &cli::Options,
+    accessibility: &config::Accessibility,
+    window: &pancurses::Window,
+    signals: &term::Signals,
+) -> bool {
+    // the solver only knows how to look for a single solution, so treasure hunts aren't demoed
+    let demo_options = cli::Options {
+        treasure: false,
+        ..options.clone()
+    };
+
+    let mut level = 1;
+    loop {
+        let outcome = run_game(
+            level,
+            Pilot::Attract,
+            &mut RunState::default(),
+            &demo_options,
+            accessibility,
+            window,
+            signals,
+        );
+        match outcome.result {
+            GameResult::Win => level += 1,
+            GameResult::Lose => level = 1,
+            GameResult::Quit => return !signals.quit_requested(),
         }
     }
 }
 
-fn render_game_over_text(
-    game_over_state: &GameOverState,
+fn run_game(
+    level: usize,
+    pilot: Pilot,
+    run: &mut RunState,
+    options: &cli::Options,
+    accessibility: &config::Accessibility,
     window: &pancurses::Window,
-    game_over_rect: &Rect,
-) {
-    let (game_over_text, game_over_attributes) = match game_over_state.result {
-        GameResult::Lose => ("Failed! Exiting in...", Color::BlackOnRed.to_color_pair()),
-        GameResult::Win => (
-            "Success! Next board in...",
-            Color::BlackOnGreen.to_color_pair(),
+    signals: &term::Signals,
+) -> LevelOutcome {
+    let screen_rect = layout::screen_rect(window);
+
+    // speedruns play the same boards every time, no matter the screen size
+    let level = match run.speedrun {
+        Some(_) => new_level(
+            level,
+            options,
+            accessibility,
+            speedrun::max_grid_size(),
+            run.time_bank,
+            &mut speedrun::level_rng(level),
+        ),
+        None => new_level(
+            level,
+            options,
+            accessibility,
+            layout::max_grid_size(&options.cell_metrics, &screen_rect),
+            run.time_bank,
+            &mut ThreadRangeRng::new(),
         ),
     };
 
-    // adjust the time by a half second so that the time reads better.
-    let adjusted_time_left =
-        game_over_state.msg_timer.time_left() + std::time::Duration::from_millis(500);
-    let secs_left = adjusted_time_left.as_secs();
+    let mut boards = [PlayerBoard::new(level, versus::Controls::Mouse)];
+    play_boards(
+        &mut boards,
+        pilot,
+        run,
+        options,
+        accessibility,
+        window,
+        signals,
+    )
+    .remove(0)
+}
 
-    let time_text = format!("{} secs", secs_left);
+// A level being played in play_boards, how it's being looked at and who's playing it
+struct PlayerBoard {
+    level: level::Level,
+    controls: versus::Controls,
+    camera: xform::Camera,
+    // the part of the screen the board gets laid out in
+    area: Rect,
+    board_layout: Option<layout::BoardLayout>,
+    // the cell keyboard players are on
+    cursor: (i32, i32),
+    game_over_state: Option<GameOverState>,
+    // what's happened on the board since the last frame, for spectators
+    events: Vec<level::LevelEvent>,
+}
 
-    window.attron(game_over_attributes);
-    for (i, text) in [game_over_text, &time_text].iter().enumerate() {
-        window.mvaddstr(
-            game_over_rect.center_y() + (i as i32),
-            game_over_rect.center_x() - (text.len() / 2) as i32,
-            text,
-        );
+impl PlayerBoard {
+    fn new(level: level::Level, controls: versus::Controls) -> Self {
+        let (width, height) = (level.grid().width(), level.grid().height());
+        PlayerBoard {
+            level,
+            controls,
+            camera: xform::Camera::new(width, height),
+            area: Rect::default(),
+            board_layout: None,
+            cursor: (width / 2, height / 2),
+            game_over_state: None,
+            events: vec![],
+        }
     }
-    window.attroff(game_over_attributes);
-}
 
-fn get_board_time_from_level(level: usize) -> std::time::Duration {
-    const MAX_TIME_SECS: u64 = 15;
-    const MAX_TIME_REDUCTION_SECS: u64 = 10;
-    const MIN_AFFECTED_LEVEL: usize = 6; // don't start reducing the board time until we get to at least level 6
+    fn reveal(&mut self, x: i32, y: i32) {
+        let events = self.level.reveal(x, y);
+        self.events.extend(events);
+    }
 
-    let adjusted_level = level - std::cmp::min(level, MIN_AFFECTED_LEVEL);
-    let difficulty_step = adjusted_level as u64 / 3; // every 3 levels the difficulty step increases
-    let time_reduction_in_secs = difficulty_step * 2; // every difficulty step drops the timer by 2 seconds
-    let capped_time_reduction_in_secs =
-        std::cmp::min(time_reduction_in_secs, MAX_TIME_REDUCTION_SECS);
+    fn lay_out(&mut self, area: Rect, metrics: &xform::CellMetrics) {
+        self.board_layout = layout::camera_layout(&mut self.camera, metrics, &area);
+        self.area = area;
+    }
 
-    std::time::Duration::from_secs(MAX_TIME_SECS - capped_time_reduction_in_secs)
-}
+    fn move_cursor(&mut self, dx: i32, dy: i32) {
+        let grid = self.level.grid();
+        self.cursor = (
+            (self.cursor.0 + dx).clamp(0, grid.width() - 1),
+            (self.cursor.1 + dy).clamp(0, grid.height() - 1),
+        );
+        self.camera.scroll_to_show(self.cursor.0, self.cursor.1);
+    }
 
-fn get_grid_size_from_level(level: usize) -> (i32, i32) {
-    // start as a 15x10 board and increase by 1 in each dimension every 3 levels
-    const START_BOARD_SIZE: (i32, i32) = (15, 10);
-    const MAX_BOARD_GROWTH: i32 = 10;
+    // the grid cell under the mouse, if the camera can see it
+    fn cell_under_mouse(
+        &self,
+        mouse_state: &MouseState,
+        metrics: &xform::CellMetrics,
+    ) -> Option<(i32, i32)> {
+        let board_layout = self.board_layout.as_ref()?;
+        let grid_pos = xform::window_to_game_grid(
+            mouse_state.x,
+            mouse_state.y,
+            board_layout.grid.left,
+            board_layout.grid.top,
+            &self.camera,
+            metrics,
+        );
+        Some(grid_pos).filter(|(x, y)| self.camera.contains(*x, *y))
+    }
 
-    let difficulty_step = level / 3; // every 3 levels the difficulty step increases
-    let board_growth = difficulty_step as i32; // every difficulty step increases the board by 1 in each dimension
-    let capped_board_growth = std::cmp::min(board_growth, MAX_BOARD_GROWTH);
+    // Cells light up under the mouse. Keyboard players get the cell under their cursor lit up
+    // instead, the same way the editor does it.
+    fn hover_state(&self, mouse_state: &MouseState, metrics: &xform::CellMetrics) -> MouseState {
+        let board_layout = match (&self.board_layout, self.controls) {
+            (Some(board_layout), versus::Controls::Arrows)
+            | (Some(board_layout), versus::Controls::Wasd) => board_layout,
+            _ => {
+                return MouseState {
+                    click: false,
+                    x: mouse_state.x,
+                    y: mouse_state.y,
+                }
+            }
+        };
 
-    (
-        START_BOARD_SIZE.0 + capped_board_growth,
-        START_BOARD_SIZE.1 + capped_board_growth,
-    )
+        let cursor_rect = xform::game_grid_to_window(
+            self.cursor.0,
+            self.cursor.1,
+            board_layout.grid.left,
+            board_layout.grid.top,
+            &self.camera,
+            metrics,
+        );
+        MouseState {
+            click: false,
+            x: cursor_rect.left,
+            y: cursor_rect.top,
+        }
+    }
 }
 
-fn get_max_revealed_cells_from_level(level: usize) -> usize {
-    const INITIAL_MAX_REVEALED_CELLS: usize = 6;
-    const MAX_REVEALED_CELL_REDUCTION: usize = 5;
-
-    let difficulty_step = level / 5; // every 5 levels, you lose 1 extra revealed cell
-    let revealed_cell_reduction = difficulty_step; // every difficulty step increases the board by 1 in each dimension
-    let capped_revealed_cell_reduction =
-        std::cmp::min(revealed_cell_reduction, MAX_REVEALED_CELL_REDUCTION);
-    INITIAL_MAX_REVEALED_CELLS - capped_revealed_cell_reduction
+fn lay_out_boards(boards: &mut [PlayerBoard], metrics: &xform::CellMetrics, screen_rect: &Rect) {
+    let areas = layout::split_screen(screen_rect, boards.len());
+    for (board, area) in boards.iter_mut().zip(areas) {
+        board.lay_out(area, metrics);
+    }
 }
 
-fn run_game(level: usize, window: &pancurses::Window) -> GameResult {
-    // Not using a Rect because this grid isn't ACTUALLY sized normally like a rect. There are spaces
-    let mut rng = ThreadRangeRng::new();
-
-    let (game_grid_width, game_grid_height) = get_grid_size_from_level(level);
-    let max_revealed_cells = get_max_revealed_cells_from_level(level);
-    let mut game_grid = GameGrid::new(
-        game_grid_width,
-        game_grid_height,
-        max_revealed_cells,
-        &mut rng,
-    );
+// Whether there's another board after one that ended with `result` after `reveals` reveals.
+// `winner` is which of the boards being played was won, if any. In hot-seat, versus and network
+// races a lost board can still go on to the next one.
+fn continues_after(
+    run: &RunState,
+    options: &cli::Options,
+    result: GameResult,
+    reveals: usize,
+    winner: Option<usize>,
+) -> bool {
+    match (&run.hot_seat, &run.versus, &run.race) {
+        (Some(roster), _, _) => roster.continues_after(result, reveals),
+        (_, Some(versus), _) => versus.continues_after(winner),
+        (_, _, Some(race)) => race.has_more_boards(),
+        // same as main's loop: a board from a file or a treasure hunt is the only level there is,
+        // and a speedrun stops after its last level
+        _ => {
+            let last_level = options.board.is_some()
+                || options.treasure
+                || run
+                    .speedrun
+                    .as_ref()
+                    .is_some_and(|speedrun| speedrun.on_last_level());
+            result == GameResult::Win && !last_level
+        }
+    }
+}
 
-    let grid_bounds = xform::game_grid_to_window(game_grid.width(), game_grid.height(), 0, 0);
-    let grid_rect = Rect {
-        left: (window.get_max_x() - grid_bounds.right()) / 2,
-        top: (window.get_max_y() - grid_bounds.bottom()) / 2,
-        width: grid_bounds.right(),
-        height: grid_bounds.bottom(),
-    };
+// Plays every board side by side in one frame loop until they're all over. The first board to
+// finish ends the others too, which is how the first player to find their hidden cell wins a round
+// of split-screen versus. Returns how each board went.
+fn play_boards(
+    boards: &mut [PlayerBoard],
+    pilot: Pilot,
+    run: &mut RunState,
+    options: &cli::Options,
+    accessibility: &config::Accessibility,
+    window: &pancurses::Window,
+    signals: &term::Signals,
+) -> Vec<LevelOutcome> {
+    let metrics = &options.cell_metrics;
+    let mut screen_rect = layout::screen_rect(window);
+    lay_out_boards(boards, metrics, &screen_rect);
 
-    let game_over_rect = Rect {
-        left: grid_rect.left,
-        top: grid_rect.bottom() + 2,
-        width: grid_rect.width,
-        height: 2,
-    };
+    // the solver only ever plays the first board
+    let mut solver = solver::Solver::new(boards[0].level.grid());
+    let show_solver_overlay = options.solver_overlay;
 
-    let time_rect = Rect {
-        left: grid_rect.left,
-        top: grid_rect.top - 4,
-        width: 30,
-        height: 2,
-    };
+    // the solver takes its time between moves so there's a chance to follow along
+    const SOLVER_MOVE_TIME: std::time::Duration = std::time::Duration::from_millis(500);
+    let mut solver_move_timer = Timer::new(SOLVER_MOVE_TIME);
 
-    let level_rect = Rect {
-        left: time_rect.left,
-        top: time_rect.top - 1,
-        width: 30,
-        height: 2,
-    };
+    // hovering past the edge of the board scrolls at a fixed rate rather than every frame
+    const EDGE_HOVER_SCROLL_TIME: std::time::Duration = std::time::Duration::from_millis(150);
+    let mut edge_hover_scroll_timer = Timer::new(EDGE_HOVER_SCROLL_TIME);
 
     let mut mouse_state = MouseState {
         click: false,
@@ -752,110 +2497,300 @@ fn run_game(level: usize, window: &pancurses::Window) -> GameResult {
         y: 0,
     };
 
+    // speedruns go straight on to the next board
     const BOARD_FINISH_MSG_TIME: std::time::Duration = std::time::Duration::from_secs(5);
-    const CONFUSION_TIME: std::time::Duration = std::time::Duration::from_secs(3);
-
-    let game_timer = Timer::new(get_board_time_from_level(level));
-    let mut confusion_timer = None;
+    let board_finish_msg_time = match run.speedrun {
+        Some(_) => std::time::Duration::ZERO,
+        None => BOARD_FINISH_MSG_TIME,
+    };
 
-    let mut game_over_state: Option<GameOverState> = None;
-    while game_over_state.is_none() || !game_over_state.as_ref().unwrap().msg_timer.finished() {
-        // If we get a mouse event, update our mouse state
-        mouse_state.click = false; // clear out any mouse state from the last frame
-        if let Some(mouse_update) = get_mouse_update(&window) {
-            mouse_state = mouse_update;
-        }
+    let quit = |boards: &[PlayerBoard]| -> Vec<LevelOutcome> {
+        boards
+            .iter()
+            .map(|board| board.level.outcome(GameResult::Quit))
+            .collect()
+    };
+    let msg_finished = |board: &PlayerBoard| {
+        board
+            .game_over_state
+            .as_ref()
+            .is_some_and(|game_over| game_over.msg_timer.finished())
+    };
 
-        // Update the board and check if we've triggered a game over
-        if game_over_state.is_none() {
-            game_grid.reset_expired_cells();
+    // a network race waits on everyone else before moving on
+    let waiting_on_race =
+        |run: &RunState| run.race.as_ref().is_some_and(|race| !race.board_decided());
 
-            // check for the lose state
-            if game_timer.finished() {
-                game_over_state = Some(GameOverState {
-                    result: GameResult::Lose,
-                    msg_timer: Timer::new(BOARD_FINISH_MSG_TIME),
-                    frozen_game_time: game_timer.time_left(),
-                });
-            } else if mouse_state.click {
-                // convert the mouse position to an item in a grid cell
-                let grid_pos = xform::window_to_game_grid(
-                    mouse_state.x,
-                    mouse_state.y,
-                    grid_rect.left,
-                    grid_rect.top,
-                );
+    while !boards.iter().all(msg_finished) || waiting_on_race(run) {
+        if signals.quit_requested() {
+            return quit(boards);
+        }
+        signals.handle_suspend(window);
 
-                match game_grid.try_reveal(grid_pos.0, grid_pos.1) {
-                    // check if our last input triggered a win state
-                    Some(GridItem::Solution) => {
-                        game_over_state = Some(GameOverState {
-                            result: GameResult::Win,
-                            msg_timer: Timer::new(BOARD_FINISH_MSG_TIME),
-                            frozen_game_time: game_timer.time_left(),
-                        })
+        // Take every input that's come in since the last frame so one player moving the mouse
+        // around can't hold up another player's keys.
+        mouse_state.click = false; // clear out any mouse state from the last frame
+        while let Some(input_update) = get_input_update(window) {
+            match input_update {
+                InputUpdate::Key(c) if pilot == Pilot::Attract => {
+                    if c == 'q' {
+                        signals.request_quit();
+                    }
+                    return quit(boards);
+                }
+                // clicking anywhere gets past the title screen
+                InputUpdate::Mouse(mouse_update) if pilot == Pilot::Attract => {
+                    if mouse_update.click {
+                        return quit(boards);
                     }
-                    // check if our last input revealed a trap
-                    Some(GridItem::Trap(trap_type)) => match trap_type {
-                        TrapType::Confusion => {
-                            confusion_timer = Some(Timer::new(CONFUSION_TIME));
+                    mouse_state = mouse_update;
+                }
+                InputUpdate::Mouse(mouse_update) => {
+                    mouse_state = mouse_update;
+                    let mouse_board = boards
+                        .iter_mut()
+                        .find(|board| board.controls == versus::Controls::Mouse);
+                    if let (true, Pilot::Player, Some(board)) =
+                        (mouse_state.click, pilot, mouse_board)
+                    {
+                        // only cells the camera can see are clickable
+                        if let Some((x, y)) = board.cell_under_mouse(&mouse_state, metrics) {
+                            board.reveal(x, y);
                         }
-                    },
-                    _ => (),
+                    }
+                }
+                // the arrow keys move the cursor of whoever's playing with them, otherwise they
+                // scroll around the board along with the mouse wheel
+                InputUpdate::Scroll(dx, dy) => {
+                    match boards
+                        .iter_mut()
+                        .find(|board| board.controls == versus::Controls::Arrows)
+                    {
+                        Some(board) => board.move_cursor(dx, dy),
+                        None => boards[0].camera.scroll(dx, dy),
+                    }
+                }
+                // there's no running out of time in zen mode, so quitting is the only way out
+                InputUpdate::Key('q') if options.zen => {
+                    return quit(boards);
+                }
+                InputUpdate::Key(c) => {
+                    for board in boards.iter_mut() {
+                        if let Some((dx, dy)) = board.controls.cursor_step(c) {
+                            board.move_cursor(dx, dy);
+                        } else if board.controls.is_reveal_key(c) {
+                            board.reveal(board.cursor.0, board.cursor.1);
+                        }
+                    }
+                }
+                InputUpdate::Resize => {
+                    screen_rect = layout::screen_rect(window);
+                    lay_out_boards(boards, metrics, &screen_rect);
+                }
+            }
+        }
+
+        for board in boards.iter_mut() {
+            if let (versus::Controls::Mouse, Some(board_layout)) =
+                (board.controls, &board.board_layout)
+            {
+                let (dx, dy) = get_edge_hover_scroll(&mouse_state, &board_layout.grid);
+                if (dx, dy) != (0, 0) && edge_hover_scroll_timer.finished() {
+                    board.camera.scroll(dx, dy);
+                    edge_hover_scroll_timer = Timer::new(EDGE_HOVER_SCROLL_TIME);
                 }
             }
         }
 
-        // Clear the confusion timer once it expires
-        if confusion_timer.is_some() && confusion_timer.as_ref().unwrap().finished() {
-            confusion_timer = None;
+        // Update the boards and check if we've triggered a game over
+        for board in boards.iter_mut() {
+            let events = board.level.update();
+            board.events.extend(events);
         }
+        solver.observe(&boards[0].level);
+        if let Some(race) = &mut run.race {
+            race.update(&mut boards[0].level);
+        }
+
+        if pilot != Pilot::Player {
+            let solver_move = if solver_move_timer.finished() {
+                solver.next_move(&boards[0].level)
+            } else {
+                None
+            };
 
-        fn flip_every_half_second(time: std::time::Duration) -> bool {
-            time.as_millis() % 1000 < 500
+            if let Some((x, y)) = solver_move {
+                let board = &mut boards[0];
+                board.camera.scroll_to_show(x, y);
+                board.reveal(x, y);
+                solver.observe(&board.level);
+                solver_move_timer = Timer::new(SOLVER_MOVE_TIME);
+            }
         }
 
-        // If the confusion timer is set, flip the confusion state every half second
-        let confusion_state = confusion_timer.as_ref().map(|enabled_confusion_timer| {
-            flip_every_half_second(enabled_confusion_timer.time_left())
-        });
+        if boards.iter().any(|board| board.level.result().is_some()) {
+            for board in boards.iter_mut() {
+                if board.level.result().is_none() {
+                    board.level.concede();
+                }
+                if let (None, Some(result)) = (&board.game_over_state, board.level.result()) {
+                    board.game_over_state = Some(GameOverState {
+                        result,
+                        msg_timer: Timer::new(board_finish_msg_time),
+                    });
+                }
+            }
+        }
 
-        let game_time_remaining = match &game_over_state {
-            Some(game_over) => game_over.frozen_game_time,
-            None => game_timer.time_left(),
-        };
+        let winner = boards
+            .iter()
+            .position(|board| board.level.result() == Some(GameResult::Win));
+
+        // spectators watch the first board
+        let first_board = &boards[0];
+        let game_over = first_board
+            .game_over_state
+            .as_ref()
+            .map(|game_over| spectate::GameOver {
+                result: game_over.result,
+                continues: continues_after(
+                    run,
+                    options,
+                    game_over.result,
+                    first_board.level.reveals(),
+                    winner,
+                ),
+                time_left: game_over.msg_timer.time_left(),
+            });
+        if let Some(spectators) = &mut run.spectators {
+            spectators.update(&first_board.level, &first_board.events, game_over);
+        }
+        for board in boards.iter_mut() {
+            board.events.clear();
+        }
 
         // use erase instead of clear to avoid tearing
         window.erase();
 
-        render_level_header(level, &level_rect, &window);
-        render_game_timer(game_time_remaining, &time_rect, &window);
-        render_game_board(
-            &game_grid,
-            &game_over_state,
-            confusion_state,
-            &grid_rect,
-            &window,
-            &mouse_state,
-        );
+        for (index, board) in boards.iter().enumerate() {
+            let board_layout = match &board.board_layout {
+                Some(board_layout) => board_layout,
+                None => {
+                    let (min_width, min_height) = layout::min_viewport_size(&board.camera);
+                    let min_size = layout::min_screen_size(min_width, min_height, metrics);
+                    render_too_small_overlay(min_size, &board.area, window);
+                    continue;
+                }
+            };
+
+            let level = &board.level;
+            let confusion_state = level.confusion_state();
+
+            render_level_header(level.number(), &board_layout.level, window);
+            render_game_timer(&TimerReadout::of(level), &board_layout.time, window);
+            if let Some(roster) = &run.hot_seat {
+                hot_seat::render_turn(
+                    roster,
+                    level.reveals(),
+                    level.result(),
+                    &board_layout.time,
+                    window,
+                );
+            }
+            if let Some(versus) = &run.versus {
+                versus::render_player(versus, index, &board_layout.time, window);
+            }
+            if let Some(speedrun) = &run.speedrun {
+                speedrun::render(
+                    speedrun,
+                    level.elapsed(),
+                    &board_layout.time,
+                    &board_layout.grid,
+                    &board.area,
+                    window,
+                );
+            }
+            if let Some(race) = &run.race {
+                race::render(
+                    race,
+                    level,
+                    &board_layout.time,
+                    &board_layout.grid,
+                    &board.area,
+                    window,
+                );
+            }
+            let board_view = BoardView {
+                grid_rect: &board_layout.grid,
+                camera: &board.camera,
+                metrics,
+                accessibility,
+            };
+            render_game_board(
+                level.grid(),
+                &board.game_over_state,
+                confusion_state,
+                &board_view,
+                window,
+                &board.hover_state(&mouse_state, metrics),
+            );
+
+            if show_solver_overlay && board.game_over_state.is_none() {
+                render_solver_overlay(
+                    &solver,
+                    solver.next_move(level),
+                    level.grid(),
+                    &board_view,
+                    &board_layout.level,
+                    window,
+                );
+            }
 
-        if let Some(game_over) = &game_over_state {
-            render_game_over_text(game_over, &window, &game_over_rect);
+            if let Some((minimap_rect, cells_per_char)) =
+                layout::minimap_layout(&board.camera, &board.area)
+            {
+                render_minimap(
+                    level.grid(),
+                    &board.camera,
+                    &minimap_rect,
+                    cells_per_char,
+                    window,
+                );
+            }
+
+            if pilot == Pilot::Attract {
+                render_title_text(window, &board_layout.game_over);
+            } else if let Some(game_over) = &board.game_over_state {
+                let continues =
+                    continues_after(run, options, game_over.result, level.reveals(), winner);
+                render_game_over_text(game_over, continues, window, &board_layout.game_over);
+            } else if confusion_state.is_some() && accessibility.reduced_motion {
+                render_confusion_text(window, &board_layout.game_over);
+            }
         }
 
-        window.refresh();
+        term::present(window);
 
         // Yield for 1/30th of a second. Don't hog that CPU.
         std::thread::sleep(std::time::Duration::from_millis(33));
     }
 
-    game_over_state.unwrap().result
+    boards
+        .iter()
+        .map(|board| board.level.outcome(GameResult::Quit))
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const ALL_CELL_METRICS: [xform::CellMetrics; 3] = [
+        xform::CellMetrics::COMPACT,
+        xform::CellMetrics::NORMAL,
+        xform::CellMetrics::LARGE,
+    ];
+
     #[test]
     fn test_game_grid_to_window() {
         // ....
@@ -873,17 +2808,41 @@ mod tests {
         // |___|___|___|___|
         let offset_left = 1;
         let offset_top = 5;
-        let expected_result = Rect {
-            left: 14,
-            top: 8,
-            width: 3,
-            height: 2,
-        };
-
-        assert_eq!(
-            expected_result,
-            xform::game_grid_to_window(input.0, input.1, offset_left, offset_top)
-        );
+        let camera = xform::Camera::new(4, 3);
+        let expected_results = [
+            Rect {
+                left: 8,
+                top: 7,
+                width: 1,
+                height: 1,
+            },
+            Rect {
+                left: 14,
+                top: 8,
+                width: 3,
+                height: 2,
+            },
+            Rect {
+                left: 20,
+                top: 9,
+                width: 5,
+                height: 3,
+            },
+        ];
+
+        for (metrics, expected_result) in ALL_CELL_METRICS.iter().zip(expected_results.iter()) {
+            assert_eq!(
+                *expected_result,
+                xform::game_grid_to_window(
+                    input.0,
+                    input.1,
+                    offset_left,
+                    offset_top,
+                    &camera,
+                    metrics
+                )
+            );
+        }
     }
 
     #[test]
@@ -898,17 +2857,27 @@ mod tests {
         // |___|___|___|___|
         let offset_left = 4;
         let offset_top = 2;
-        let input = (15, 6);
+        let camera = xform::Camera::new(4, 3);
+        let inputs = [(9, 4), (15, 6), (19, 8)];
 
         // ....
         // ..x.
         // ....
         let expected_result = (2, 1);
 
-        assert_eq!(
-            expected_result,
-            xform::window_to_game_grid(input.0, input.1, offset_left, offset_top)
-        );
+        for (metrics, input) in ALL_CELL_METRICS.iter().zip(inputs.iter()) {
+            assert_eq!(
+                expected_result,
+                xform::window_to_game_grid(
+                    input.0,
+                    input.1,
+                    offset_left,
+                    offset_top,
+                    &camera,
+                    metrics
+                )
+            );
+        }
     }
 
     #[test]
@@ -928,27 +2897,457 @@ mod tests {
         // |___|___|___|___|
         let offset_left = 6;
         let offset_top = 7;
-        let expected_result = Rect {
-            left: 11,
-            top: 12,
-            width: 3,
-            height: 2,
+        let camera = xform::Camera::new(4, 3);
+        let expected_results = [
+            Rect {
+                left: 9,
+                top: 10,
+                width: 1,
+                height: 1,
+            },
+            Rect {
+                left: 11,
+                top: 12,
+                width: 3,
+                height: 2,
+            },
+            Rect {
+                left: 13,
+                top: 14,
+                width: 5,
+                height: 3,
+            },
+        ];
+
+        for (metrics, expected_result) in ALL_CELL_METRICS.iter().zip(expected_results.iter()) {
+            // First verify that we calculated the correct range of cells
+            assert_eq!(
+                *expected_result,
+                xform::game_grid_to_window(
+                    input.0,
+                    input.1,
+                    offset_left,
+                    offset_top,
+                    &camera,
+                    metrics
+                )
+            );
+
+            // Next verify that each cell in that range maps back to our input
+            for row in expected_result.top..=expected_result.bottom() {
+                for col in expected_result.left..=expected_result.right() {
+                    assert_eq!(
+                        input,
+                        xform::window_to_game_grid(
+                            col,
+                            row,
+                            offset_left,
+                            offset_top,
+                            &camera,
+                            metrics
+                        )
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_game_grid_to_window_to_game_grid_with_camera() {
+        // the camera is looking at a 4x3 slice of a 10x10 grid starting at (5,6)
+        // ..........
+        // ..........
+        // ..........
+        // ..........
+        // ..........
+        // ..........
+        // .....cccc.
+        // .....cxcc.
+        // .....cccc.
+        // ..........
+        let input = (6, 7);
+        let mut camera = xform::Camera::new(10, 10);
+        camera.resize(4, 3);
+        camera.scroll(5, 6);
+
+        // |-----(6,7)
+        // V___ ___ ___ ___
+        // |   |   |   |   |
+        // |___|___|___|___|
+        // |   |xxx|   |   |
+        // |___|___|___|___|
+        // |   |   |   |   |
+        // |___|___|___|___|
+        let offset_left = 6;
+        let offset_top = 7;
+        let expected_results = [
+            Rect {
+                left: 9,
+                top: 9,
+                width: 1,
+                height: 1,
+            },
+            Rect {
+                left: 11,
+                top: 10,
+                width: 3,
+                height: 2,
+            },
+            Rect {
+                left: 13,
+                top: 11,
+                width: 5,
+                height: 3,
+            },
+        ];
+
+        for (metrics, expected_result) in ALL_CELL_METRICS.iter().zip(expected_results.iter()) {
+            // First verify that we calculated the correct range of cells
+            assert_eq!(
+                *expected_result,
+                xform::game_grid_to_window(
+                    input.0,
+                    input.1,
+                    offset_left,
+                    offset_top,
+                    &camera,
+                    metrics
+                )
+            );
+
+            // Next verify that each cell in that range maps back to our input
+            for row in expected_result.top..=expected_result.bottom() {
+                for col in expected_result.left..=expected_result.right() {
+                    assert_eq!(
+                        input,
+                        xform::window_to_game_grid(
+                            col,
+                            row,
+                            offset_left,
+                            offset_top,
+                            &camera,
+                            metrics
+                        )
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_camera_stays_inside_grid() {
+        let mut camera = xform::Camera::new(10, 8);
+        assert!(camera.sees_whole_grid());
+
+        // a camera can't show more than the whole grid
+        camera.resize(20, 20);
+        assert_eq!((10, 8), (camera.width(), camera.height()));
+
+        camera.resize(4, 3);
+        assert!(!camera.sees_whole_grid());
+
+        camera.scroll(-5, -5);
+        assert_eq!((0, 0), (camera.x(), camera.y()));
+
+        camera.scroll(100, 100);
+        assert_eq!((6, 5), (camera.x(), camera.y()));
+        assert!(camera.contains(9, 7));
+        assert!(!camera.contains(5, 7));
+
+        // growing the viewport back out pulls the camera back inside the grid
+        camera.resize(8, 8);
+        assert_eq!((2, 0), (camera.x(), camera.y()));
+    }
+
+    #[test]
+    fn test_camera_scrolls_to_show_cell() {
+        let mut camera = xform::Camera::new(20, 20);
+        camera.resize(5, 4);
+
+        camera.scroll_to_show(2, 3);
+        assert_eq!((0, 0), (camera.x(), camera.y()));
+
+        // only scrolls as far as it has to
+        camera.scroll_to_show(7, 10);
+        assert_eq!((3, 7), (camera.x(), camera.y()));
+        assert!(camera.contains(7, 10));
+
+        camera.scroll_to_show(1, 7);
+        assert_eq!((1, 7), (camera.x(), camera.y()));
+    }
+
+    #[test]
+    fn test_seeded_rng_repeats() {
+        let mut a = utils::SeededRng::new(42);
+        let mut b = utils::SeededRng::new(42);
+        let mut c = utils::SeededRng::new(43);
+        let rolls = |rng: &mut utils::SeededRng| -> Vec<usize> {
+            (0..20).map(|_| rng.gen_range(3, 10)).collect()
+        };
+
+        let a_rolls = rolls(&mut a);
+        assert_eq!(a_rolls, rolls(&mut b));
+        assert_ne!(a_rolls, rolls(&mut c));
+        assert!(a_rolls.iter().all(|roll| (3..10).contains(roll)));
+    }
+
+    #[test]
+    fn test_hidden_cells_stay_seen() {
+        // with no room for revealed cells, every reveal gets hidden again on the next update
+        let mut game_grid = GameGrid::new(
+            3,
+            3,
+            0,
+            DEFAULT_REVEAL_TIME,
+            CellDistribution::default(),
+            &mut ThreadRangeRng::new(),
+        );
+        assert!(!game_grid.cell(1, 2).unwrap().seen);
+
+        game_grid.try_reveal(1, 2);
+        let revealed_cell = game_grid.cell(1, 2).unwrap();
+        assert!(revealed_cell.revealed && revealed_cell.seen);
+
+        game_grid.reset_expired_cells();
+        let hidden_cell = game_grid.cell(1, 2).unwrap();
+        assert!(!hidden_cell.revealed && hidden_cell.seen);
+    }
+
+    #[test]
+    fn test_treasure_hunt_hints() {
+        let treasure_values = [1, 1, 2, 5];
+        let game_grid = GameGrid::new_treasure_hunt(
+            12,
+            8,
+            6,
+            DEFAULT_REVEAL_TIME,
+            CellDistribution::default(),
+            &treasure_values,
+            &mut ThreadRangeRng::new(),
+        );
+        let mut values: Vec<u32> = game_grid.treasures().iter().map(|t| t.2).collect();
+        values.sort_unstable();
+        assert_eq!(treasure_values.to_vec(), values);
+        assert_eq!(None, game_grid.find_solution());
+
+        // tiny boards leave room for a hint
+        let game_grid = GameGrid::new_treasure_hunt(
+            2,
+            3,
+            6,
+            DEFAULT_REVEAL_TIME,
+            CellDistribution::default(),
+            &[1, 1, 2, 2, 3, 5],
+            &mut ThreadRangeRng::new(),
+        );
+        assert_eq!(5, game_grid.treasures().len());
+        let game_grid = GameGrid::new_treasure_hunt(
+            1,
+            1,
+            6,
+            DEFAULT_REVEAL_TIME,
+            CellDistribution::default(),
+            &treasure_values,
+            &mut ThreadRangeRng::new(),
+        );
+        assert_eq!(vec![(0, 0, 1)], game_grid.treasures());
+
+        // plain hints go to the nearest treasure and value hints to the most valuable one
+        let mut game_grid = GameGrid::from_items(
+            6,
+            1,
+            vec![
+                GridItem::ValueHint(HintDir::Left),
+                GridItem::Treasure(1),
+                GridItem::Hint(HintDir::Left),
+                GridItem::Hint(HintDir::Left),
+                GridItem::Treasure(5),
+                GridItem::Treasure(2),
+            ],
+            6,
+            DEFAULT_REVEAL_TIME,
+        );
+        game_grid.collect_treasure(5, 0);
+        let item = |x| game_grid.cell(x, 0).unwrap().item;
+        assert_eq!(GridItem::ValueHint(HintDir::Right), item(0));
+        assert_eq!(GridItem::Hint(HintDir::Left), item(2));
+        assert_eq!(GridItem::Hint(HintDir::Right), item(3));
+        assert_eq!(GridItem::Empty, item(5));
+
+        game_grid.collect_treasure(4, 0);
+        let item = |x| game_grid.cell(x, 0).unwrap().item;
+        assert_eq!(GridItem::Hint(HintDir::Left), item(3));
+        assert_eq!(vec![(1, 0, 1)], game_grid.treasures());
+    }
+
+    #[test]
+    fn test_board_layout_fits_min_screen_size() {
+        let (grid_width, grid_height) = (15, 10);
+        let (min_width, min_height) =
+            layout::min_screen_size(grid_width, grid_height, &xform::CellMetrics::NORMAL);
+
+        let screen = Rect {
+            left: 0,
+            top: 0,
+            width: min_width,
+            height: min_height,
+        };
+        let board_layout = layout::board_layout(
+            grid_width,
+            grid_height,
+            &xform::CellMetrics::NORMAL,
+            &screen,
+        )
+        .unwrap();
+        assert!(board_layout.level.top >= screen.top);
+        assert!(board_layout.game_over.bottom() <= screen.bottom());
+        assert_eq!(screen.left, board_layout.grid.left);
+
+        let too_narrow = Rect {
+            width: min_width - 1,
+            ..screen
         };
+        assert!(layout::board_layout(
+            grid_width,
+            grid_height,
+            &xform::CellMetrics::NORMAL,
+            &too_narrow
+        )
+        .is_none());
+
+        let too_short = Rect {
+            height: min_height - 1,
+            ..screen
+        };
+        assert!(layout::board_layout(
+            grid_width,
+            grid_height,
+            &xform::CellMetrics::NORMAL,
+            &too_short
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_board_layout_centers_grid() {
+        let screen = Rect {
+            left: 0,
+            top: 0,
+            width: 100,
+            height: 60,
+        };
+        let board_layout =
+            layout::board_layout(15, 10, &xform::CellMetrics::NORMAL, &screen).unwrap();
 
-        // First verify that we calculated the correct range of cells
+        // a 15x10 grid is 63x22 characters
         assert_eq!(
-            expected_result,
-            xform::game_grid_to_window(input.0, input.1, offset_left, offset_top)
+            Rect {
+                left: 18,
+                top: 19,
+                width: 63,
+                height: 22,
+            },
+            board_layout.grid
         );
+        assert_eq!(board_layout.grid.left, board_layout.time.left);
+        assert_eq!(board_layout.time.top - 1, board_layout.level.top);
+    }
 
-        // Next verify that each cell in that range maps back to our input
-        for row in expected_result.top..=expected_result.bottom() {
-            for col in expected_result.left..=expected_result.right() {
-                assert_eq!(
-                    input,
-                    xform::window_to_game_grid(col, row, offset_left, offset_top)
-                );
-            }
-        }
+    #[test]
+    fn test_split_screen() {
+        let screen = Rect {
+            left: 0,
+            top: 0,
+            width: 81,
+            height: 24,
+        };
+        assert_eq!(vec![screen], layout::split_screen(&screen, 1));
+
+        let halves = layout::split_screen(&screen, 2);
+        assert_eq!((0, 40), (halves[0].left, halves[0].width));
+        assert_eq!((40, 41), (halves[1].left, halves[1].width));
+        assert!(halves.iter().all(|half| half.height == 24));
+    }
+
+    #[test]
+    fn test_grid_size_from_level_fits_screen() {
+        // plenty of room: the board grows every 3 levels
+        assert_eq!(((15, 10), 0), get_grid_size_from_level(1, (100, 100)));
+        assert_eq!(((18, 13), 0), get_grid_size_from_level(9, (100, 100)));
+
+        // the board stops growing once it fills the screen and the rest turns into overflow
+        assert_eq!(((17, 12), 1), get_grid_size_from_level(9, (17, 20)));
+
+        // tiny screens shrink the board rather than letting it run off screen
+        assert_eq!(((15, 6), 0), get_grid_size_from_level(1, (19, 6)));
+        assert_eq!(((4, 4), 3), get_grid_size_from_level(9, (2, 2)));
+    }
+
+    #[test]
+    fn test_survival_board_time_tops_up_the_bank() {
+        let board_time = std::time::Duration::from_secs(15);
+        assert_eq!(board_time, get_survival_board_time(board_time, None));
+        assert_eq!(
+            std::time::Duration::from_secs(7),
+            get_survival_board_time(board_time, Some(std::time::Duration::from_secs(2)))
+        );
+    }
+
+    #[test]
+    fn test_level_config_overflow_increases_difficulty() {
+        let roomy_config = get_level_config(15, (100, 100));
+        let cramped_config = get_level_config(15, (15, 10));
+
+        assert_eq!(roomy_config.grid_size, (20, 15));
+        assert_eq!(cramped_config.grid_size, (15, 10));
+        assert!(cramped_config.board_time < roomy_config.board_time);
+        assert!(cramped_config.max_revealed_cells < roomy_config.max_revealed_cells);
+        assert!(cramped_config.cell_distribution.traps > roomy_config.cell_distribution.traps);
+        assert_eq!(
+            roomy_config.cell_distribution.total(),
+            cramped_config.cell_distribution.total()
+        );
+    }
+
+    #[test]
+    fn test_continues_after_a_win() {
+        let options = cli::Options::default();
+        let mut run = RunState::default();
+        assert!(continues_after(&run, &options, GameResult::Win, 3, None));
+        assert!(!continues_after(&run, &options, GameResult::Lose, 3, None));
+
+        // a speedrun goes on until its last level
+        run.speedrun = Some(speedrun::Run::new(2, &speedrun::PersonalBests::new()));
+        assert!(continues_after(&run, &options, GameResult::Win, 3, None));
+        run.speedrun
+            .as_mut()
+            .unwrap()
+            .finish_level(std::time::Duration::from_secs(5));
+        assert!(!continues_after(&run, &options, GameResult::Win, 3, None));
+
+        // boards from a file and treasure hunts are the only board there is
+        let run = RunState::default();
+        let board_options = cli::Options {
+            board: Some(board_file::parse("|>*|").unwrap()),
+            ..cli::Options::default()
+        };
+        assert!(!continues_after(
+            &run,
+            &board_options,
+            GameResult::Win,
+            3,
+            None
+        ));
+        let treasure_options = cli::Options {
+            treasure: true,
+            ..cli::Options::default()
+        };
+        assert!(!continues_after(
+            &run,
+            &treasure_options,
+            GameResult::Win,
+            3,
+            None
+        ));
     }
 }
@@ -0,0 +1,727 @@
+// The server and client for network races (see race.rs). One `lnf host` runs the server on its own
+// threads and joins it like everyone else, and every `lnf join` connects to it over TCP.
+//
+// Every message is one line of JSON with a "type" field saying what it is. This is version 1 of the
+// protocol, and a client has to say which version it speaks before anything else.
+//
+// client to server:
+//
+//     hello      {"type": "hello", "version": 1, "name": "ada"}
+//                the first message after connecting. the reply is a welcome, or an error
+//     start      {"type": "start"}
+//                starts the race. only the host can start it and only once
+//     progress   {"type": "progress", "board": 1, "reveals": 4}
+//                how many cells have been revealed on the board so far
+//     found      {"type": "found", "board": 1}
+//                the hidden cell on the board was found
+//     lost       {"type": "lost", "board": 1}
+//                the board ran out of time
+//
+// server to client:
+//
+//     welcome    {"type": "welcome", "version": 1, "player": 0, "host": true, "seed": 99,
+//                 "boards": 5}
+//                "player" is the id everyone else sees this player as. every board is generated
+//                from "seed" (see race.rs) and there are "boards" of them in the race. if the host
+//                leaves, the next player in line gets sent another welcome with "host" set
+//     players    {"type": "players", "players": [{"id": 0, "name": "ada", "reveals": 4,
+//                 "boards_won": 1, "connected": true}]}
+//                everyone in the race. sent whenever anyone joins, leaves or makes progress
+//     board      {"type": "board", "board": 1}
+//                everyone starts playing the board. boards are numbered from 1
+//     board_won  {"type": "board_won", "board": 1, "winner": 0}
+//                the board is over. "winner" found it first, or is null if nobody found it
+//     finished   {"type": "finished"}
+//                the race is over
+//     error      {"type": "error", "message": "..."}
+//                something went wrong. the server hangs up after errors during the hello
+//
+// Messages about a board other than the one being played are ignored, so a find that shows up just
+// after someone else's is never counted.
+
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::time::Duration;
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// how long to wait for the server to answer a hello
+const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Hello { version: u32, name: String },
+    Start,
+    Progress { board: usize, reveals: usize },
+    Found { board: usize },
+    Lost { board: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Welcome {
+        version: u32,
+        player: usize,
+        host: bool,
+        seed: u64,
+        boards: usize,
+    },
+    Players {
+        players: Vec<PlayerInfo>,
+    },
+    Board {
+        board: usize,
+    },
+    BoardWon {
+        board: usize,
+        winner: Option<usize>,
+    },
+    Finished,
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerInfo {
+    pub id: usize,
+    pub name: String,
+    // on the board being played
+    pub reveals: usize,
+    pub boards_won: usize,
+    pub connected: bool,
+}
+
+fn send<M: Serialize>(mut stream: &TcpStream, message: &M) -> std::io::Result<()> {
+    let line = serde_json::to_string(message).map_err(std::io::Error::from)?;
+    writeln!(stream, "{}", line)?;
+    stream.flush()
+}
+
+// what the server's connection threads tell the thread running the race
+enum Event {
+    Connected(usize, TcpStream),
+    Message(usize, Result<ClientMessage, String>),
+    Disconnected(usize),
+}
+
+pub struct Server {
+    listener: TcpListener,
+    seed: u64,
+    boards: usize,
+}
+
+impl Server {
+    pub fn bind(addr: &str, seed: u64, boards: usize) -> Result<Self, String> {
+        let listener =
+            TcpListener::bind(addr).map_err(|e| format!("failed to listen on {}: {}", addr, e))?;
+        Ok(Server {
+            listener,
+            seed,
+            boards,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.listener.local_addr().unwrap()
+    }
+
+    // Runs the race on background threads. The server stops once the race has started and everyone
+    // has left.
+    pub fn spawn(self) {
+        let (events, event_receiver) = mpsc::channel();
+
+        let listener = self.listener;
+        std::thread::spawn(move || {
+            let mut next_id = 0;
+            for stream in listener.incoming() {
+                let (stream, reader) = match stream.and_then(|s| s.try_clone().map(|r| (s, r))) {
+                    Ok(streams) => streams,
+                    Err(_) => continue,
+                };
+
+                let id = next_id;
+                next_id += 1;
+                if events.send(Event::Connected(id, stream)).is_err() {
+                    return;
+                }
+
+                let events = events.clone();
+                std::thread::spawn(move || {
+                    for line in BufReader::new(reader).lines() {
+                        let line = match line {
+                            Ok(line) => line,
+                            Err(_) => break,
+                        };
+                        let message = serde_json::from_str(&line)
+                            .map_err(|e| format!("invalid message '{}': {}", line, e));
+                        if events.send(Event::Message(id, message)).is_err() {
+                            return;
+                        }
+                    }
+                    let _ = events.send(Event::Disconnected(id));
+                });
+            }
+        });
+
+        let mut race = ServerRace::new(self.seed, self.boards);
+        std::thread::spawn(move || {
+            for event in event_receiver {
+                race.handle(event);
+                if race.is_over() {
+                    return;
+                }
+            }
+        });
+    }
+}
+
+struct Connection {
+    stream: Option<TcpStream>,
+    // None until they've said hello
+    name: Option<String>,
+    reveals: usize,
+    boards_won: usize,
+    // whether they've found the board being played or run out of time on it
+    done: bool,
+}
+
+// the race as the server sees it
+struct ServerRace {
+    seed: u64,
+    boards: usize,
+    connections: Vec<Connection>,
+    // the board being played, 0 while waiting for the host to start
+    board: usize,
+}
+
+impl ServerRace {
+    fn new(seed: u64, boards: usize) -> Self {
+        ServerRace {
+            seed,
+            boards,
+            connections: vec![],
+            board: 0,
+        }
+    }
+
+    // Once the race has started and everyone has left there's nothing left to do
+    fn is_over(&self) -> bool {
+        self.board > 0 && self.connections.iter().all(|c| c.stream.is_none())
+    }
+
+    // the first player still here to have said hello hosts
+    fn host(&self) -> Option<usize> {
+        self.connections
+            .iter()
+            .position(|c| c.name.is_some() && c.stream.is_some())
+    }
+
+    fn welcome(&self, id: usize) -> ServerMessage {
+        ServerMessage::Welcome {
+            version: PROTOCOL_VERSION,
+            player: id,
+            host: self.host() == Some(id),
+            seed: self.seed,
+            boards: self.boards,
+        }
+    }
+
+    fn handle(&mut self, event: Event) {
+        match event {
+            Event::Connected(id, stream) => {
+                // ids come from the order connections were accepted, so they line up
+                debug_assert_eq!(id, self.connections.len());
+                self.connections.push(Connection {
+                    stream: Some(stream),
+                    name: None,
+                    reveals: 0,
+                    boards_won: 0,
+                    done: false,
+                });
+                if self.board > 0 {
+                    self.hang_up(id, "the race has already started");
+                }
+            }
+            // anything a player sends after being hung up on gets ignored
+            Event::Message(id, _) if self.connections[id].stream.is_none() => (),
+            Event::Message(id, Ok(message)) => self.handle_message(id, message),
+            Event::Message(id, Err(message)) => self.send(id, &ServerMessage::Error { message }),
+            Event::Disconnected(id) => {
+                let host = self.host();
+                self.connections[id].stream = None;
+                // someone has to be able to start the race
+                if let Some(new_host) = self.host().filter(|new_host| host != Some(*new_host)) {
+                    self.send(new_host, &self.welcome(new_host));
+                }
+                self.broadcast_players();
+                self.check_board_over();
+            }
+        }
+    }
+
+    fn handle_message(&mut self, id: usize, message: ClientMessage) {
+        let said_hello = self.connections[id].name.is_some();
+        match message {
+            ClientMessage::Hello { version, .. } if version != PROTOCOL_VERSION => {
+                let message = format!(
+                    "protocol version {} isn't supported (the server speaks version {})",
+                    version, PROTOCOL_VERSION
+                );
+                self.hang_up(id, &message);
+            }
+            ClientMessage::Hello { .. } if said_hello => {
+                self.send_error(id, "already said hello");
+            }
+            ClientMessage::Hello { name, .. } => {
+                self.connections[id].name = Some(name);
+                self.send(id, &self.welcome(id));
+                self.broadcast_players();
+            }
+            _ if !said_hello => self.hang_up(id, "expected a hello first"),
+            ClientMessage::Start if self.host() != Some(id) => {
+                self.send_error(id, "only the host can start the race");
+            }
+            ClientMessage::Start if self.board > 0 => {
+                self.send_error(id, "the race has already started");
+            }
+            ClientMessage::Start => self.next_board(),
+            // nothing counts in the lobby or once the race is over
+            ClientMessage::Progress { .. }
+            | ClientMessage::Found { .. }
+            | ClientMessage::Lost { .. }
+                if !self.racing() => {}
+            ClientMessage::Progress { board, reveals } if board == self.board => {
+                self.connections[id].reveals = reveals;
+                self.broadcast_players();
+            }
+            ClientMessage::Found { board } if board == self.board => {
+                self.connections[id].boards_won += 1;
+                self.broadcast_players();
+                self.finish_board(Some(id));
+            }
+            ClientMessage::Lost { board } if board == self.board => {
+                self.connections[id].done = true;
+                self.check_board_over();
+            }
+            // about a board that's already over
+            ClientMessage::Progress { .. }
+            | ClientMessage::Found { .. }
+            | ClientMessage::Lost { .. } => (),
+        }
+    }
+
+    // whether a board is being played, i.e. the race has started and isn't over yet
+    fn racing(&self) -> bool {
+        self.board > 0 && self.board <= self.boards
+    }
+
+    // The board goes to nobody once everyone still here has run out of time on it
+    fn check_board_over(&mut self) {
+        let everyone_done = self
+            .connections
+            .iter()
+            .filter(|c| c.stream.is_some() && c.name.is_some())
+            .all(|c| c.done);
+        if self.racing() && everyone_done {
+            self.finish_board(None);
+        }
+    }
+
+    fn finish_board(&mut self, winner: Option<usize>) {
+        let board_won = ServerMessage::BoardWon {
+            board: self.board,
+            winner,
+        };
+        self.broadcast(&board_won);
+        self.next_board();
+    }
+
+    fn next_board(&mut self) {
+        self.board += 1;
+        if self.board > self.boards {
+            self.broadcast(&ServerMessage::Finished);
+            return;
+        }
+
+        for connection in &mut self.connections {
+            connection.reveals = 0;
+            connection.done = false;
+        }
+        self.broadcast_players();
+        self.broadcast(&ServerMessage::Board { board: self.board });
+    }
+
+    fn broadcast_players(&mut self) {
+        let players = self
+            .connections
+            .iter()
+            .enumerate()
+            .filter_map(|(id, c)| {
+                Some(PlayerInfo {
+                    id,
+                    name: c.name.clone()?,
+                    reveals: c.reveals,
+                    boards_won: c.boards_won,
+                    connected: c.stream.is_some(),
+                })
+            })
+            .collect();
+        self.broadcast(&ServerMessage::Players { players });
+    }
+
+    // only players that have said hello hear about the race
+    fn broadcast(&mut self, message: &ServerMessage) {
+        for id in 0..self.connections.len() {
+            if self.connections[id].name.is_some() {
+                self.send(id, message);
+            }
+        }
+    }
+
+    fn send(&mut self, id: usize, message: &ServerMessage) {
+        let connection = &mut self.connections[id];
+        if let Some(stream) = &connection.stream {
+            if send(stream, message).is_err() {
+                connection.stream = None;
+            }
+        }
+    }
+
+    fn send_error(&mut self, id: usize, message: &str) {
+        let message = String::from(message);
+        self.send(id, &ServerMessage::Error { message });
+    }
+
+    fn hang_up(&mut self, id: usize, message: &str) {
+        self.send_error(id, message);
+        // only stop writing so the error still makes it there before the connection closes
+        if let Some(stream) = self.connections[id].stream.take() {
+            let _ = stream.shutdown(Shutdown::Write);
+        }
+    }
+}
+
+pub struct Client {
+    stream: TcpStream,
+    messages: mpsc::Receiver<ServerMessage>,
+    pub player: usize,
+    pub host: bool,
+    pub seed: u64,
+    pub boards: usize,
+}
+
+impl Client {
+    // Connects to the server at `addr` and says hello, waiting for the welcome
+    pub fn connect(addr: &str, name: &str) -> Result<Self, String> {
+        let stream = TcpStream::connect(addr)
+            .map_err(|e| format!("failed to connect to {}: {}", addr, e))?;
+        let hello = ClientMessage::Hello {
+            version: PROTOCOL_VERSION,
+            name: String::from(name),
+        };
+        send(&stream, &hello).map_err(|e| format!("failed to say hello: {}", e))?;
+
+        let reader = stream.try_clone().map_err(|e| e.to_string())?;
+        reader
+            .set_read_timeout(Some(HELLO_TIMEOUT))
+            .map_err(|e| e.to_string())?;
+        let mut reader = BufReader::new(reader);
+        let mut line = String::new();
+        let welcome = match reader.read_line(&mut line) {
+            Ok(0) => return Err(format!("{} hung up", addr)),
+            Ok(_) => serde_json::from_str(&line)
+                .map_err(|e| format!("invalid message '{}': {}", line.trim(), e))?,
+            Err(e) => return Err(format!("no answer from {}: {}", addr, e)),
+        };
+
+        let (player, host, seed, boards) = match welcome {
+            ServerMessage::Welcome {
+                player,
+                host,
+                seed,
+                boards,
+                ..
+            } => (player, host, seed, boards),
+            ServerMessage::Error { message } => return Err(message),
+            message => return Err(format!("expected a welcome, got {:?}", message)),
+        };
+
+        // everything after the welcome comes in on its own thread so the game never waits on it
+        reader
+            .get_ref()
+            .set_read_timeout(None)
+            .map_err(|e| e.to_string())?;
+        let (message_sender, messages) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in reader.lines() {
+                let message = match line.map(|line| serde_json::from_str(&line)) {
+                    Ok(Ok(message)) => message,
+                    _ => return,
+                };
+                if message_sender.send(message).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(Client {
+            stream,
+            messages,
+            player,
+            host,
+            seed,
+            boards,
+        })
+    }
+
+    pub fn send(&self, message: &ClientMessage) -> Result<(), String> {
+        send(&self.stream, message).map_err(|_| String::from("lost the connection to the server"))
+    }
+
+    // The next message from the server, if one has come in. Errors once the server is gone.
+    pub fn try_recv(&self) -> Result<Option<ServerMessage>, String> {
+        match self.messages.try_recv() {
+            Ok(message) => Ok(Some(message)),
+            Err(mpsc::TryRecvError::Empty) => Ok(None),
+            Err(mpsc::TryRecvError::Disconnected) => {
+                Err(String::from("lost the connection to the server"))
+            }
+        }
+    }
+}
+
+impl Drop for Client {
+    // the reader thread holds on to its own handle to the connection, so it has to be closed
+    // explicitly for the server to see this player leave
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn start_server(boards: usize) -> String {
+        let server = Server::bind("127.0.0.1:0", 42, boards).unwrap();
+        let addr = server.local_addr().to_string();
+        server.spawn();
+        addr
+    }
+
+    // skips over everyone's progress updates
+    fn next_message(client: &Client) -> ServerMessage {
+        loop {
+            match client.messages.recv_timeout(TIMEOUT) {
+                Ok(ServerMessage::Players { .. }) => continue,
+                Ok(message) => return message,
+                Err(e) => panic!("no message from the server: {}", e),
+            }
+        }
+    }
+
+    fn next_players(client: &Client) -> Vec<PlayerInfo> {
+        loop {
+            match client.messages.recv_timeout(TIMEOUT) {
+                Ok(ServerMessage::Players { players }) => return players,
+                Ok(_) => continue,
+                Err(e) => panic!("no players from the server: {}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_race_over_localhost() {
+        let addr = start_server(2);
+        let ada = Client::connect(&addr, "ada").unwrap();
+        assert!(ada.host);
+        assert_eq!((0, 42, 2), (ada.player, ada.seed, ada.boards));
+
+        let bob = Client::connect(&addr, "bob").unwrap();
+        assert!(!bob.host);
+        assert_eq!(1, bob.player);
+
+        // only the host starts the race
+        bob.send(&ClientMessage::Start).unwrap();
+        assert!(matches!(next_message(&bob), ServerMessage::Error { .. }));
+
+        ada.send(&ClientMessage::Start).unwrap();
+        for client in [&ada, &bob] {
+            assert_eq!(ServerMessage::Board { board: 1 }, next_message(client));
+        }
+
+        bob.send(&ClientMessage::Progress {
+            board: 1,
+            reveals: 3,
+        })
+        .unwrap();
+        let players = loop {
+            let players = next_players(&ada);
+            if players[1].reveals == 3 {
+                break players;
+            }
+        };
+        assert_eq!("bob", players[1].name);
+
+        bob.send(&ClientMessage::Found { board: 1 }).unwrap();
+        let boards_won = |players: Vec<PlayerInfo>| -> Vec<usize> {
+            players.iter().map(|player| player.boards_won).collect()
+        };
+        assert_eq!(vec![0, 1], boards_won(next_players(&ada)));
+        for client in [&ada, &bob] {
+            assert_eq!(
+                ServerMessage::BoardWon {
+                    board: 1,
+                    winner: Some(1)
+                },
+                next_message(client)
+            );
+            assert_eq!(ServerMessage::Board { board: 2 }, next_message(client));
+        }
+
+        // bob got there first, so ada finding board 1 now doesn't count for anything
+        ada.send(&ClientMessage::Found { board: 1 }).unwrap();
+
+        // nobody wins a board that everyone runs out of time on
+        ada.send(&ClientMessage::Lost { board: 2 }).unwrap();
+        bob.send(&ClientMessage::Lost { board: 2 }).unwrap();
+        assert_eq!(
+            ServerMessage::BoardWon {
+                board: 2,
+                winner: None
+            },
+            next_message(&ada)
+        );
+        assert_eq!(ServerMessage::Finished, next_message(&ada));
+    }
+
+    #[test]
+    fn test_finds_outside_the_race_are_ignored() {
+        let addr = start_server(1);
+        let ada = Client::connect(&addr, "ada").unwrap();
+        let bob = Client::connect(&addr, "bob").unwrap();
+
+        // a find in the lobby doesn't win anything or start the race
+        bob.send(&ClientMessage::Found { board: 0 }).unwrap();
+        bob.send(&ClientMessage::Start).unwrap();
+        assert_eq!(
+            ServerMessage::Error {
+                message: String::from("only the host can start the race")
+            },
+            next_message(&bob)
+        );
+
+        ada.send(&ClientMessage::Start).unwrap();
+        assert_eq!(ServerMessage::Board { board: 1 }, next_message(&ada));
+        ada.send(&ClientMessage::Found { board: 1 }).unwrap();
+        assert_eq!(
+            ServerMessage::BoardWon {
+                board: 1,
+                winner: Some(0)
+            },
+            next_message(&ada)
+        );
+        assert_eq!(ServerMessage::Finished, next_message(&ada));
+
+        // and neither does one after the race is over
+        ada.send(&ClientMessage::Found { board: 2 }).unwrap();
+        ada.send(&ClientMessage::Start).unwrap();
+        assert_eq!(
+            ServerMessage::Error {
+                message: String::from("the race has already started")
+            },
+            next_message(&ada)
+        );
+    }
+
+    #[test]
+    fn test_host_leaving_the_lobby() {
+        let addr = start_server(1);
+        let ada = Client::connect(&addr, "ada").unwrap();
+        let bob = Client::connect(&addr, "bob").unwrap();
+        assert!(!bob.host);
+
+        drop(ada);
+        assert!(matches!(
+            next_message(&bob),
+            ServerMessage::Welcome { host: true, .. }
+        ));
+        bob.send(&ClientMessage::Start).unwrap();
+        assert_eq!(ServerMessage::Board { board: 1 }, next_message(&bob));
+    }
+
+    #[test]
+    fn test_leaving_mid_board() {
+        let addr = start_server(1);
+        let ada = Client::connect(&addr, "ada").unwrap();
+        let bob = Client::connect(&addr, "bob").unwrap();
+        ada.send(&ClientMessage::Start).unwrap();
+        assert_eq!(ServerMessage::Board { board: 1 }, next_message(&bob));
+
+        // once bob's gone, ada running out of time is everyone running out of time
+        drop(bob);
+        ada.send(&ClientMessage::Lost { board: 1 }).unwrap();
+        assert_eq!(ServerMessage::Board { board: 1 }, next_message(&ada));
+        assert_eq!(
+            ServerMessage::BoardWon {
+                board: 1,
+                winner: None
+            },
+            next_message(&ada)
+        );
+    }
+
+    #[test]
+    fn test_hello_is_checked() {
+        let addr = start_server(1);
+
+        let stream = TcpStream::connect(&addr).unwrap();
+        let hello = ClientMessage::Hello {
+            version: PROTOCOL_VERSION + 1,
+            name: String::from("future"),
+        };
+        send(&stream, &hello).unwrap();
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line).unwrap();
+        assert!(line.starts_with(r#"{"type":"error","message":"protocol version 2"#));
+
+        let ada = Client::connect(&addr, "ada").unwrap();
+        ada.send(&ClientMessage::Start).unwrap();
+        assert_eq!(ServerMessage::Board { board: 1 }, next_message(&ada));
+        assert_eq!(
+            Err(String::from("the race has already started")),
+            Client::connect(&addr, "late").map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_messages_are_documented_json() {
+        assert_eq!(
+            r#"{"type":"progress","board":1,"reveals":4}"#,
+            serde_json::to_string(&ClientMessage::Progress {
+                board: 1,
+                reveals: 4
+            })
+            .unwrap()
+        );
+        assert_eq!(
+            Ok(ServerMessage::BoardWon {
+                board: 1,
+                winner: None
+            }),
+            serde_json::from_str(r#"{"type": "board_won", "board": 1, "winner": null}"#)
+                .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Ok(ClientMessage::Start),
+            serde_json::from_str(r#"{"type": "start"}"#).map_err(|e| e.to_string())
+        );
+    }
+}
@@ -0,0 +1,476 @@
+// Network races. `lnf host` starts a race that other players join from their own terminals with
+// `lnf join <HOST>:<PORT>`, and the host starts it from the lobby once everyone's in.
+//
+// Everyone plays the same boards, one at a time, rolled from the seed the server hands out. The
+// first player to find a board's hidden cell wins it and the board ends for everyone else. The
+// sidebar keeps track of how everyone's doing: reveals on the board being played, boards won, and
+// who won each board so far. See net.rs for the server and the protocol.
+
+use crate::level::Level;
+use crate::net::{self, ClientMessage, PlayerInfo, ServerMessage};
+use crate::render::Surface;
+use crate::theme::Style;
+use crate::utils::{Rect, SeededRng};
+use crate::{cli, config, hot_seat, layout, term, versus};
+use crate::{GameResult, InputUpdate, Pilot, PlayerBoard, RunState};
+use snm_rand_utils::range_rng::{RangeRng, ThreadRangeRng};
+use std::time::Duration;
+
+pub const DEFAULT_PORT: u16 = 7878;
+pub const DEFAULT_BOARDS: usize = 5;
+
+const SIDEBAR_WIDTH: i32 = 30;
+
+pub struct Race {
+    client: net::Client,
+    // everyone in the race as of the server's last update
+    players: Vec<PlayerInfo>,
+    // the board being played, 0 before the race starts
+    board: usize,
+    // the board the server says to play next
+    next_board: Option<usize>,
+    // who won each board that's over, in order
+    winners: Vec<Option<usize>>,
+    finished: bool,
+    // what the server's been told about the board being played
+    sent_reveals: usize,
+    sent_result: bool,
+    // set once the connection to the server is gone
+    error: Option<String>,
+}
+
+impl Race {
+    pub fn new(client: net::Client) -> Self {
+        Race {
+            client,
+            players: vec![],
+            board: 0,
+            next_board: None,
+            winners: vec![],
+            finished: false,
+            sent_reveals: 0,
+            sent_result: false,
+            error: None,
+        }
+    }
+
+    // Takes in everything the server's said since the last time
+    fn receive(&mut self) {
+        loop {
+            let message = match self.client.try_recv() {
+                Ok(Some(message)) => message,
+                Ok(None) => return,
+                Err(e) => {
+                    self.error.get_or_insert(e);
+                    return;
+                }
+            };
+
+            match message {
+                ServerMessage::Players { players } => self.players = players,
+                ServerMessage::Board { board } => self.next_board = Some(board),
+                ServerMessage::BoardWon { board, winner } if board == self.winners.len() + 1 => {
+                    self.winners.push(winner)
+                }
+                ServerMessage::Finished => self.finished = true,
+                // the host left the lobby and it's up to us to start the race now
+                ServerMessage::Welcome { host, .. } => self.client.host = host,
+                // nothing we send mid-race should get an error back, and there's nothing to be
+                // done about it if it does
+                ServerMessage::BoardWon { .. } | ServerMessage::Error { .. } => (),
+            }
+        }
+    }
+
+    fn send(&mut self, message: &ClientMessage) {
+        if let Err(e) = self.client.send(message) {
+            self.error.get_or_insert(e);
+        }
+    }
+
+    fn start_board(&mut self, board: usize) {
+        self.board = board;
+        self.next_board = None;
+        self.sent_reveals = 0;
+        self.sent_result = false;
+    }
+
+    // Keeps the server up to date with how the board is going and ends it early once someone else
+    // has found their hidden cell. Called every frame while a board is being played.
+    pub fn update(&mut self, level: &mut Level) {
+        self.receive();
+
+        // losing the server ends the board too
+        if self.board_decided() && level.result().is_none() {
+            level.concede();
+            self.sent_result = true;
+        }
+
+        let board = self.board;
+        if level.reveals() != self.sent_reveals {
+            self.sent_reveals = level.reveals();
+            let reveals = self.sent_reveals;
+            self.send(&ClientMessage::Progress { board, reveals });
+        }
+
+        if let (false, Some(result)) = (self.sent_result, level.result()) {
+            self.sent_result = true;
+            match result {
+                GameResult::Win => self.send(&ClientMessage::Found { board }),
+                GameResult::Lose | GameResult::Quit => self.send(&ClientMessage::Lost { board }),
+            }
+        }
+    }
+
+    // whether the board being played is over for everyone
+    pub fn board_decided(&self) -> bool {
+        self.winners.len() >= self.board || self.error.is_some()
+    }
+
+    pub fn has_more_boards(&self) -> bool {
+        self.board < self.client.boards
+    }
+
+    fn name(&self, id: usize) -> &str {
+        self.players
+            .iter()
+            .find(|player| player.id == id)
+            .map_or("?", |player| player.name.as_str())
+    }
+
+    // everyone by how many boards they won, then who won the race
+    pub fn summary(&self) -> Vec<String> {
+        let mut players: Vec<&PlayerInfo> = self.players.iter().collect();
+        players.sort_by_key(|player| std::cmp::Reverse(player.boards_won));
+
+        let mut lines: Vec<String> = players
+            .iter()
+            .map(|player| {
+                let boards = if player.boards_won == 1 {
+                    "board"
+                } else {
+                    "boards"
+                };
+                format!("{}: {} {} won", player.name, player.boards_won, boards)
+            })
+            .collect();
+
+        match players.as_slice() {
+            [first, second, ..] if first.boards_won == second.boards_won => {
+                lines.push(String::from("It's a tie!"))
+            }
+            [first, ..] => lines.push(format!("{} wins the race!", first.name)),
+            [] => (),
+        }
+        lines
+    }
+}
+
+// Starts a server for a race of `boards` boards on `port` and joins it. Returns the race along with
+// the port it's on, which is picked by the OS if `port` is 0.
+pub fn host(port: u16, boards: usize, name: &str) -> Result<(Race, u16), String> {
+    let seed = ThreadRangeRng::new().gen_range(0, usize::MAX) as u64;
+    let server = net::Server::bind(&format!("0.0.0.0:{}", port), seed, boards)?;
+    let port = server.local_addr().port();
+    server.spawn();
+
+    let client = net::Client::connect(&format!("127.0.0.1:{}", port), name)?;
+    Ok((Race::new(client), port))
+}
+
+pub fn join(addr: &str, name: &str) -> Result<Race, String> {
+    Ok(Race::new(net::Client::connect(addr, name)?))
+}
+
+// what to call this player if they didn't say
+pub fn default_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| String::from("player"))
+}
+
+// Races from the lobby through every board, then shows how everyone did. `hosting` is the port
+// the race is hosted on if this is the host. Returns the final standings so they can be printed
+// once the terminal is handed back.
+pub fn run(
+    race: Race,
+    hosting: Option<u16>,
+    options: &cli::Options,
+    accessibility: &config::Accessibility,
+    window: &pancurses::Window,
+    signals: &term::Signals,
+) -> Result<Vec<String>, String> {
+    let mut run = RunState {
+        race: Some(race),
+        ..RunState::default()
+    };
+
+    loop {
+        let race = run.race.as_mut().unwrap();
+        let board = match run_lobby(race, hosting, window, signals) {
+            Some(board) => board,
+            None => break,
+        };
+        race.start_board(board);
+
+        // Everyone's board has to come out the same no matter how big their terminal is, so
+        // they're sized like speedrun boards are.
+        let seed = race.client.seed.wrapping_add((board as u64) << 32);
+        let level = crate::new_level(
+            board,
+            options,
+            accessibility,
+            crate::speedrun::max_grid_size(),
+            None,
+            &mut SeededRng::new(seed),
+        );
+        let mut boards = [PlayerBoard::new(level, versus::Controls::Mouse)];
+        crate::play_boards(
+            &mut boards,
+            Pilot::Player,
+            &mut run,
+            options,
+            accessibility,
+            window,
+            signals,
+        );
+
+        if signals.quit_requested() {
+            break;
+        }
+    }
+
+    let race = run.race.as_ref().unwrap();
+    if let Some(error) = &race.error {
+        return Err(error.clone());
+    }
+
+    let summary = race.summary();
+    if race.finished && !signals.quit_requested() {
+        hot_seat::run_results_screen(&summary, window, signals);
+    }
+    Ok(summary)
+}
+
+// Waits for the server to say which board is next, showing who's in the race in the meantime. The
+// host starts the race from here. Returns None once the race is over or someone quits.
+fn run_lobby(
+    race: &mut Race,
+    hosting: Option<u16>,
+    window: &pancurses::Window,
+    signals: &term::Signals,
+) -> Option<usize> {
+    let mut screen_rect = layout::screen_rect(window);
+    while !signals.quit_requested() {
+        signals.handle_suspend(window);
+
+        race.receive();
+        if race.error.is_some() || race.finished {
+            return None;
+        }
+        if let Some(board) = race.next_board {
+            return Some(board);
+        }
+
+        match crate::get_input_update(window) {
+            Some(InputUpdate::Key('\n')) | Some(InputUpdate::Key(' ')) if race.client.host => {
+                race.send(&ClientMessage::Start)
+            }
+            Some(InputUpdate::Key('q')) => return None,
+            Some(InputUpdate::Resize) => screen_rect = layout::screen_rect(window),
+            _ => (),
+        }
+
+        window.erase();
+        render_lobby(race, hosting, &screen_rect, window);
+        term::present(window);
+
+        std::thread::sleep(Duration::from_millis(33));
+    }
+
+    None
+}
+
+// Draws who's in the race in the middle of the screen, and what happens next
+fn render_lobby<S: Surface>(race: &Race, hosting: Option<u16>, screen_rect: &Rect, surface: &S) {
+    let mut lines = vec![];
+    match hosting {
+        Some(port) => lines.push(format!("Hosting a race on port {}", port)),
+        None => lines.push(String::from("Joined the race")),
+    }
+    lines.push(String::new());
+    lines.extend(race.players.iter().map(|player| player.name.clone()));
+    lines.push(String::new());
+    lines.push(if race.client.host {
+        String::from("press enter to start, q to quit")
+    } else {
+        String::from("waiting for the host to start...")
+    });
+
+    let top = screen_rect.center_y() - lines.len() as i32 / 2;
+    for (i, line) in lines.iter().enumerate() {
+        surface.draw_text(
+            top + i as i32,
+            std::cmp::max(0, screen_rect.center_x() - (line.len() / 2) as i32),
+            line,
+        );
+    }
+}
+
+// Draws which board of the race this is under the board timer, plus how everyone's doing along
+// the right side of the screen if there's room for it next to the board.
+pub fn render<S: Surface>(
+    race: &Race,
+    level: &Level,
+    time_rect: &Rect,
+    grid_rect: &Rect,
+    screen_rect: &Rect,
+    surface: &S,
+) {
+    let mut board_text = format!("Board {} of {}", race.board, race.client.boards);
+    if level.result().is_some() && !race.board_decided() {
+        board_text += ", waiting on everyone else";
+    }
+    surface.draw_text(time_rect.top + 1, time_rect.left, board_text);
+
+    let sidebar_left = screen_rect.right() - SIDEBAR_WIDTH;
+    if sidebar_left < grid_rect.right() + 2 {
+        return;
+    }
+
+    // leave the top rows for the minimap
+    let mut top = screen_rect.top + 5;
+    for player in &race.players {
+        let attributes = if player.id == race.client.player {
+            Style::Highlight.attributes()
+        } else if !player.connected {
+            pancurses::A_DIM
+        } else {
+            pancurses::A_NORMAL
+        };
+        let status = if player.connected {
+            format!("{} won, {} reveals", player.boards_won, player.reveals)
+        } else {
+            String::from("left")
+        };
+
+        surface.attributes_on(attributes);
+        surface.draw_text(top, sidebar_left, format!("{:.12}", player.name));
+        surface.attributes_off(attributes);
+        surface.draw_text(top, sidebar_left + 13, status);
+        top += 1;
+    }
+
+    top += 1;
+    for (index, winner) in race.winners.iter().enumerate() {
+        if top >= screen_rect.bottom() {
+            break;
+        }
+
+        let winner = winner.map_or("nobody", |winner| race.name(winner));
+        surface.draw_text(
+            top,
+            sidebar_left,
+            format!("{:>2} {:.12}", index + 1, winner),
+        );
+        top += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::test_level;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    // keeps taking in messages until `done` says the race has caught up
+    fn wait_for(race: &mut Race, done: impl Fn(&Race) -> bool) {
+        let start = std::time::Instant::now();
+        while !done(race) {
+            assert!(start.elapsed() < TIMEOUT, "timed out waiting on the server");
+            race.receive();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_lobby() {
+        let server = net::Server::bind("127.0.0.1:0", 7, 1).unwrap();
+        let addr = server.local_addr().to_string();
+        server.spawn();
+
+        let mut ada = Race::new(net::Client::connect(&addr, "ada").unwrap());
+        let mut bob = Race::new(net::Client::connect(&addr, "bob").unwrap());
+        for race in [&mut ada, &mut bob] {
+            wait_for(race, |race| race.players.len() == 2);
+        }
+
+        let screen_rect = Rect {
+            left: 0,
+            top: 0,
+            width: 36,
+            height: 8,
+        };
+        let buffer = crate::render::Buffer::new(screen_rect.width, screen_rect.height);
+        render_lobby(&ada, Some(7777), &screen_rect, &buffer);
+        assert_eq!(
+            vec![
+                "",
+                "     Hosting a race on port 7777",
+                "",
+                "                 ada",
+                "                 bob",
+                "",
+                "   press enter to start, q to quit",
+            ],
+            buffer.text().lines().collect::<Vec<_>>()
+        );
+
+        // everyone else waits on the host
+        let buffer = crate::render::Buffer::new(screen_rect.width, screen_rect.height);
+        render_lobby(&bob, None, &screen_rect, &buffer);
+        assert!(buffer.text().starts_with("\n           Joined the race"));
+        assert!(buffer
+            .text()
+            .ends_with("\n  waiting for the host to start..."));
+    }
+
+    #[test]
+    fn test_racing_another_player() {
+        let server = net::Server::bind("127.0.0.1:0", 7, 1).unwrap();
+        let addr = server.local_addr().to_string();
+        server.spawn();
+
+        let mut ada = Race::new(net::Client::connect(&addr, "ada").unwrap());
+        let mut bob = Race::new(net::Client::connect(&addr, "bob").unwrap());
+        ada.send(&ClientMessage::Start);
+        for race in [&mut ada, &mut bob] {
+            wait_for(race, |race| race.next_board.is_some());
+            race.start_board(1);
+        }
+
+        // bob finds it, which ends ada's board
+        let mut bob_level = test_level(1, (6, 4));
+        let (x, y) = bob_level.grid().find_solution().unwrap();
+        bob_level.reveal(x, y);
+        bob.update(&mut bob_level);
+
+        let mut ada_level = test_level(1, (6, 4));
+        wait_for(&mut ada, |race| race.board_decided());
+        ada.update(&mut ada_level);
+        assert_eq!(Some(GameResult::Lose), ada_level.result());
+        assert_eq!(vec![Some(bob.client.player)], ada.winners);
+
+        wait_for(&mut ada, |race| race.finished);
+        assert!(!ada.has_more_boards());
+        assert_eq!(
+            vec![
+                "bob: 1 board won",
+                "ada: 0 boards won",
+                "bob wins the race!"
+            ],
+            ada.summary()
+        );
+    }
+}
@@ -0,0 +1,408 @@
+// Recording sessions to asciicast files, started with `--record <FILE>`. The file can be played
+// back with `asciinema play` or embedded with asciinema's web player.
+//
+// Every frame gets read back out of the curses window into a Framebuffer once it's been drawn. The
+// first frame is written out whole and after that only the rows that changed, as the escape codes
+// that would draw them on an xterm. See https://docs.asciinema.org/manual/asciicast/v2/ for the
+// format.
+
+use crate::theme::{Color, Style};
+use serde_json::json;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Instant;
+
+// Where curses keeps the character in a chtype. pancurses' A_CHARTEXT is right for pdcurses but
+// not for ncurses, which only keeps the character in the low byte.
+#[cfg(unix)]
+const CHAR_MASK: pancurses::chtype = 0xff;
+#[cfg(windows)]
+const CHAR_MASK: pancurses::chtype = pancurses::A_CHARTEXT;
+// the color pair sits in A_COLOR's bits, wherever those are
+const PAIR_SHIFT: u32 = pancurses::A_COLOR.trailing_zeros();
+
+// the attributes worth recording
+const ATTRIBUTES: [(pancurses::chtype, u8); 5] = [
+    (pancurses::A_BOLD, 1),
+    (pancurses::A_DIM, 2),
+    (pancurses::A_UNDERLINE, 4),
+    (pancurses::A_BLINK, 5),
+    (pancurses::A_REVERSE, 7),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    // None for the terminal's own colors
+    pub style: Option<Style>,
+    // the attributes from ATTRIBUTES the cell is drawn with
+    pub attributes: pancurses::chtype,
+}
+
+impl Cell {
+    const BLANK: Cell = Cell {
+        ch: ' ',
+        style: None,
+        attributes: pancurses::A_NORMAL,
+    };
+
+    // Picks apart a chtype the way curses stores it
+    pub fn from_chtype(c: pancurses::chtype) -> Self {
+        let code = (c & CHAR_MASK) as u32;
+        let ch = if c & pancurses::A_ALTCHARSET != 0 {
+            line_drawing_char(code as u8)
+        } else {
+            std::char::from_u32(code).unwrap_or(' ')
+        };
+
+        let pair_num = ((c & pancurses::A_COLOR) >> PAIR_SHIFT) as i16;
+        let attributes = ATTRIBUTES
+            .iter()
+            .map(|(attribute, _)| c & attribute)
+            .fold(pancurses::A_NORMAL, |all, attribute| all | attribute);
+
+        Cell {
+            ch,
+            style: Style::from_pair_num(pair_num),
+            attributes,
+        }
+    }
+
+    // the escape code that sets up the terminal to draw the cell
+    fn sgr(&self) -> String {
+        let mut codes = vec![String::from("0")];
+        codes.extend(
+            ATTRIBUTES
+                .iter()
+                .filter(|(attribute, _)| self.attributes & attribute != 0)
+                .map(|(_, code)| code.to_string()),
+        );
+
+        if let Some(style) = self.style {
+            let (fg, bg) = style.colors();
+            codes.push(color_code(fg, 30, 38));
+            codes.push(color_code(bg, 40, 48));
+        }
+        format!("\x1b[{}m", codes.join(";"))
+    }
+}
+
+fn color_code(color: Color, standard_base: i16, rgb_code: u8) -> String {
+    match color {
+        Color::Standard(number) => (standard_base + number).to_string(),
+        Color::Rgb((r, g, b)) => {
+            // curses channels go to 1000 where the terminal's go to 255
+            let channel = |value: i16| i32::from(value) * 255 / 1000;
+            format!(
+                "{};2;{};{};{}",
+                rgb_code,
+                channel(r),
+                channel(g),
+                channel(b)
+            )
+        }
+    }
+}
+
+// Curses draws lines and symbols with the VT100 line drawing set, where they sit on top of plain
+// ascii characters.
+fn line_drawing_char(byte: u8) -> char {
+    match byte {
+        b'`' => '◆',
+        b'a' => '▒',
+        b'f' => '°',
+        b'g' => '±',
+        b'j' => '┘',
+        b'k' => '┐',
+        b'l' => '┌',
+        b'm' => '└',
+        b'n' => '┼',
+        b'q' => '─',
+        b't' => '├',
+        b'u' => '┤',
+        b'v' => '┴',
+        b'w' => '┬',
+        b'x' => '│',
+        b'~' => '·',
+        _ => byte as char,
+    }
+}
+
+// what's on screen, cell by cell
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Framebuffer {
+    width: i32,
+    height: i32,
+    cells: Vec<Cell>,
+}
+
+impl Framebuffer {
+    pub fn new(width: i32, height: i32) -> Self {
+        Framebuffer {
+            width,
+            height,
+            cells: vec![Cell::BLANK; (width * height) as usize],
+        }
+    }
+
+    // everything drawn on the window so far
+    pub fn capture(window: &pancurses::Window) -> Self {
+        let (height, width) = window.get_max_yx();
+        let mut framebuffer = Framebuffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                framebuffer.set(x, y, Cell::from_chtype(window.mvinch(y, x)));
+            }
+        }
+        framebuffer
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, cell: Cell) {
+        if x >= 0 && x < self.width && y >= 0 && y < self.height {
+            self.cells[(y * self.width + x) as usize] = cell;
+        }
+    }
+
+    #[cfg(test)]
+    pub fn get(&self, x: i32, y: i32) -> Option<Cell> {
+        if x >= 0 && x < self.width && y >= 0 && y < self.height {
+            Some(self.cells[(y * self.width + x) as usize])
+        } else {
+            None
+        }
+    }
+
+    #[cfg(test)]
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        (0..self.height).map(move |y| self.row(y))
+    }
+
+    fn row(&self, y: i32) -> &[Cell] {
+        let start = (y * self.width) as usize;
+        &self.cells[start..start + self.width as usize]
+    }
+
+    // The escape codes that draw row `y` on a terminal, starting from its left edge
+    fn draw_row(&self, y: i32) -> String {
+        let mut drawn = format!("\x1b[{};1H", y + 1);
+        let mut sgr = None;
+        for cell in self.row(y) {
+            let cell_sgr = cell.sgr();
+            if sgr.as_ref() != Some(&cell_sgr) {
+                drawn += &cell_sgr;
+                sgr = Some(cell_sgr);
+            }
+            drawn.push(cell.ch);
+        }
+        drawn + "\x1b[0m"
+    }
+}
+
+pub struct Recorder {
+    out: Box<dyn Write + Send>,
+    start: Instant,
+    // the last frame that was written out
+    last_frame: Option<Framebuffer>,
+}
+
+impl Recorder {
+    pub fn create(path: &std::path::Path) -> Result<Self, String> {
+        let file = std::fs::File::create(path)
+            .map_err(|e| format!("failed to create {}: {}", path.display(), e))?;
+        Ok(Recorder::new(Box::new(std::io::BufWriter::new(file))))
+    }
+
+    fn new(out: Box<dyn Write + Send>) -> Self {
+        Recorder {
+            out,
+            start: Instant::now(),
+            last_frame: None,
+        }
+    }
+
+    // The header comes first and says how big the terminal is at the start
+    fn write_header(&mut self, width: i32, height: i32) -> std::io::Result<()> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+        let header = json!({
+            "version": 2,
+            "width": width,
+            "height": height,
+            "timestamp": timestamp,
+            "title": crate::TITLE,
+            "env": { "TERM": "xterm-256color" },
+        });
+        writeln!(self.out, "{}", header)?;
+        self.start = Instant::now();
+        self.out.flush()
+    }
+
+    // Writes out whatever changed since the last frame, if anything did
+    fn write_frame(&mut self, frame: Framebuffer) -> std::io::Result<()> {
+        let time = self.start.elapsed().as_secs_f64();
+
+        // the whole screen gets redrawn on the first frame and whenever it's resized
+        let changed_rows: Vec<i32> = match &self.last_frame {
+            Some(last_frame)
+                if (last_frame.width, last_frame.height) == (frame.width, frame.height) =>
+            {
+                (0..frame.height)
+                    .filter(|y| last_frame.row(*y) != frame.row(*y))
+                    .collect()
+            }
+            Some(_) => {
+                let size = format!("{}x{}", frame.width, frame.height);
+                writeln!(self.out, "{}", json!([time, "r", size]))?;
+                (0..frame.height).collect()
+            }
+            None => (0..frame.height).collect(),
+        };
+        if changed_rows.is_empty() {
+            return Ok(());
+        }
+
+        let mut output = String::new();
+        if self.last_frame.is_none() {
+            // clear the screen and hide the cursor
+            output += "\x1b[2J\x1b[?25l";
+        }
+        for y in changed_rows {
+            output += &frame.draw_row(y);
+        }
+
+        writeln!(self.out, "{}", json!([time, "o", output]))?;
+        self.last_frame = Some(frame);
+        self.out.flush()
+    }
+}
+
+// the session being recorded, if there is one
+static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+
+// Records everything drawn on `window` from here on. Call once the terminal's set up.
+pub fn start(mut recorder: Recorder, window: &pancurses::Window) -> Result<(), String> {
+    let (height, width) = window.get_max_yx();
+    recorder
+        .write_header(width, height)
+        .map_err(|e| format!("failed to write the recording: {}", e))?;
+    *RECORDER.lock().unwrap() = Some(recorder);
+    Ok(())
+}
+
+// Adds the frame just drawn on `window` to the recording, if there is one. A recording that can't
+// be written to anymore is given up on rather than getting in the way of the game.
+pub fn frame(window: &pancurses::Window) {
+    let mut recorder = RECORDER.lock().unwrap();
+    if let Some(active) = recorder.as_mut() {
+        if active.write_frame(Framebuffer::capture(window)).is_err() {
+            *recorder = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    // a Write that can still be read from after it's been handed to a Recorder
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        fn lines(&self) -> Vec<serde_json::Value> {
+            let output = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+            output
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+    }
+
+    fn text_cell(ch: char) -> Cell {
+        Cell { ch, ..Cell::BLANK }
+    }
+
+    #[test]
+    fn test_cells_from_chtypes() {
+        let cell = Cell::from_chtype(
+            'v' as pancurses::chtype
+                | pancurses::A_BOLD
+                | pancurses::A_UNDERLINE
+                | pancurses::COLOR_PAIR(3),
+        );
+        assert_eq!('v', cell.ch);
+        assert_eq!(Some(Style::HintUp), cell.style);
+        assert_eq!(pancurses::A_BOLD | pancurses::A_UNDERLINE, cell.attributes);
+
+        let cell = Cell::from_chtype('x' as pancurses::chtype | pancurses::A_ALTCHARSET);
+        assert_eq!(
+            Cell {
+                ch: '│',
+                ..Cell::BLANK
+            },
+            cell
+        );
+        assert_eq!(
+            "\x1b[0;1;7m",
+            Cell {
+                attributes: pancurses::A_BOLD | pancurses::A_REVERSE,
+                ..Cell::BLANK
+            }
+            .sgr()
+        );
+    }
+
+    #[test]
+    fn test_only_changed_rows_are_written() {
+        let output = Output::default();
+        let mut recorder = Recorder::new(Box::new(output.clone()));
+        recorder.write_header(3, 2).unwrap();
+
+        let mut frame = Framebuffer::new(3, 2);
+        frame.set(0, 0, text_cell('a'));
+        recorder.write_frame(frame.clone()).unwrap();
+        // nothing changed, so nothing gets written
+        recorder.write_frame(frame.clone()).unwrap();
+        frame.set(2, 1, text_cell('b'));
+        recorder.write_frame(frame.clone()).unwrap();
+
+        let lines = output.lines();
+        assert_eq!(3, lines.len());
+        assert_eq!(2, lines[0]["version"]);
+        assert_eq!(3, lines[0]["width"]);
+        assert_eq!(2, lines[0]["height"]);
+
+        assert_eq!("o", lines[1][1]);
+        assert_eq!(
+            "\x1b[2J\x1b[?25l\x1b[1;1H\x1b[0ma  \x1b[0m\x1b[2;1H\x1b[0m   \x1b[0m",
+            lines[1][2]
+        );
+        assert_eq!("\x1b[2;1H\x1b[0m  b\x1b[0m", lines[2][2]);
+    }
+
+    #[test]
+    fn test_resizes_are_recorded() {
+        let output = Output::default();
+        let mut recorder = Recorder::new(Box::new(output.clone()));
+        recorder.write_frame(Framebuffer::new(3, 2)).unwrap();
+        recorder.write_frame(Framebuffer::new(4, 1)).unwrap();
+
+        let lines = output.lines();
+        assert_eq!("r", lines[1][1]);
+        assert_eq!("4x1", lines[1][2]);
+        assert_eq!("\x1b[1;1H\x1b[0m    \x1b[0m", lines[2][2]);
+    }
+}
@@ -0,0 +1,422 @@
+// Everything on screen gets drawn on a Surface. The game draws on the curses window, but drawing
+// code only sees the Surface so it can be pointed at an in-memory Buffer and checked in tests.
+//
+// Attributes are curses attributes, which is also how colors get picked: Style::attributes() gives
+// a style's color pair along with any attributes the theme adds to it.
+
+#[cfg(test)]
+use crate::record::{Cell, Framebuffer};
+
+// Something to draw on a cell that isn't just a character. Curses draws these out of the line
+// drawing set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Glyph {
+    Char(char),
+    Diamond,
+    VerticalLine,
+    Bullet,
+}
+
+impl From<char> for Glyph {
+    fn from(c: char) -> Self {
+        Glyph::Char(c)
+    }
+}
+
+impl Glyph {
+    // only good once curses has been set up, since that's when the line drawing set gets mapped
+    fn chtype(self) -> pancurses::chtype {
+        match self {
+            Glyph::Char(c) => c as pancurses::chtype,
+            Glyph::Diamond => pancurses::ACS_DIAMOND(),
+            Glyph::VerticalLine => pancurses::ACS_VLINE(),
+            Glyph::Bullet => pancurses::ACS_BULLET(),
+        }
+    }
+
+    #[cfg(test)]
+    fn to_char(self) -> char {
+        match self {
+            Glyph::Char(c) => c,
+            Glyph::Diamond => '◆',
+            Glyph::VerticalLine => '│',
+            Glyph::Bullet => '·',
+        }
+    }
+}
+
+// Coordinates are rows and columns like they are in curses, with `y` first.
+pub trait Surface {
+    // draws everything after this with `attributes` on top of any that are already on
+    fn attributes_on(&self, attributes: pancurses::chtype);
+
+    fn attributes_off(&self, attributes: pancurses::chtype);
+
+    fn draw_text<T: AsRef<str>>(&self, y: i32, x: i32, text: T);
+
+    // draws `text` right after whatever was drawn last
+    fn append_text<T: AsRef<str>>(&self, text: T);
+
+    fn draw_glyph(&self, y: i32, x: i32, glyph: Glyph);
+
+    // adds `attributes` to the cell at (x, y) without redrawing it, i.e. to highlight it
+    fn add_attributes(&self, y: i32, x: i32, attributes: pancurses::chtype);
+}
+
+impl Surface for pancurses::Window {
+    fn attributes_on(&self, attributes: pancurses::chtype) {
+        self.attron(attributes);
+    }
+
+    fn attributes_off(&self, attributes: pancurses::chtype) {
+        self.attroff(attributes);
+    }
+
+    fn draw_text<T: AsRef<str>>(&self, y: i32, x: i32, text: T) {
+        self.mvaddstr(y, x, text);
+    }
+
+    fn append_text<T: AsRef<str>>(&self, text: T) {
+        self.addstr(text);
+    }
+
+    fn draw_glyph(&self, y: i32, x: i32, glyph: Glyph) {
+        self.mvaddch(y, x, glyph.chtype());
+    }
+
+    fn add_attributes(&self, y: i32, x: i32, attributes: pancurses::chtype) {
+        self.mvchgat(y, x, 1, self.mvinch(y, x) | attributes, 0);
+    }
+}
+
+// A surface that just remembers what was drawn where. Anything drawn off the edge is dropped.
+#[cfg(test)]
+pub struct Buffer {
+    framebuffer: std::cell::RefCell<Framebuffer>,
+    // where the next appended text goes, as (x, y)
+    cursor: std::cell::Cell<(i32, i32)>,
+    attributes: std::cell::Cell<pancurses::chtype>,
+}
+
+#[cfg(test)]
+impl Buffer {
+    pub fn new(width: i32, height: i32) -> Self {
+        Buffer {
+            framebuffer: std::cell::RefCell::new(Framebuffer::new(width, height)),
+            cursor: std::cell::Cell::new((0, 0)),
+            attributes: std::cell::Cell::new(pancurses::A_NORMAL),
+        }
+    }
+
+    pub fn cell(&self, x: i32, y: i32) -> Option<Cell> {
+        self.framebuffer.borrow().get(x, y)
+    }
+
+    // Every row as plain text with the trailing blanks trimmed off, which is what snapshots get
+    // compared against
+    pub fn text(&self) -> String {
+        let framebuffer = self.framebuffer.borrow();
+        let rows: Vec<String> = framebuffer
+            .rows()
+            .map(|row| {
+                let text: String = row.iter().map(|cell| cell.ch).collect();
+                text.trim_end().to_string()
+            })
+            .collect();
+        rows.join("\n").trim_end().to_string()
+    }
+
+    fn put(&self, x: i32, y: i32, ch: char) {
+        let cell = Cell {
+            ch,
+            ..Cell::from_chtype(self.attributes.get())
+        };
+        self.framebuffer.borrow_mut().set(x, y, cell);
+        self.cursor.set((x + 1, y));
+    }
+}
+
+#[cfg(test)]
+impl Surface for Buffer {
+    fn attributes_on(&self, attributes: pancurses::chtype) {
+        self.attributes.set(self.attributes.get() | attributes);
+    }
+
+    fn attributes_off(&self, attributes: pancurses::chtype) {
+        self.attributes.set(self.attributes.get() & !attributes);
+    }
+
+    // like curses, nothing gets drawn if the text starts off the edge
+    fn draw_text<T: AsRef<str>>(&self, y: i32, x: i32, text: T) {
+        if self.cell(x, y).is_some() {
+            self.cursor.set((x, y));
+            self.append_text(text);
+        }
+    }
+
+    fn append_text<T: AsRef<str>>(&self, text: T) {
+        for ch in text.as_ref().chars() {
+            let (x, y) = self.cursor.get();
+            self.put(x, y, ch);
+        }
+    }
+
+    fn draw_glyph(&self, y: i32, x: i32, glyph: Glyph) {
+        self.put(x, y, glyph.to_char());
+    }
+
+    fn add_attributes(&self, y: i32, x: i32, attributes: pancurses::chtype) {
+        if let Some(cell) = self.cell(x, y) {
+            let added = Cell::from_chtype(attributes);
+            let cell = Cell {
+                style: added.style.or(cell.style),
+                attributes: cell.attributes | added.attributes,
+                ..cell
+            };
+            self.framebuffer.borrow_mut().set(x, y, cell);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameGrid, DEFAULT_REVEAL_TIME};
+    use crate::theme::Style;
+    use crate::utils::{clock, Rect, Timer};
+    use crate::{board_file, config, layout, xform, GameOverState, GameResult, MouseState};
+    use std::time::Duration;
+
+    const BOARD: &str = "
+        |>>v<<|
+        |>~*< |
+        |>>^<~|
+    ";
+
+    fn board_grid(revealed: &[(i32, i32)]) -> GameGrid {
+        let mut grid = board_file::parse(BOARD)
+            .unwrap()
+            .to_grid(DEFAULT_REVEAL_TIME);
+        for (x, y) in revealed {
+            grid.try_reveal(*x, *y);
+        }
+        grid
+    }
+
+    // Draws a whole frame of the board the way the game does, minus the minimap
+    fn render_frame(
+        grid: &GameGrid,
+        game_over_state: &Option<GameOverState>,
+        confusion_state: Option<bool>,
+        accessibility: &config::Accessibility,
+    ) -> Buffer {
+        let screen = Rect {
+            left: 0,
+            top: 0,
+            width: 28,
+            height: 18,
+        };
+        let metrics = xform::CellMetrics::NORMAL;
+        let camera = xform::Camera::new(grid.width(), grid.height());
+        let board_layout =
+            layout::board_layout(grid.width(), grid.height(), &metrics, &screen).unwrap();
+        let buffer = Buffer::new(screen.width, screen.height);
+
+        crate::render_level_header(1, &board_layout.level, &buffer);
+        let readout = crate::TimerReadout {
+            timed: true,
+            time: Duration::from_millis(12_345),
+            reveals: 0,
+            score: 0,
+            treasure_total: 0,
+        };
+        crate::render_game_timer(&readout, &board_layout.time, &buffer);
+
+        let board_view = crate::BoardView {
+            grid_rect: &board_layout.grid,
+            camera: &camera,
+            metrics: &metrics,
+            accessibility,
+        };
+        let mouse_state = MouseState {
+            click: false,
+            x: 0,
+            y: 0,
+        };
+        crate::render_game_board(
+            grid,
+            game_over_state,
+            confusion_state,
+            &board_view,
+            &buffer,
+            &mouse_state,
+        );
+
+        if let Some(game_over) = game_over_state {
+            crate::render_game_over_text(game_over, false, &buffer, &board_layout.game_over);
+        } else if confusion_state.is_some() && accessibility.reduced_motion {
+            crate::render_confusion_text(&buffer, &board_layout.game_over);
+        }
+        buffer
+    }
+
+    // Snapshots have a '|' at the start of every line so they can be indented along with the test
+    fn assert_snapshot(buffer: &Buffer, snapshot: &str) {
+        let lines: Vec<&str> = snapshot
+            .lines()
+            .filter_map(|line| line.trim_start().strip_prefix('|'))
+            .collect();
+        assert_eq!(lines.join("\n"), buffer.text());
+    }
+
+    #[test]
+    fn test_revealed_board() {
+        let buffer = render_frame(
+            &board_grid(&[(0, 0), (3, 0), (2, 2)]),
+            &None,
+            None,
+            &config::Accessibility::default(),
+        );
+        assert_snapshot(
+            &buffer,
+            "
+            |  Level: 1
+            |  Time: 12.345
+            |
+            |
+            |
+            |
+            |   > >│   │   │< <│
+            |   > >│   │   │< <│
+            |      │   │   │   │
+            |      │   │   │   │
+            |      │   │^ ^│   │
+            |      │   │^ ^│   │
+            ",
+        );
+
+        // the revealed hint, the hidden cell next to it and the border between them
+        let hint = buffer.cell(3, 6).unwrap();
+        assert_eq!(Some(Style::HintRight), hint.style);
+        assert_eq!(Some(Style::Hidden), buffer.cell(8, 6).unwrap().style);
+        assert_eq!(Some(Style::Border), buffer.cell(6, 6).unwrap().style);
+        // the bottom row of every cell is underlined to draw the border below it
+        let hint_bottom = buffer.cell(3, 7).unwrap();
+        assert_eq!(pancurses::A_UNDERLINE, hint_bottom.attributes);
+        assert_eq!(pancurses::A_NORMAL, hint.attributes);
+    }
+
+    #[test]
+    fn test_confused_board() {
+        let grid = board_grid(&[(0, 0), (3, 0), (1, 1)]);
+        let buffer = render_frame(&grid, &None, Some(true), &config::Accessibility::default());
+        assert_snapshot(
+            &buffer,
+            "
+            |  Level: 1
+            |  Time: 12.345
+            |
+            |
+            |
+            |
+            |   < <│   │   │> >│
+            |   < <│   │   │> >│
+            |      │~ ~│   │   │
+            |      │~ ~│   │   │
+            |      │   │   │   │
+            |      │   │   │   │
+            ",
+        );
+
+        let accessibility = config::Accessibility {
+            reduced_motion: true,
+            ..config::Accessibility::default()
+        };
+        let buffer = render_frame(&grid, &None, Some(true), &accessibility);
+        assert_snapshot(
+            &buffer,
+            "
+            |  Level: 1
+            |  Time: 12.345
+            |
+            |
+            |
+            |
+            |   ? ?│   │   │? ?│
+            |   ? ?│   │   │? ?│
+            |      │~ ~│   │   │
+            |      │~ ~│   │   │
+            |      │   │   │   │
+            |      │   │   │   │
+            |
+            |
+            |
+            |Confused! Hints are hidden
+            ",
+        );
+    }
+
+    #[test]
+    fn test_lost_board() {
+        clock::use_stepped_time();
+        let game_over = GameOverState {
+            result: GameResult::Lose,
+            msg_timer: Timer::new(Duration::from_secs(3)),
+        };
+        let buffer = render_frame(
+            &board_grid(&[(0, 0)]),
+            &Some(game_over),
+            None,
+            &config::Accessibility::default(),
+        );
+        assert_snapshot(
+            &buffer,
+            "
+            |  Level: 1
+            |  Time: 12.345
+            |
+            |
+            |
+            |
+            |   > >│> >│v v│< <│< <
+            |   > >│> >│v v│< <│< <
+            |   > >│~ ~│◆ ◆│< <│
+            |   > >│~ ~│◆ ◆│< <│
+            |   > >│> >│^ ^│< <│~ ~
+            |   > >│> >│^ ^│< <│~ ~
+            |
+            |
+            |
+            |   Failed! Exiting in...
+            |          3 secs
+            ",
+        );
+    }
+
+    #[test]
+    fn test_game_over_text() {
+        clock::use_stepped_time();
+        let game_over_rect = Rect {
+            left: 0,
+            top: 0,
+            width: 26,
+            height: 3,
+        };
+        let render_text = |result, continues| {
+            let game_over = GameOverState {
+                result,
+                msg_timer: Timer::new(Duration::from_secs(3)),
+            };
+            let buffer = Buffer::new(game_over_rect.width, game_over_rect.height);
+            crate::render_game_over_text(&game_over, continues, &buffer, &game_over_rect);
+            buffer.text()
+        };
+
+        let text = render_text(GameResult::Win, true);
+        assert_eq!("\n Success! Next board in...\n          3 secs", text);
+        let text = render_text(GameResult::Win, false);
+        assert_eq!("\n  Success! Exiting in...\n          3 secs", text);
+        let text = render_text(GameResult::Lose, true);
+        assert_eq!("\n Failed! Next board in...\n          3 secs", text);
+    }
+}
@@ -0,0 +1,418 @@
+// A headless harness for tuning difficulty: `lnf simulate` plays thousands of boards per level with
+// the solver and no rendering, then prints statistics about each level as CSV or JSON.
+//
+// Every level's boards come from a seeded rng, so the same options always play the same boards.
+// That makes it easy to compare the numbers before and after tweaking the level curve.
+//
+// The solver doesn't get to click instantly. Every click takes `click_time` of game time, which is
+// roughly how long a quick player takes to read the board and move the mouse, so the win rate says
+// something about whether the board time is fair.
+
+use crate::difficulty;
+use crate::game::{CellDistribution, GameGrid, DEFAULT_REVEAL_TIME};
+use crate::level::{Level, LevelEvent};
+use crate::solver::Solver;
+use crate::utils::{clock, Rect, SeededRng};
+use crate::xform::CellMetrics;
+use serde::Serialize;
+use std::io::Write;
+use std::time::Duration;
+
+// how much time passes at once while the solver is waiting out a confusion trap
+const WAIT_STEP: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("invalid format '{}' (expected csv or json)", value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulateOptions {
+    pub levels: usize,
+    pub boards: usize,
+    pub seed: u64,
+    pub format: Format,
+    // the terminal size boards have to fit on, which caps how big they grow
+    pub screen: (i32, i32),
+    pub click_time: Duration,
+    // replaces every level's cell distribution
+    pub cell_distribution: Option<CellDistribution>,
+    // play the first board that's rolled instead of re-rolling into the level's difficulty band
+    pub no_reroll: bool,
+}
+
+impl Default for SimulateOptions {
+    fn default() -> Self {
+        SimulateOptions {
+            levels: 10,
+            boards: 1000,
+            seed: 0,
+            format: Format::Csv,
+            screen: (80, 24),
+            click_time: Duration::from_secs(1),
+            cell_distribution: None,
+            no_reroll: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Summary {
+    pub mean: f64,
+    pub p50: usize,
+    pub p90: usize,
+    pub max: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LevelStats {
+    pub level: usize,
+    pub width: i32,
+    pub height: i32,
+    pub board_time_secs: f64,
+    pub max_revealed_cells: usize,
+    pub cell_distribution: CellDistribution,
+    pub difficulty_band: (f64, f64),
+    pub boards: usize,
+    pub wins: usize,
+    pub win_rate: f64,
+    // only counts the boards that were won
+    pub clicks_to_solve: Summary,
+    pub trap_hits_per_board: f64,
+    pub time_used_secs: f64,
+    // the mean difficulty score (see difficulty.rs) of the boards that were played
+    pub difficulty_score: f64,
+    // how often the solution landed on each cell, one array per row
+    pub solution_positions: Vec<Vec<usize>>,
+}
+
+impl LevelStats {
+    // the fraction of solutions that were on the edge of the board
+    pub fn solution_edge_rate(&self) -> f64 {
+        let mut on_edge = 0;
+        for (y, row) in self.solution_positions.iter().enumerate() {
+            for (x, count) in row.iter().enumerate() {
+                let x_edge = x == 0 || x as i32 == self.width - 1;
+                let y_edge = y == 0 || y as i32 == self.height - 1;
+                if x_edge || y_edge {
+                    on_edge += count;
+                }
+            }
+        }
+
+        on_edge as f64 / self.boards as f64
+    }
+}
+
+struct BoardStats {
+    difficulty_score: f64,
+    won: bool,
+    clicks: usize,
+    trap_hits: usize,
+    time_used: Duration,
+    solution: (i32, i32),
+}
+
+pub fn run(options: &SimulateOptions) {
+    let stats = simulate(options);
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    let written = match options.format {
+        Format::Csv => write_csv(&stats, &mut out),
+        Format::Json => serde_json::to_writer_pretty(&mut out, &stats)
+            .map_err(std::io::Error::from)
+            .and_then(|_| writeln!(out)),
+    };
+
+    if let Err(e) = written {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+pub fn simulate(options: &SimulateOptions) -> Vec<LevelStats> {
+    clock::use_stepped_time();
+
+    let screen = Rect {
+        left: 0,
+        top: 0,
+        width: options.screen.0,
+        height: options.screen.1,
+    };
+    let max_grid_size = crate::layout::max_grid_size(&CellMetrics::NORMAL, &screen);
+
+    (1..=options.levels)
+        .map(|level| simulate_level(level, max_grid_size, options))
+        .collect()
+}
+
+fn simulate_level(
+    level: usize,
+    max_grid_size: (i32, i32),
+    options: &SimulateOptions,
+) -> LevelStats {
+    let mut config = crate::get_level_config(level, max_grid_size);
+    if let Some(cell_distribution) = options.cell_distribution {
+        config.cell_distribution = cell_distribution;
+    }
+
+    let (width, height) = config.grid_size;
+    let mut rng = SeededRng::new(options.seed.wrapping_add((level as u64) << 32));
+
+    let boards: Vec<BoardStats> = (0..options.boards)
+        .map(|_| {
+            let grid = if options.no_reroll {
+                GameGrid::new(
+                    width,
+                    height,
+                    config.max_revealed_cells,
+                    DEFAULT_REVEAL_TIME,
+                    config.cell_distribution,
+                    &mut rng,
+                )
+            } else {
+                crate::generate_grid(&config, DEFAULT_REVEAL_TIME, &mut rng)
+            };
+            simulate_board(&mut Level::new(level, grid, config), options.click_time)
+        })
+        .collect();
+
+    let mut clicks_to_solve: Vec<usize> = boards
+        .iter()
+        .filter(|board| board.won)
+        .map(|board| board.clicks)
+        .collect();
+    clicks_to_solve.sort_unstable();
+
+    let mut solution_positions = vec![vec![0; width as usize]; height as usize];
+    for board in &boards {
+        solution_positions[board.solution.1 as usize][board.solution.0 as usize] += 1;
+    }
+
+    let board_count = std::cmp::max(boards.len(), 1) as f64;
+    let wins = clicks_to_solve.len();
+    LevelStats {
+        level,
+        width,
+        height,
+        board_time_secs: config.board_time.as_secs_f64(),
+        max_revealed_cells: config.max_revealed_cells,
+        cell_distribution: config.cell_distribution,
+        difficulty_band: config.difficulty_band,
+        boards: boards.len(),
+        wins,
+        win_rate: wins as f64 / board_count,
+        clicks_to_solve: summarize(&clicks_to_solve),
+        trap_hits_per_board: boards.iter().map(|board| board.trap_hits).sum::<usize>() as f64
+            / board_count,
+        time_used_secs: boards
+            .iter()
+            .map(|board| board.time_used.as_secs_f64())
+            .sum::<f64>()
+            / board_count,
+        difficulty_score: boards
+            .iter()
+            .map(|board| board.difficulty_score)
+            .sum::<f64>()
+            / board_count,
+        solution_positions,
+    }
+}
+
+fn simulate_board(level: &mut Level, click_time: Duration) -> BoardStats {
+    let solution = level.grid().find_solution().unwrap();
+    let difficulty_score = difficulty::analyze(level.grid()).score();
+    let board_time = level.time_left();
+    let mut solver = Solver::new(level.grid());
+    let mut clicks = 0;
+    let mut trap_hits = 0;
+
+    while level.result().is_none() {
+        solver.observe(level);
+        let next_move = solver.next_move(level);
+
+        // the time it takes to make the click, or to wait for the confusion to pass
+        clock::step(if next_move.is_some() {
+            click_time
+        } else {
+            WAIT_STEP
+        });
+        level.update();
+
+        if let (None, Some((x, y))) = (level.result(), next_move) {
+            clicks += 1;
+            trap_hits += level
+                .reveal(x, y)
+                .iter()
+                .filter(|event| matches!(event, LevelEvent::Trap { .. }))
+                .count();
+        }
+    }
+
+    BoardStats {
+        difficulty_score,
+        won: level.result() == Some(crate::GameResult::Win),
+        clicks,
+        trap_hits,
+        time_used: board_time - level.time_left(),
+        solution,
+    }
+}
+
+// expects `values` to already be sorted
+fn summarize(values: &[usize]) -> Summary {
+    if values.is_empty() {
+        return Summary {
+            mean: 0.0,
+            p50: 0,
+            p90: 0,
+            max: 0,
+        };
+    }
+
+    let percentile = |p: usize| values[(values.len() - 1) * p / 100];
+    Summary {
+        mean: values.iter().sum::<usize>() as f64 / values.len() as f64,
+        p50: percentile(50),
+        p90: percentile(90),
+        max: values[values.len() - 1],
+    }
+}
+
+// CSV only has room for a summary of where the solutions landed. The full grid of solution
+// positions is in the JSON output.
+fn write_csv<W: Write>(stats: &[LevelStats], out: &mut W) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "level,width,height,board_time_secs,max_revealed_cells,traps,empties,hints,boards,wins,\
+         win_rate,clicks_mean,clicks_p50,clicks_p90,clicks_max,trap_hits_per_board,\
+         time_used_secs,difficulty_score,solution_edge_rate"
+    )?;
+
+    for level in stats {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{},{},{:.4},{:.2},{},{},{},{:.4},{:.3},{:.3},{:.4}",
+            level.level,
+            level.width,
+            level.height,
+            level.board_time_secs,
+            level.max_revealed_cells,
+            level.cell_distribution.traps,
+            level.cell_distribution.empties,
+            level.cell_distribution.hints,
+            level.boards,
+            level.wins,
+            level.win_rate,
+            level.clicks_to_solve.mean,
+            level.clicks_to_solve.p50,
+            level.clicks_to_solve.p90,
+            level.clicks_to_solve.max,
+            level.trap_hits_per_board,
+            level.time_used_secs,
+            level.difficulty_score,
+            level.solution_edge_rate(),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_run() -> SimulateOptions {
+        SimulateOptions {
+            levels: 3,
+            boards: 20,
+            seed: 7,
+            ..SimulateOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_simulation_is_repeatable() {
+        let stats = simulate(&small_run());
+        assert_eq!(3, stats.len());
+        assert_eq!(stats, simulate(&small_run()));
+
+        let other_seed = SimulateOptions {
+            seed: 8,
+            ..small_run()
+        };
+        assert_ne!(stats, simulate(&other_seed));
+    }
+
+    #[test]
+    fn test_level_stats() {
+        let stats = simulate(&small_run());
+        for level in &stats {
+            assert_eq!(20, level.boards);
+            assert!(level.wins <= level.boards);
+
+            let solutions: usize = level.solution_positions.iter().flatten().sum();
+            assert_eq!(level.boards, solutions);
+            assert_eq!(level.height as usize, level.solution_positions.len());
+        }
+
+        // with all the time in the world, the solver never loses
+        let untimed = SimulateOptions {
+            click_time: Duration::from_millis(1),
+            ..small_run()
+        };
+        assert!(simulate(&untimed).iter().all(|level| level.win_rate == 1.0));
+    }
+
+    #[test]
+    fn test_rerolled_boards_land_in_band() {
+        for level in simulate(&small_run()) {
+            let (low, high) = level.difficulty_band;
+            assert!(level.difficulty_score >= low && level.difficulty_score <= high);
+        }
+    }
+
+    #[test]
+    fn test_csv_has_a_row_per_level() {
+        let mut csv = vec![];
+        write_csv(&simulate(&small_run()), &mut csv).unwrap();
+
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(4, lines.len());
+        assert!(lines[0].starts_with("level,width,height"));
+        assert!(lines[1].starts_with("1,"));
+
+        let columns = lines[0].split(',').count();
+        assert!(lines.iter().all(|line| line.split(',').count() == columns));
+    }
+
+    #[test]
+    fn test_summarize() {
+        assert_eq!(
+            Summary {
+                mean: 5.5,
+                p50: 5,
+                p90: 9,
+                max: 10
+            },
+            summarize(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10])
+        );
+        assert_eq!(0, summarize(&[]).max);
+    }
+}
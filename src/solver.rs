@@ -0,0 +1,233 @@
+// A solver that plays boards using only what a player could know: the items on revealed cells and
+// whatever it remembers about cells that have since been hidden again. Since it never forgets a
+// cell, going over the max_revealed_cells budget and having old cells hidden again costs it
+// nothing, so it never has to hold back on revealing.
+//
+// It keeps track of every cell that could still be the solution given the hints it's seen so far
+// (the candidate region) and reveals whichever candidate would narrow that region down the most.
+
+use crate::game::{hint_direction, GameGrid, GridItem, HintDir};
+use crate::level::Level;
+
+// Scoring a probe means looking at every candidate, so on huge boards only a sample of the
+// candidates get considered as probes.
+const MAX_PROBES: usize = 400;
+
+pub struct Solver {
+    width: i32,
+    height: i32,
+    // what the solver has seen in each cell, including cells that were hidden again since
+    known: Vec<Option<GridItem>>,
+    candidates: Vec<bool>,
+}
+
+impl Solver {
+    pub fn new(grid: &GameGrid) -> Self {
+        let num_cells = (grid.width() * grid.height()) as usize;
+        Solver {
+            width: grid.width(),
+            height: grid.height(),
+            known: vec![None; num_cells],
+            candidates: vec![true; num_cells],
+        }
+    }
+
+    // Looks over the board and narrows down the candidate region with anything new. Hints can't
+    // be trusted while confused since they might be showing flipped, so those get read once the
+    // confusion wears off, if they're still revealed by then.
+    pub fn observe(&mut self, level: &Level) {
+        let confused = level.confusion_state().is_some();
+        let grid = level.grid();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let grid_cell = grid.cell(x, y).unwrap();
+                let index = self.index(x, y);
+                if !grid_cell.revealed || self.known[index].is_some() {
+                    continue;
+                }
+
+                if confused {
+                    if let GridItem::Hint(_) = grid_cell.item {
+                        continue;
+                    }
+                }
+
+                self.learn(x, y, grid_cell.item);
+            }
+        }
+    }
+
+    // Narrows down the candidate region with what's in the cell at (x, y).
+    pub fn learn(&mut self, x: i32, y: i32, item: GridItem) {
+        let index = self.index(x, y);
+        self.known[index] = Some(item);
+
+        match item {
+            GridItem::Solution => {
+                for (i, candidate) in self.candidates.iter_mut().enumerate() {
+                    *candidate = i == index;
+                }
+            }
+            GridItem::Hint(hint_dir) => {
+                self.candidates[index] = false;
+                for (i, candidate) in self.candidates.iter_mut().enumerate() {
+                    let (candidate_x, candidate_y) = (i as i32 % self.width, i as i32 / self.width);
+                    *candidate &=
+                        i != index && hint_direction(x - candidate_x, y - candidate_y) == hint_dir;
+                }
+            }
+            _ => self.candidates[index] = false,
+        }
+
+        // Only possible if something we saw was wrong. Rather than give up, start over with
+        // everything we haven't seen for ourselves.
+        if self.candidate_count() == 0 {
+            for (candidate, known) in self.candidates.iter_mut().zip(self.known.iter()) {
+                *candidate = known.is_none();
+            }
+        }
+    }
+
+    pub fn is_candidate(&self, x: i32, y: i32) -> bool {
+        self.candidates[self.index(x, y)]
+    }
+
+    pub fn candidate_count(&self) -> usize {
+        self.candidates
+            .iter()
+            .filter(|candidate| **candidate)
+            .count()
+    }
+
+    // Picks the next cell to reveal. Returns None while confused since any hint we'd reveal
+    // couldn't be read anyway.
+    pub fn next_move(&self, level: &Level) -> Option<(i32, i32)> {
+        if level.confusion_state().is_some() {
+            return None;
+        }
+
+        // every cell that could be the solution and that we're not already looking at
+        let grid = level.grid();
+        self.best_probe(|x, y| !grid.cell(x, y).unwrap().revealed)
+    }
+
+    // The candidate that would narrow the candidate region down the most, out of the ones that
+    // `can_probe` allows.
+    pub fn best_probe(&self, can_probe: impl Fn(i32, i32) -> bool) -> Option<(i32, i32)> {
+        let probes: Vec<(i32, i32)> = self
+            .candidate_cells()
+            .filter(|(x, y)| can_probe(*x, *y))
+            .collect();
+
+        let stride = std::cmp::max(1, probes.len() / MAX_PROBES);
+        probes
+            .iter()
+            .step_by(stride)
+            .min_by_key(|probe| self.score_probe(**probe))
+            .copied()
+    }
+
+    // How well revealing `probe` would split up the candidates. Each candidate would make the
+    // probe show one of four hints, and the score is the sum of the squared sizes of those groups,
+    // which is proportional to how many candidates we'd expect to have left. Lower is better.
+    fn score_probe(&self, probe: (i32, i32)) -> usize {
+        let mut group_sizes = [0usize; 4];
+        for (x, y) in self.candidate_cells() {
+            if (x, y) == probe {
+                continue;
+            }
+
+            let group = match hint_direction(probe.0 - x, probe.1 - y) {
+                HintDir::Left => 0,
+                HintDir::Right => 1,
+                HintDir::Up => 2,
+                HintDir::Down => 3,
+            };
+            group_sizes[group] += 1;
+        }
+
+        group_sizes.iter().map(|size| size * size).sum()
+    }
+
+    fn candidate_cells(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        let width = self.width;
+        self.candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, candidate)| **candidate)
+            .map(move |(index, _)| (index as i32 % width, index as i32 / width))
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        (self.width * y + x) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::test_level;
+    use crate::utils::clock;
+    use std::time::Duration;
+
+    #[test]
+    fn test_solution_always_stays_a_candidate() {
+        clock::use_stepped_time();
+
+        for _ in 0..20 {
+            let mut level = test_level(1, (15, 10));
+            let solution = level.grid().find_solution().unwrap();
+            let mut solver = Solver::new(level.grid());
+
+            for _ in 0..20 {
+                if level.result().is_some() {
+                    break;
+                }
+
+                // wait out any confusion, like a player would
+                let (x, y) = match solver.next_move(&level) {
+                    Some(next_move) => next_move,
+                    None => {
+                        clock::step(Duration::from_secs(1));
+                        level.update();
+                        solver.observe(&level);
+                        continue;
+                    }
+                };
+
+                level.reveal(x, y);
+                solver.observe(&level);
+                assert!(solver.is_candidate(solution.0, solution.1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_solver_beats_boards() {
+        clock::use_stepped_time();
+
+        let mut moves = 0;
+        for _ in 0..20 {
+            let mut level = test_level(1, (20, 15));
+            let mut solver = Solver::new(level.grid());
+            while level.result().is_none() {
+                match solver.next_move(&level) {
+                    Some((x, y)) => {
+                        level.reveal(x, y);
+                        moves += 1;
+                    }
+                    None => clock::step(Duration::from_millis(500)),
+                }
+                level.update();
+                solver.observe(&level);
+            }
+
+            assert_eq!(Some(crate::GameResult::Win), level.result());
+        }
+
+        // 300 cells takes more than 8 guesses to find when guessing blindly, but narrowing things
+        // down with hints should find it in far fewer
+        assert!(moves / 20 < 20, "took {} moves on average", moves / 20);
+    }
+}
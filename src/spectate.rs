@@ -0,0 +1,680 @@
+// Spectating. Playing with `--spectate-port <PORT>` streams the game to anyone on the same machine
+// who connects, and `lnf watch <HOST>:<PORT>` shows it from another terminal, drawn just like the
+// game draws it but read-only. The arrow keys scroll around boards too big for the watcher's
+// screen and q stops watching.
+//
+// The stream is one line of JSON per message with a "type" field saying what it is. It only goes
+// from the game to its watchers, and anything a watcher sends is ignored.
+//
+//     board    {"type": "board", "level": 3, "width": 12, "height": 8, "cells": [...]}
+//              every cell row by row, like {"item": {"kind": "hint", "value": "left"},
+//              "revealed": true, "seen": true}, with items as they're described in machine.rs.
+//              Hidden cells that have never been seen are null until the board's over, so the
+//              stream doesn't give away where anything is. Sent whenever a cell changes and to
+//              every watcher as soon as they connect
+//     status   {"type": "status", "timer": {...}, "confusion": null, "game_over": null}
+//              sent ten times a second. "timer" is what the timer shows: "timed", the "time" left
+//              (or elapsed, when untimed) as {"secs", "nanos"}, "reveals", "score" and
+//              "treasure_total". "confusion" is null unless hints are confused, and then whether
+//              they're flipped right now. Once the board's over "game_over" has its "result"
+//              ("win" or "lose"), whether another board "continues" after it and the "time_left"
+//              before that
+//     event    {"type": "event", "event": {"event": "revealed", "x": 2, "y": 6, "item": {...}}}
+//              something that happened on the board: revealed, rehidden, trap, confusion_ended,
+//              collected, won or lost
+//
+// Only the first board gets streamed, so only the left player of split-screen versus can be
+// watched.
+
+use crate::game::{GameGrid, GridCell, GridItem};
+use crate::level::{Level, LevelEvent};
+use crate::render::Surface;
+use crate::utils::Timer;
+use crate::{config, layout, term, xform};
+use crate::{GameOverState, GameResult, InputUpdate, MouseState, TimerReadout};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+// how often watchers hear what the timer says
+const STATUS_INTERVAL: Duration = Duration::from_millis(100);
+// how many lines a watcher can fall behind by before they get dropped
+const WATCHER_BACKLOG: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameOver {
+    pub result: GameResult,
+    pub continues: bool,
+    pub time_left: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    Board {
+        level: usize,
+        width: i32,
+        height: i32,
+        cells: Vec<Option<GridCell>>,
+    },
+    Status {
+        timer: TimerReadout,
+        confusion: Option<bool>,
+        game_over: Option<GameOver>,
+    },
+    Event {
+        event: LevelEvent,
+    },
+}
+
+fn to_line(message: &Message) -> String {
+    serde_json::to_string(message).expect("spectator messages always serialize")
+}
+
+// what watchers get to see of a cell
+fn spectated(cell: &GridCell, board_over: bool) -> Option<GridCell> {
+    if cell.revealed || cell.seen || board_over {
+        Some(*cell)
+    } else {
+        None
+    }
+}
+
+// The grid a board message shows, where the cells that weren't sent are hidden. Returns None if
+// the message doesn't add up to a board.
+fn board_grid(width: i32, height: i32, cells: Vec<Option<GridCell>>) -> Option<GameGrid> {
+    let num_cells = width.checked_mul(height)?;
+    if width <= 0 || height <= 0 || num_cells as usize != cells.len() {
+        return None;
+    }
+
+    let hidden = GridCell {
+        item: GridItem::Empty,
+        revealed: false,
+        seen: false,
+    };
+    let cells = cells
+        .into_iter()
+        .map(|cell| cell.unwrap_or(hidden))
+        .collect();
+    Some(GameGrid::from_cells(width, height, cells))
+}
+
+// everyone watching, and the board they get shown as soon as they connect
+#[derive(Default)]
+struct Watchers {
+    senders: Vec<mpsc::SyncSender<String>>,
+    board: Option<String>,
+}
+
+impl Watchers {
+    // anyone who's stopped watching, or can't keep up, gets dropped along the way
+    fn broadcast(&mut self, line: &str) {
+        self.senders
+            .retain(|sender| sender.try_send(String::from(line)).is_ok());
+    }
+}
+
+pub struct Server {
+    watchers: Arc<Mutex<Watchers>>,
+    status_timer: Timer,
+    // the last board watchers were sent
+    board: Option<Message>,
+}
+
+impl Server {
+    // Starts taking watchers on `addr`. They get sent the game from then on.
+    pub fn listen(addr: &str) -> Result<Self, String> {
+        let listener =
+            TcpListener::bind(addr).map_err(|e| format!("failed to listen on {}: {}", addr, e))?;
+
+        let watchers = Arc::new(Mutex::new(Watchers::default()));
+        let accepting = Arc::clone(&watchers);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+
+                // every watcher gets their own thread so a slow one can't hold up the game
+                let (sender, lines) = mpsc::sync_channel::<String>(WATCHER_BACKLOG);
+                std::thread::spawn(move || {
+                    for line in lines {
+                        if writeln!(stream, "{}", line).is_err() {
+                            return;
+                        }
+                    }
+                });
+
+                let mut watchers = accepting.lock().unwrap();
+                let sent = match &watchers.board {
+                    Some(board) => sender.try_send(board.clone()).is_ok(),
+                    None => true,
+                };
+                if sent {
+                    watchers.senders.push(sender);
+                }
+            }
+        });
+
+        Ok(Server {
+            watchers,
+            status_timer: Timer::new(Duration::ZERO),
+            board: None,
+        })
+    }
+
+    // whether `level`'s board looks any different to watchers than the last one they were sent
+    fn board_changed(&self, level: &Level, board_over: bool) -> bool {
+        let grid = level.grid();
+        match &self.board {
+            Some(Message::Board {
+                level: number,
+                width,
+                height,
+                cells,
+            }) => {
+                *number != level.number()
+                    || (*width, *height) != (grid.width(), grid.height())
+                    || !cells
+                        .iter()
+                        .copied()
+                        .eq(grid.cells().iter().map(|cell| spectated(cell, board_over)))
+            }
+            _ => true,
+        }
+    }
+
+    // Sends watchers what's happened on `level` since the last frame. Called every frame.
+    pub fn update(&mut self, level: &Level, events: &[LevelEvent], game_over: Option<GameOver>) {
+        let board_changed = self.board_changed(level, game_over.is_some());
+        let mut watchers = self.watchers.lock().unwrap();
+        for event in events {
+            watchers.broadcast(&to_line(&Message::Event { event: *event }));
+        }
+
+        // the board only gets sent again when something on it changes
+        if board_changed {
+            let grid = level.grid();
+            let board = Message::Board {
+                level: level.number(),
+                width: grid.width(),
+                height: grid.height(),
+                cells: grid
+                    .cells()
+                    .iter()
+                    .map(|cell| spectated(cell, game_over.is_some()))
+                    .collect(),
+            };
+            let line = to_line(&board);
+            watchers.broadcast(&line);
+            watchers.board = Some(line);
+            self.board = Some(board);
+        }
+
+        if self.status_timer.finished() {
+            watchers.broadcast(&to_line(&Message::Status {
+                timer: TimerReadout::of(level),
+                confusion: level.confusion_state(),
+                game_over,
+            }));
+            self.status_timer = Timer::new(STATUS_INTERVAL);
+        }
+    }
+}
+
+pub struct Watcher {
+    addr: String,
+    messages: mpsc::Receiver<Message>,
+}
+
+impl Watcher {
+    pub fn connect(addr: &str) -> Result<Self, String> {
+        let stream = TcpStream::connect(addr)
+            .map_err(|e| format!("failed to connect to {}: {}", addr, e))?;
+
+        let (sender, messages) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stream).lines() {
+                let message = match line.map(|line| serde_json::from_str(&line)) {
+                    Ok(Ok(message)) => message,
+                    _ => return,
+                };
+                if sender.send(message).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(Watcher {
+            addr: String::from(addr),
+            messages,
+        })
+    }
+}
+
+// the game being watched, as of the last messages from it
+struct Watched {
+    level: usize,
+    grid: GameGrid,
+    camera: xform::Camera,
+    timer: Option<TimerReadout>,
+    confusion: Option<bool>,
+    game_over: Option<GameOverState>,
+    continues: bool,
+}
+
+impl Watched {
+    fn new(level: usize, grid: GameGrid) -> Self {
+        Watched {
+            level,
+            camera: xform::Camera::new(grid.width(), grid.height()),
+            grid,
+            timer: None,
+            confusion: None,
+            game_over: None,
+            continues: false,
+        }
+    }
+
+    // Takes in a message, returning true if the board needs laying out again. Boards that don't
+    // add up are dropped.
+    fn receive(&mut self, message: Message) -> bool {
+        match message {
+            Message::Board {
+                level,
+                width,
+                height,
+                cells,
+            } => {
+                let grid = match board_grid(width, height, cells) {
+                    Some(grid) => grid,
+                    None => return false,
+                };
+                let resized = (width, height) != (self.grid.width(), self.grid.height());
+                self.level = level;
+                self.grid = grid;
+                if resized {
+                    self.camera = xform::Camera::new(width, height);
+                }
+                resized
+            }
+            Message::Status {
+                timer,
+                confusion,
+                game_over,
+            } => {
+                self.timer = Some(timer);
+                self.confusion = confusion;
+                self.continues = game_over.is_some_and(|game_over| game_over.continues);
+                self.game_over = game_over.map(|game_over| GameOverState {
+                    result: game_over.result,
+                    msg_timer: Timer::new(game_over.time_left),
+                });
+                false
+            }
+            Message::Event { .. } => false,
+        }
+    }
+}
+
+// Shows the game being streamed until it's over or the watcher quits.
+pub fn run(
+    watcher: Watcher,
+    options: &crate::cli::Options,
+    accessibility: &config::Accessibility,
+    window: &pancurses::Window,
+    signals: &term::Signals,
+) {
+    let metrics = &options.cell_metrics;
+    let mut screen_rect = layout::screen_rect(window);
+    let mut watched: Option<Watched> = None;
+    let mut board_layout = None;
+    let mut ended = false;
+
+    while !signals.quit_requested() {
+        signals.handle_suspend(window);
+
+        loop {
+            let message = match watcher.messages.try_recv() {
+                Ok(message) => message,
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    ended = true;
+                    break;
+                }
+            };
+
+            let lay_out = match (&mut watched, message) {
+                (Some(watched), message) => watched.receive(message),
+                (
+                    None,
+                    Message::Board {
+                        level,
+                        width,
+                        height,
+                        cells,
+                    },
+                ) => {
+                    watched =
+                        board_grid(width, height, cells).map(|grid| Watched::new(level, grid));
+                    watched.is_some()
+                }
+                // nothing can be shown until the board comes in
+                (None, _) => false,
+            };
+            if let (true, Some(watched)) = (lay_out, &mut watched) {
+                board_layout = layout::camera_layout(&mut watched.camera, metrics, &screen_rect);
+            }
+        }
+
+        match crate::get_input_update(window) {
+            Some(InputUpdate::Key(_)) if ended => return,
+            Some(InputUpdate::Key('q')) => return,
+            Some(InputUpdate::Scroll(dx, dy)) => {
+                if let Some(watched) = &mut watched {
+                    watched.camera.scroll(dx, dy);
+                }
+            }
+            Some(InputUpdate::Resize) => {
+                screen_rect = layout::screen_rect(window);
+                if let Some(watched) = &mut watched {
+                    board_layout =
+                        layout::camera_layout(&mut watched.camera, metrics, &screen_rect);
+                }
+            }
+            _ => (),
+        }
+
+        window.erase();
+        match (&watched, &board_layout) {
+            (Some(watched), Some(board_layout)) => render_watched(
+                watched,
+                board_layout,
+                &screen_rect,
+                metrics,
+                accessibility,
+                window,
+            ),
+            (Some(watched), None) => {
+                let (min_width, min_height) = layout::min_viewport_size(&watched.camera);
+                let min_size = layout::min_screen_size(min_width, min_height, metrics);
+                crate::render_too_small_overlay(min_size, &screen_rect, window);
+            }
+            (None, _) => render_waiting(&watcher.addr, &screen_rect, window),
+        }
+        render_status(&watcher.addr, ended, &screen_rect, window);
+        term::present(window);
+
+        std::thread::sleep(Duration::from_millis(33));
+    }
+}
+
+// what's on screen until the first board comes in
+fn render_waiting<S: Surface>(addr: &str, screen_rect: &crate::Rect, surface: &S) {
+    let text = format!("Waiting for the game at {}...", addr);
+    surface.draw_text(
+        screen_rect.center_y(),
+        std::cmp::max(0, screen_rect.center_x() - (text.len() / 2) as i32),
+        text,
+    );
+}
+
+// the line along the bottom of the screen saying who's being watched and what the keys do
+fn render_status<S: Surface>(addr: &str, ended: bool, screen_rect: &crate::Rect, surface: &S) {
+    let status = if ended {
+        String::from("The game has ended. press any key to exit")
+    } else {
+        format!("Watching {}   arrows: scroll   q: quit", addr)
+    };
+    surface.draw_text(screen_rect.bottom() - 1, 0, status);
+}
+
+fn render_watched<S: Surface>(
+    watched: &Watched,
+    board_layout: &layout::BoardLayout,
+    screen_rect: &crate::Rect,
+    metrics: &xform::CellMetrics,
+    accessibility: &config::Accessibility,
+    surface: &S,
+) {
+    crate::render_level_header(watched.level, &board_layout.level, surface);
+    if let Some(timer) = &watched.timer {
+        crate::render_game_timer(timer, &board_layout.time, surface);
+    }
+
+    // nobody's pointing at anything, and the top left corner is never over the board
+    let mouse_state = MouseState {
+        click: false,
+        x: 0,
+        y: 0,
+    };
+    let board_view = crate::BoardView {
+        grid_rect: &board_layout.grid,
+        camera: &watched.camera,
+        metrics,
+        accessibility,
+    };
+    crate::render_game_board(
+        &watched.grid,
+        &watched.game_over,
+        watched.confusion,
+        &board_view,
+        surface,
+        &mouse_state,
+    );
+
+    if let Some((minimap_rect, cells_per_char)) =
+        layout::minimap_layout(&watched.camera, screen_rect)
+    {
+        crate::render_minimap(
+            &watched.grid,
+            &watched.camera,
+            &minimap_rect,
+            cells_per_char,
+            surface,
+        );
+    }
+
+    if let Some(game_over) = &watched.game_over {
+        crate::render_game_over_text(
+            game_over,
+            watched.continues,
+            surface,
+            &board_layout.game_over,
+        );
+    } else if watched.confusion.is_some() && accessibility.reduced_motion {
+        crate::render_confusion_text(surface, &board_layout.game_over);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::test_level;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn next_message(watcher: &Watcher) -> Message {
+        watcher
+            .messages
+            .recv_timeout(TIMEOUT)
+            .expect("no message from the game")
+    }
+
+    #[test]
+    fn test_watching_a_board() {
+        // borrow a free port from the OS
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .unwrap()
+            .to_string();
+        let mut server = Server::listen(&addr).unwrap();
+        let mut level = test_level(2, (6, 4));
+        server.update(&level, &[], None);
+
+        // watchers who show up late still get the board
+        let watcher = Watcher::connect(&addr).unwrap();
+        while server.watchers.lock().unwrap().senders.is_empty() {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let mut watched = match next_message(&watcher) {
+            Message::Board {
+                level,
+                width,
+                height,
+                cells,
+            } => Watched::new(level, board_grid(width, height, cells).unwrap()),
+            message => panic!("expected the board, got {:?}", message),
+        };
+        assert_eq!(2, watched.level);
+        // nothing hidden gets sent before the board's over
+        let (x, y) = level.grid().find_solution().unwrap();
+        assert_eq!(GridItem::Empty, watched.grid.cell(x, y).unwrap().item);
+
+        let events = level.reveal(x, y);
+        let game_over = Some(GameOver {
+            result: GameResult::Win,
+            continues: true,
+            time_left: Duration::from_secs(5),
+        });
+        // wait out the status timer from the first update so this one sends a status too
+        std::thread::sleep(STATUS_INTERVAL);
+        server.update(&level, &events, game_over);
+
+        assert_eq!(
+            Message::Event {
+                event: LevelEvent::Revealed {
+                    x,
+                    y,
+                    item: GridItem::Solution
+                }
+            },
+            next_message(&watcher)
+        );
+        assert_eq!(
+            Message::Event {
+                event: LevelEvent::Won
+            },
+            next_message(&watcher)
+        );
+        watched.receive(next_message(&watcher));
+        watched.receive(next_message(&watcher));
+        assert!(watched.grid.cell(x, y).unwrap().revealed);
+        assert_eq!(Some(1), watched.timer.map(|timer| timer.reveals));
+        assert_eq!(
+            Some(GameResult::Win),
+            watched.game_over.as_ref().map(|game_over| game_over.result)
+        );
+        assert!(watched.continues);
+    }
+
+    #[test]
+    fn test_waiting_for_the_game() {
+        let screen_rect = crate::Rect {
+            left: 0,
+            top: 0,
+            width: 50,
+            height: 5,
+        };
+        let buffer = crate::render::Buffer::new(screen_rect.width, screen_rect.height);
+        render_waiting("127.0.0.1:7777", &screen_rect, &buffer);
+        render_status("127.0.0.1:7777", false, &screen_rect, &buffer);
+        assert_eq!(
+            vec![
+                "",
+                "",
+                "     Waiting for the game at 127.0.0.1:7777...",
+                "Watching 127.0.0.1:7777   arrows: scroll   q: quit",
+            ],
+            buffer.text().lines().collect::<Vec<_>>()
+        );
+
+        let buffer = crate::render::Buffer::new(screen_rect.width, screen_rect.height);
+        render_status("127.0.0.1:7777", true, &screen_rect, &buffer);
+        assert_eq!(
+            "The game has ended. press any key to exit",
+            buffer.text().trim_start()
+        );
+    }
+
+    #[test]
+    fn test_slow_watchers_are_dropped() {
+        let mut watchers = Watchers::default();
+        let (sender, lines) = mpsc::sync_channel(WATCHER_BACKLOG);
+        watchers.senders.push(sender);
+        for _ in 0..WATCHER_BACKLOG {
+            watchers.broadcast("line");
+        }
+        assert_eq!(1, watchers.senders.len());
+
+        // one more than they've got room for
+        watchers.broadcast("line");
+        assert!(watchers.senders.is_empty());
+        assert_eq!(WATCHER_BACKLOG, lines.try_iter().count());
+    }
+
+    #[test]
+    fn test_bad_boards_are_dropped() {
+        let cell = Some(GridCell {
+            item: GridItem::Solution,
+            revealed: false,
+            seen: false,
+        });
+        assert!(board_grid(2, 1, vec![cell, None]).is_some());
+        assert!(board_grid(2, 2, vec![cell, None]).is_none());
+        assert!(board_grid(-2, -1, vec![cell, None]).is_none());
+        assert!(board_grid(0, 0, vec![]).is_none());
+        assert!(board_grid(i32::MAX, 2, vec![cell]).is_none());
+
+        let mut watched = Watched::new(1, board_grid(2, 1, vec![cell, None]).unwrap());
+        let bad_board = Message::Board {
+            level: 2,
+            width: 3,
+            height: 3,
+            cells: vec![cell],
+        };
+        assert!(!watched.receive(bad_board));
+        assert_eq!(
+            (1, 2, 1),
+            (watched.level, watched.grid.width(), watched.grid.height())
+        );
+    }
+
+    #[test]
+    fn test_messages_are_documented_json() {
+        let line = to_line(&Message::Event {
+            event: LevelEvent::Rehidden { x: 2, y: 6 },
+        });
+        assert_eq!(
+            r#"{"type":"event","event":{"event":"rehidden","x":2,"y":6}}"#,
+            line
+        );
+
+        let message: Message = serde_json::from_str(
+            r#"{"type": "board", "level": 1, "width": 2, "height": 1, "cells": [
+                {"item": {"kind": "hint", "value": "left"}, "revealed": true, "seen": true},
+                null]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            Message::Board {
+                level: 1,
+                width: 2,
+                height: 1,
+                cells: vec![
+                    Some(GridCell {
+                        item: GridItem::Hint(crate::game::HintDir::Left),
+                        revealed: true,
+                        seen: true
+                    }),
+                    None
+                ],
+            },
+            message
+        );
+    }
+}
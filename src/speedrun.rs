@@ -0,0 +1,363 @@
+// Speedrun mode, started with `--speedrun <LEVELS>`. Every run plays the same boards, levels 1 to
+// LEVELS from a fixed seed, as fast as possible. There's no pause between boards and losing one
+// ends the run.
+//
+// A split is the total run time when a level is finished. The live timer compares the run against
+// the personal best for the same number of levels, green while ahead and red while behind. A run
+// that finishes faster than the personal best replaces it in the speedrun file next to the config
+// file, which has a `<levels> = <split>, <split>, ...` line (in seconds) for every run length
+// that's been finished.
+
+use crate::config;
+use crate::render::Surface;
+use crate::theme::Style;
+use crate::utils::{Rect, SeededRng};
+use crate::xform::CellMetrics;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const SEED: u64 = 0x0105_7f0d;
+
+// Boards are sized for an 80x24 terminal no matter how big the real one is, so every run is on the
+// same boards. Bigger boards scroll.
+const SCREEN: Rect = Rect {
+    left: 0,
+    top: 0,
+    width: 80,
+    height: 24,
+};
+
+const SPLITS_WIDTH: i32 = 24;
+
+// the personal best splits for every run length
+pub type PersonalBests = BTreeMap<usize, Vec<Duration>>;
+
+pub struct Run {
+    pub levels: usize,
+    // the split for every level finished so far
+    pub splits: Vec<Duration>,
+    pub personal_best: Option<Vec<Duration>>,
+}
+
+impl Run {
+    pub fn new(levels: usize, personal_bests: &PersonalBests) -> Self {
+        Run {
+            levels,
+            splits: vec![],
+            personal_best: personal_bests.get(&levels).cloned(),
+        }
+    }
+
+    // the run time before the current level started
+    pub fn time_so_far(&self) -> Duration {
+        self.splits.last().copied().unwrap_or_default()
+    }
+
+    pub fn finish_level(&mut self, level_time: Duration) {
+        self.splits.push(self.time_so_far() + level_time);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.splits.len() == self.levels
+    }
+
+    // whether the level being played is the one that finishes the run
+    pub fn on_last_level(&self) -> bool {
+        self.splits.len() + 1 >= self.levels
+    }
+
+    pub fn is_personal_best(&self) -> bool {
+        if !self.is_finished() {
+            return false;
+        }
+
+        match (&self.personal_best, self.splits.last()) {
+            (Some(personal_best), Some(time)) => time < personal_best.last().unwrap(),
+            _ => true,
+        }
+    }
+
+    // How far ahead (negative) or behind (positive) of the personal best the run is, in seconds,
+    // with `time` on the clock at the end of the level at `level_index`.
+    pub fn delta(&self, level_index: usize, time: Duration) -> Option<f64> {
+        let personal_best = self.personal_best.as_ref()?.get(level_index)?;
+        Some(time.as_secs_f64() - personal_best.as_secs_f64())
+    }
+}
+
+pub fn max_grid_size() -> (i32, i32) {
+    crate::layout::max_grid_size(&CellMetrics::NORMAL, &SCREEN)
+}
+
+// every level gets its own rng so a level's board doesn't depend on how the levels before it went
+pub fn level_rng(level: usize) -> SeededRng {
+    SeededRng::new(SEED.wrapping_add((level as u64) << 32))
+}
+
+fn path() -> Option<PathBuf> {
+    config::dir().map(|dir| dir.join("speedrun"))
+}
+
+pub fn load_personal_bests() -> Result<PersonalBests, String> {
+    let path = match path() {
+        Some(path) => path,
+        None => return Ok(PersonalBests::new()),
+    };
+
+    match std::fs::read_to_string(&path) {
+        Ok(text) => parse(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(PersonalBests::new()),
+        Err(e) => Err(format!("failed to read {}: {}", path.display(), e)),
+    }
+}
+
+pub fn save_personal_bests(personal_bests: &PersonalBests) -> Result<(), String> {
+    let path =
+        path().ok_or_else(|| String::from("couldn't find a directory for the speedrun file"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+    }
+
+    std::fs::write(&path, to_text(personal_bests))
+        .map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+pub fn parse(text: &str) -> Result<PersonalBests, String> {
+    let mut personal_bests = PersonalBests::new();
+
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line_error = |e: String| format!("line {}: {}", line_index + 1, e);
+        let (levels, splits) = line.split_once('=').ok_or_else(|| {
+            line_error(format!(
+                "expected <levels> = <split>, <split>, ..., got '{}'",
+                line
+            ))
+        })?;
+
+        let levels = match levels.trim().parse::<usize>() {
+            Ok(levels) if levels > 0 => levels,
+            _ => {
+                return Err(line_error(format!(
+                    "invalid level count '{}' (expected a positive number)",
+                    levels.trim()
+                )))
+            }
+        };
+
+        let splits = splits
+            .split(',')
+            .map(|split| config::parse_seconds(split.trim()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(line_error)?;
+        if splits.len() != levels {
+            return Err(line_error(format!(
+                "expected {} splits, got {}",
+                levels,
+                splits.len()
+            )));
+        }
+
+        personal_bests.insert(levels, splits);
+    }
+
+    Ok(personal_bests)
+}
+
+pub fn to_text(personal_bests: &PersonalBests) -> String {
+    let mut text = String::from("# personal best splits in seconds, by how many levels were run\n");
+    for (levels, splits) in personal_bests {
+        let splits: Vec<String> = splits
+            .iter()
+            .map(|split| split.as_secs_f64().to_string())
+            .collect();
+        text += &format!("{} = {}\n", levels, splits.join(", "));
+    }
+    text
+}
+
+// i.e. 01:23.456
+pub fn format_time(time: Duration) -> String {
+    format!(
+        "{:02}:{:02}.{:03}",
+        time.as_secs() / 60,
+        time.as_secs() % 60,
+        time.subsec_millis()
+    )
+}
+
+fn format_delta(delta: f64) -> String {
+    format!("{:+.3}", delta)
+}
+
+// the splits table that's printed once the run is over
+pub fn summary(run: &Run) -> String {
+    let mut text = String::new();
+    for (level_index, split) in run.splits.iter().enumerate() {
+        text += &format!("level {:>2}  {}", level_index + 1, format_time(*split));
+        if let Some(delta) = run.delta(level_index, *split) {
+            text += &format!("  {}", format_delta(delta));
+        }
+        text.push('\n');
+    }
+
+    if run.is_personal_best() {
+        text += "new personal best!\n";
+    } else if !run.is_finished() {
+        text += &format!(
+            "run ended on level {} of {}\n",
+            run.splits.len() + 1,
+            run.levels
+        );
+    }
+    text
+}
+
+// Draws the run timer under the board timer, plus the splits so far along the right side of the
+// screen if there's room for them next to the board.
+pub fn render<S: Surface>(
+    run: &Run,
+    level_time: Duration,
+    time_rect: &Rect,
+    grid_rect: &Rect,
+    screen_rect: &Rect,
+    surface: &S,
+) {
+    let run_time = run.time_so_far() + level_time;
+    let current_level = run.splits.len();
+    let run_text = format!("Run: {}", format_time(run_time));
+    surface.draw_text(time_rect.top + 1, time_rect.left, &run_text);
+    render_delta(
+        run.delta(current_level, run_time),
+        time_rect.top + 1,
+        time_rect.left + run_text.len() as i32 + 1,
+        surface,
+    );
+
+    // leave the top rows for the minimap
+    let splits_left = screen_rect.right() - SPLITS_WIDTH;
+    if splits_left < grid_rect.right() + 2 {
+        return;
+    }
+
+    let splits_top = screen_rect.top + 5;
+    let shown_levels = std::cmp::min(run.levels, (screen_rect.bottom() - splits_top) as usize);
+    // keep the current level in view on long runs
+    let first_level = (current_level + 1).saturating_sub(shown_levels);
+    for level_index in first_level..first_level + shown_levels {
+        let top = splits_top + (level_index - first_level) as i32;
+        let split = run.splits.get(level_index).copied();
+        let time = match split {
+            Some(split) => format_time(split),
+            None if level_index == current_level => format_time(run_time),
+            None => String::from("--:--.---"),
+        };
+        surface.draw_text(top, splits_left, format!("{:>2} {}", level_index + 1, time));
+
+        // only show how the current level is going once it's falling behind, like most split timers
+        let delta = match split {
+            Some(split) => run.delta(level_index, split),
+            None if level_index == current_level => run
+                .delta(level_index, run_time)
+                .filter(|delta| *delta > 0.0),
+            None => None,
+        };
+        render_delta(delta, top, splits_left + 13, surface);
+    }
+}
+
+fn render_delta<S: Surface>(delta: Option<f64>, top: i32, left: i32, surface: &S) {
+    let delta = match delta {
+        Some(delta) => delta,
+        None => return,
+    };
+
+    let attributes = if delta <= 0.0 {
+        Style::Success.attributes()
+    } else {
+        Style::Alert.attributes()
+    };
+    surface.attributes_on(attributes);
+    surface.draw_text(top, left, format_delta(delta));
+    surface.attributes_off(attributes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_splits() {
+        let mut personal_bests = PersonalBests::new();
+        personal_bests.insert(3, vec![secs(5), secs(12), secs(20)]);
+
+        let mut run = Run::new(3, &personal_bests);
+        assert!(!run.on_last_level());
+        run.finish_level(secs(4));
+        run.finish_level(secs(9));
+        assert_eq!(vec![secs(4), secs(13)], run.splits);
+        assert_eq!(secs(13), run.time_so_far());
+        assert_eq!(Some(-1.0), run.delta(0, run.splits[0]));
+        assert_eq!(Some(1.0), run.delta(1, run.splits[1]));
+        assert!(!run.is_finished());
+        assert!(run.on_last_level());
+        assert!(!run.is_personal_best());
+
+        run.finish_level(secs(6));
+        assert!(run.is_finished());
+        assert!(run.is_personal_best());
+        assert!(summary(&run).ends_with("new personal best!\n"));
+
+        // the first finished run is always the best so far
+        let mut run = Run::new(1, &personal_bests);
+        assert_eq!(None, run.delta(0, secs(1)));
+        run.finish_level(secs(60));
+        assert!(run.is_personal_best());
+    }
+
+    #[test]
+    fn test_personal_bests_round_trip() {
+        let mut personal_bests = PersonalBests::new();
+        personal_bests.insert(2, vec![Duration::from_millis(4500), secs(11)]);
+        personal_bests.insert(10, (1..=10).map(|level| secs(level * 7)).collect());
+        assert_eq!(Ok(personal_bests.clone()), parse(&to_text(&personal_bests)));
+
+        assert_eq!(
+            Err(String::from("line 1: expected 3 splits, got 2")),
+            parse("3 = 1, 2")
+        );
+        assert!(parse("0 = ").is_err());
+        assert!(parse("2 = 1, soon").is_err());
+    }
+
+    #[test]
+    fn test_boards_are_the_same_every_run() {
+        use snm_rand_utils::range_rng::RangeRng;
+
+        let rolls = |level| {
+            let mut rng = level_rng(level);
+            (0..10)
+                .map(|_| rng.gen_range(0, 100))
+                .collect::<Vec<usize>>()
+        };
+        assert_eq!(rolls(3), rolls(3));
+        assert_ne!(rolls(3), rolls(4));
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!("01:23.456", format_time(Duration::from_millis(83456)));
+        assert_eq!("+1.500", format_delta(1.5));
+        assert_eq!("-0.250", format_delta(-0.25));
+    }
+}
@@ -0,0 +1,314 @@
+// Color themes for lnf. Everything on screen is drawn with one of a handful of styles and the active
+// theme decides what each of those styles looks like.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Theme {
+    #[default]
+    Classic,
+    Colorblind,
+    HighContrast,
+    // no colors at all. Everything is told apart by its glyph and text attributes.
+    Monochrome,
+}
+
+impl std::str::FromStr for Theme {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "classic" => Ok(Theme::Classic),
+            "colorblind" => Ok(Theme::Colorblind),
+            "high-contrast" => Ok(Theme::HighContrast),
+            "monochrome" => Ok(Theme::Monochrome),
+            _ => Err(format!(
+                "invalid theme '{}' (expected classic, colorblind, high-contrast, or monochrome)",
+                value
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Theme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Theme::Classic => "classic",
+            Theme::Colorblind => "colorblind",
+            Theme::HighContrast => "high-contrast",
+            Theme::Monochrome => "monochrome",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    HintLeft,
+    HintRight,
+    HintUp,
+    HintDown,
+    Trap,
+    Empty,
+    Hidden,
+    Border,
+    Highlight,
+    Alert,
+    Success,
+}
+
+const ALL_STYLES: [Style; 11] = [
+    Style::HintLeft,
+    Style::HintRight,
+    Style::HintUp,
+    Style::HintDown,
+    Style::Trap,
+    Style::Empty,
+    Style::Hidden,
+    Style::Border,
+    Style::Highlight,
+    Style::Alert,
+    Style::Success,
+];
+
+// an rgb color where each channel goes from 0 to 1000
+pub type Rgb = (i16, i16, i16);
+
+// A color the way a terminal other than the one being drawn on would need it, i.e. in a recording.
+// Standard colors are the curses color numbers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    Standard(i16),
+    Rgb(Rgb),
+}
+
+// The colors for one style. `fg` and `bg` are standard curses colors that every color terminal
+// has. If the terminal lets us redefine colors, `custom` swaps in a nicer rgb background along with
+// the foreground that reads best on top of it.
+struct PairSpec {
+    fg: i16,
+    bg: i16,
+    custom: Option<(i16, Rgb)>,
+}
+
+const fn standard(fg: i16, bg: i16) -> PairSpec {
+    PairSpec {
+        fg,
+        bg,
+        custom: None,
+    }
+}
+
+const fn custom(custom_fg: i16, rgb: Rgb, fg: i16, bg: i16) -> PairSpec {
+    PairSpec {
+        fg,
+        bg,
+        custom: Some((custom_fg, rgb)),
+    }
+}
+
+const BLACK: i16 = pancurses::COLOR_BLACK;
+const WHITE: i16 = pancurses::COLOR_WHITE;
+
+const GRAY: PairSpec = custom(BLACK, (500, 500, 500), BLACK, WHITE);
+const DARK_GRAY: PairSpec = custom(BLACK, (250, 250, 250), WHITE, BLACK);
+
+// custom colors are defined past the 16 standard ones so the terminal's own palette is left alone
+const FIRST_CUSTOM_COLOR: i16 = 16;
+
+// set once by setup and read back whenever something gets drawn
+static ACTIVE_THEME: AtomicU8 = AtomicU8::new(0);
+
+impl Theme {
+    const ALL: [Theme; 4] = [
+        Theme::Classic,
+        Theme::Colorblind,
+        Theme::HighContrast,
+        Theme::Monochrome,
+    ];
+
+    fn pair_spec(self, style: Style) -> PairSpec {
+        match self {
+            Theme::Classic => match style {
+                Style::HintLeft => custom(BLACK, (500, 500, 1000), BLACK, pancurses::COLOR_BLUE),
+                Style::HintRight => custom(BLACK, (750, 750, 500), BLACK, pancurses::COLOR_YELLOW),
+                Style::HintUp | Style::Alert => {
+                    custom(BLACK, (1000, 500, 500), BLACK, pancurses::COLOR_RED)
+                }
+                Style::HintDown | Style::Success => {
+                    custom(BLACK, (500, 1000, 500), BLACK, pancurses::COLOR_GREEN)
+                }
+                Style::Trap => custom(BLACK, (750, 450, 0), BLACK, pancurses::COLOR_MAGENTA),
+                Style::Empty => GRAY,
+                Style::Hidden | Style::Border => DARK_GRAY,
+                Style::Highlight => standard(BLACK, WHITE),
+            },
+            // Hint colors come from the Okabe-Ito palette, which stays distinguishable under the
+            // common kinds of color blindness.
+            Theme::Colorblind => match style {
+                Style::HintLeft => custom(BLACK, (337, 706, 914), BLACK, pancurses::COLOR_CYAN),
+                Style::HintRight => custom(BLACK, (941, 894, 259), BLACK, pancurses::COLOR_YELLOW),
+                Style::HintUp => custom(BLACK, (800, 475, 655), BLACK, pancurses::COLOR_MAGENTA),
+                Style::HintDown | Style::Success => {
+                    custom(BLACK, (0, 620, 451), BLACK, pancurses::COLOR_GREEN)
+                }
+                Style::Trap | Style::Alert => {
+                    custom(BLACK, (835, 369, 0), BLACK, pancurses::COLOR_RED)
+                }
+                Style::Empty => GRAY,
+                Style::Hidden | Style::Border => DARK_GRAY,
+                Style::Highlight => standard(BLACK, WHITE),
+            },
+            // only standard colors so the palette looks the same on every terminal
+            Theme::HighContrast => match style {
+                Style::HintLeft => standard(BLACK, pancurses::COLOR_CYAN),
+                Style::HintRight => standard(BLACK, pancurses::COLOR_YELLOW),
+                Style::HintUp | Style::Alert => standard(WHITE, pancurses::COLOR_RED),
+                Style::HintDown | Style::Success => standard(BLACK, pancurses::COLOR_GREEN),
+                Style::Trap => standard(WHITE, pancurses::COLOR_MAGENTA),
+                Style::Empty => standard(BLACK, WHITE),
+                Style::Hidden | Style::Border => standard(WHITE, BLACK),
+                Style::Highlight => standard(WHITE, pancurses::COLOR_BLUE),
+            },
+            Theme::Monochrome => standard(WHITE, BLACK),
+        }
+    }
+
+    // attributes to draw the style with on top of its color pair
+    fn extra_attributes(self, style: Style) -> pancurses::chtype {
+        match self {
+            Theme::Classic | Theme::Colorblind => pancurses::A_NORMAL,
+            Theme::HighContrast => pancurses::A_BOLD,
+            // Hidden cells are drawn as solid blocks so they can't be mistaken for revealed empty
+            // cells. Everything else is either plain or bold, which leaves reverse video free for
+            // things like the minimap's viewport.
+            Theme::Monochrome => match style {
+                Style::HintLeft
+                | Style::HintRight
+                | Style::HintUp
+                | Style::HintDown
+                | Style::Trap
+                | Style::Highlight => pancurses::A_BOLD,
+                Style::Empty | Style::Border => pancurses::A_NORMAL,
+                Style::Hidden | Style::Success => pancurses::A_REVERSE,
+                Style::Alert => pancurses::A_REVERSE | pancurses::A_BOLD,
+            },
+        }
+    }
+}
+
+impl Style {
+    fn pair_num(self) -> i16 {
+        self as i16 + 1
+    }
+
+    // the style drawn with color pair `pair_num`, if any
+    pub fn from_pair_num(pair_num: i16) -> Option<Style> {
+        ALL_STYLES
+            .iter()
+            .copied()
+            .find(|style| style.pair_num() == pair_num)
+    }
+
+    // The foreground and background the active theme draws the style with. These are the colors
+    // the theme asks for, even if the terminal couldn't show the custom ones.
+    pub fn colors(self) -> (Color, Color) {
+        let pair_spec = active_theme().pair_spec(self);
+        match pair_spec.custom {
+            Some((fg, rgb)) => (Color::Standard(fg), Color::Rgb(rgb)),
+            None => (Color::Standard(pair_spec.fg), Color::Standard(pair_spec.bg)),
+        }
+    }
+
+    pub fn attributes(self) -> pancurses::chtype {
+        let theme = active_theme();
+        let color_pair = if theme == Theme::Monochrome {
+            0
+        } else {
+            pancurses::COLOR_PAIR(self.pair_num() as pancurses::chtype)
+        };
+
+        color_pair | theme.extra_attributes(self)
+    }
+}
+
+fn active_theme() -> Theme {
+    Theme::ALL[ACTIVE_THEME.load(Ordering::Relaxed) as usize]
+}
+
+// Sets up the color pairs for a theme and makes it the active one. Terminals without color support
+// always get the monochrome theme. Returns the theme that ended up active.
+pub fn setup(theme: Theme) -> Theme {
+    let theme = if pancurses::has_colors() {
+        theme
+    } else {
+        Theme::Monochrome
+    };
+
+    if theme != Theme::Monochrome {
+        pancurses::start_color();
+
+        let can_change_color = pancurses::can_change_color();
+        for (i, style) in ALL_STYLES.iter().enumerate() {
+            let pair_spec = theme.pair_spec(*style);
+            let custom_color = FIRST_CUSTOM_COLOR + i as i16;
+
+            let (fg, bg) = match pair_spec.custom {
+                Some((custom_fg, (r, g, b)))
+                    if can_change_color && i32::from(custom_color) < pancurses::COLORS() =>
+                {
+                    pancurses::init_color(custom_color, r, g, b);
+                    (custom_fg, custom_color)
+                }
+                _ => (pair_spec.fg, pair_spec.bg),
+            };
+
+            pancurses::init_pair(style.pair_num(), fg, bg);
+        }
+    }
+
+    let theme_index = Theme::ALL.iter().position(|t| *t == theme).unwrap();
+    ACTIVE_THEME.store(theme_index as u8, Ordering::Relaxed);
+    theme
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_theme() {
+        assert_eq!(Ok(Theme::Classic), "classic".parse());
+        assert_eq!(Ok(Theme::Colorblind), "colorblind".parse());
+        assert_eq!(Ok(Theme::HighContrast), "high-contrast".parse());
+        assert_eq!(Ok(Theme::Monochrome), "monochrome".parse());
+        assert!("rainbow".parse::<Theme>().is_err());
+    }
+
+    #[test]
+    fn test_styles_round_trip_through_pair_nums() {
+        for style in ALL_STYLES {
+            assert_eq!(Some(style), Style::from_pair_num(style.pair_num()));
+        }
+        assert_eq!(None, Style::from_pair_num(0));
+    }
+
+    #[test]
+    fn test_colorblind_hints_are_distinct() {
+        let hints = [
+            Style::HintLeft,
+            Style::HintRight,
+            Style::HintUp,
+            Style::HintDown,
+        ];
+
+        for (i, a) in hints.iter().enumerate() {
+            for b in &hints[i + 1..] {
+                let a = Theme::Colorblind.pair_spec(*a);
+                let b = Theme::Colorblind.pair_spec(*b);
+                assert_ne!(a.bg, b.bg);
+                assert_ne!(a.custom.map(|c| c.1), b.custom.map(|c| c.1));
+            }
+        }
+    }
+}
@@ -0,0 +1,276 @@
+// Split-screen versus, started with `--versus <CONTROLS>`, races two players side by side on two
+// copies of the same board. CONTROLS says how each player plays, left board first, i.e.
+// `--versus wasd,arrows`:
+//
+//     mouse    point and click
+//     arrows   move with the arrow keys and reveal with enter
+//     wasd     move with w a s d and reveal with space
+//
+// Whoever finds their hidden cell first wins the round and the boards get harder every round like
+// they do in the regular game. If time runs out on both boards nobody wins the round. The first
+// player to win ROUNDS_TO_WIN rounds wins the match.
+
+use crate::render::Surface;
+use crate::theme::Style;
+use crate::utils::Rect;
+use crate::{cli, config, hot_seat, layout, term, GameResult, Pilot, PlayerBoard, RunState};
+use snm_rand_utils::range_rng::ThreadRangeRng;
+
+const ROUNDS_TO_WIN: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Controls {
+    Mouse,
+    Arrows,
+    Wasd,
+}
+
+impl std::str::FromStr for Controls {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "mouse" => Ok(Controls::Mouse),
+            "arrows" => Ok(Controls::Arrows),
+            "wasd" => Ok(Controls::Wasd),
+            _ => Err(format!(
+                "invalid controls '{}' (expected mouse, arrows or wasd)",
+                value
+            )),
+        }
+    }
+}
+
+impl Controls {
+    pub fn name(self) -> &'static str {
+        match self {
+            Controls::Mouse => "mouse",
+            Controls::Arrows => "arrows",
+            Controls::Wasd => "wasd",
+        }
+    }
+
+    // how far a key moves the cursor. The arrow keys come in as scrolling instead (see
+    // get_input_update).
+    pub fn cursor_step(self, c: char) -> Option<(i32, i32)> {
+        match (self, c) {
+            (Controls::Wasd, 'w') => Some((0, -1)),
+            (Controls::Wasd, 'a') => Some((-1, 0)),
+            (Controls::Wasd, 's') => Some((0, 1)),
+            (Controls::Wasd, 'd') => Some((1, 0)),
+            _ => None,
+        }
+    }
+
+    pub fn is_reveal_key(self, c: char) -> bool {
+        matches!((self, c), (Controls::Arrows, '\n') | (Controls::Wasd, ' '))
+    }
+}
+
+// Parses a pair of controls like `wasd,arrows`. Two players can't share the same controls.
+pub fn parse_controls(value: &str) -> Result<[Controls; 2], String> {
+    let controls = value
+        .split(',')
+        .map(|controls| controls.trim().parse())
+        .collect::<Result<Vec<Controls>, _>>()?;
+
+    match controls.as_slice() {
+        [first, second] if first == second => Err(format!(
+            "both players can't use the same controls ('{}')",
+            value
+        )),
+        [first, second] => Ok([*first, *second]),
+        _ => Err(format!(
+            "expected controls for two players like wasd,arrows, got '{}'",
+            value
+        )),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub controls: [Controls; 2],
+    pub wins: [usize; 2],
+    // the round being played, which is also its level
+    pub round: usize,
+}
+
+impl Match {
+    pub fn new(controls: [Controls; 2]) -> Self {
+        Match {
+            controls,
+            wins: [0, 0],
+            round: 1,
+        }
+    }
+
+    // Scores a round that `winner` won, or nobody did. Returns false once the match is over.
+    pub fn finish_round(&mut self, winner: Option<usize>) -> bool {
+        if let Some(winner) = winner {
+            self.wins[winner] += 1;
+        }
+        self.round += 1;
+        self.wins.iter().all(|wins| *wins < ROUNDS_TO_WIN)
+    }
+
+    pub fn continues_after(&self, winner: Option<usize>) -> bool {
+        self.clone().finish_round(winner)
+    }
+
+    // one line per player, then who won
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines: Vec<String> = (0..self.controls.len())
+            .map(|player| {
+                let rounds = if self.wins[player] == 1 {
+                    "round"
+                } else {
+                    "rounds"
+                };
+                format!(
+                    "{}: {} {} won",
+                    self.name(player),
+                    self.wins[player],
+                    rounds
+                )
+            })
+            .collect();
+
+        lines.push(match self.wins[0].cmp(&self.wins[1]) {
+            std::cmp::Ordering::Greater => format!("{} wins!", self.name(0)),
+            std::cmp::Ordering::Less => format!("{} wins!", self.name(1)),
+            std::cmp::Ordering::Equal => String::from("It's a tie!"),
+        });
+        lines
+    }
+
+    fn name(&self, player: usize) -> String {
+        format!("Player {} ({})", player + 1, self.controls[player].name())
+    }
+}
+
+// Plays rounds until someone wins the match or the game is quit, then shows how it went. Returns
+// the final scores so they can be printed once the terminal is handed back.
+pub fn run(
+    controls: [Controls; 2],
+    options: &cli::Options,
+    accessibility: &config::Accessibility,
+    window: &pancurses::Window,
+    signals: &term::Signals,
+) -> Vec<String> {
+    let mut versus = Match::new(controls);
+    let mut rng = ThreadRangeRng::new();
+
+    loop {
+        // The board gets rolled once, sized to fit the narrower half of the screen, and both
+        // players start fresh on copies of it so neither clock starts before the other.
+        let screen_rect = layout::screen_rect(window);
+        let max_grid_size = layout::split_screen(&screen_rect, controls.len())
+            .iter()
+            .map(|area| layout::max_grid_size(&options.cell_metrics, area))
+            .min()
+            .unwrap();
+        let level = crate::new_level(
+            versus.round,
+            options,
+            accessibility,
+            max_grid_size,
+            None,
+            &mut rng,
+        );
+        let mut boards = [
+            PlayerBoard::new(level.restart(), controls[0]),
+            PlayerBoard::new(level.restart(), controls[1]),
+        ];
+
+        let mut run = RunState {
+            versus: Some(versus.clone()),
+            ..RunState::default()
+        };
+        let outcomes = crate::play_boards(
+            &mut boards,
+            Pilot::Player,
+            &mut run,
+            options,
+            accessibility,
+            window,
+            signals,
+        );
+        if signals.quit_requested() {
+            break;
+        }
+
+        let winner = outcomes
+            .iter()
+            .position(|outcome| outcome.result == GameResult::Win);
+        if !versus.finish_round(winner) {
+            break;
+        }
+    }
+
+    let summary = versus.summary();
+    if !signals.quit_requested() {
+        hot_seat::run_results_screen(&summary, window, signals);
+    }
+    summary
+}
+
+// Draws who's playing the board and how many rounds they've won under the board timer.
+pub fn render_player<S: Surface>(versus: &Match, player: usize, time_rect: &Rect, surface: &S) {
+    let attributes = Style::Highlight.attributes();
+    surface.attributes_on(attributes);
+    surface.draw_text(time_rect.top + 1, time_rect.left, versus.name(player));
+    surface.attributes_off(attributes);
+    surface.append_text(format!("  wins: {}", versus.wins[player]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_controls() {
+        assert_eq!(
+            Ok([Controls::Wasd, Controls::Arrows]),
+            parse_controls("wasd,arrows")
+        );
+        assert_eq!(
+            Ok([Controls::Mouse, Controls::Wasd]),
+            parse_controls("mouse, wasd")
+        );
+        assert!(parse_controls("mouse").is_err());
+        assert!(parse_controls("mouse,arrows,wasd").is_err());
+        assert!(parse_controls("arrows,arrows").is_err());
+        assert!(parse_controls("wasd,joystick").is_err());
+    }
+
+    #[test]
+    fn test_keys() {
+        assert_eq!(Some((-1, 0)), Controls::Wasd.cursor_step('a'));
+        assert_eq!(None, Controls::Arrows.cursor_step('a'));
+        assert!(Controls::Wasd.is_reveal_key(' '));
+        assert!(Controls::Arrows.is_reveal_key('\n'));
+        assert!(!Controls::Mouse.is_reveal_key(' '));
+    }
+
+    #[test]
+    fn test_first_to_three_rounds() {
+        let mut versus = Match::new([Controls::Mouse, Controls::Arrows]);
+        assert!(versus.finish_round(Some(1)));
+        assert!(versus.finish_round(None));
+        assert!(versus.finish_round(Some(0)));
+        assert!(versus.finish_round(Some(1)));
+        assert_eq!(5, versus.round);
+        assert!(!versus.continues_after(Some(1)));
+        assert!(versus.continues_after(Some(0)));
+
+        assert!(!versus.finish_round(Some(1)));
+        assert_eq!(
+            vec![
+                "Player 1 (mouse): 1 round won",
+                "Player 2 (arrows): 3 rounds won",
+                "Player 2 (arrows) wins!"
+            ],
+            versus.summary()
+        );
+    }
+}